@@ -0,0 +1,504 @@
+use ray_tracing::{Color, Scene, ToneMapping, Vec3};
+use std::str::FromStr;
+
+pub const USAGE: &str = "Usage: ray-tracing [OPTIONS] [SCENE]
+
+Renders one of the built-in scenes (default: object-loader) or a scene file.
+
+Options:
+  -l, --list-scenes          List the built-in scenes and exit
+  -s, --scene <NAME>         Scene to render
+  -f, --file <FILE>          Scene file to render instead of a built-in scene
+      --obj <FILE>           OBJ file used by the object-loader scene [default: head.obj]
+  -o, --output <FILE>        Output image path, .hdr, .pfm and .exr keep the linear light
+      --save-scene <FILE>    Write the scene to a scene file instead of rendering it
+      --width <PIXELS>       Image width
+      --height <PIXELS>      Image height
+      --aspect-ratio <RATIO> Aspect ratio used to derive the missing width or height
+      --samples <N>          Samples per pixel
+      --depth <N>            Recursive depth of each ray
+      --diffuse-depth <N>    Most diffuse bounces of each ray
+      --glossy-depth <N>     Most glossy bounces of each ray
+      --transmission-depth <N>
+                             Most bounces through glass of each ray
+      --roulette <N>         Stop paths at random after N bounces (Russian roulette)
+      --threads <N>          Number of render threads
+      --leaf-size <N>        Maximum number of objects per bounding box tree leaf
+      --background <R,G,B>   Background color, bright skies can go above 1
+      --exposure <EV>        Brightens 8 bit outputs by this many stops, negative darkens
+      --tone-map <NAME>      Tone mapping of 8 bit outputs: clamp, reinhard,
+                             extended_reinhard, aces or hable
+      --white <L>            Luminance extended_reinhard maps to white
+      --dither <true|false>  Dither 8 bit outputs against banding
+      --look-from <X,Y,Z>    Camera position
+      --look-at <X,Y,Z>      Point the camera looks at
+      --vup <X,Y,Z>          Camera up vector
+      --vfov <DEGREES>       Vertical field of view
+      --aperture <F>         Lens aperture
+      --focus-dist <F>       Focus distance
+      --shutter <OPEN,CLOSE> Shutter interval, moving objects are blurred over it
+  -h, --help                 Print this help and exit";
+
+pub enum Command {
+    Help,
+    ListScenes,
+    Render(Box<RenderArgs>),
+}
+
+// Everything left as None keeps the value chosen by the scene.
+pub struct RenderArgs {
+    pub scene: String,
+    pub scene_file: Option<String>,
+    pub obj_file: String,
+    pub save_scene: Option<String>,
+    pub output: Option<String>,
+    pub img_width: Option<u32>,
+    pub img_height: Option<u32>,
+    pub aspect_ratio: Option<f64>,
+    pub samples_per_pixel: Option<u32>,
+    pub recursive_depth: Option<u32>,
+    pub diffuse_depth: Option<u32>,
+    pub glossy_depth: Option<u32>,
+    pub transmission_depth: Option<u32>,
+    pub russian_roulette: Option<u32>,
+    pub num_threads: Option<u32>,
+    pub bvh_leaf_size: Option<u32>,
+    pub background: Option<Color>,
+    pub exposure: Option<f64>,
+    pub tone_mapping: Option<ToneMapping>,
+    pub dither: Option<bool>,
+    pub look_from: Option<Vec3>,
+    pub look_at: Option<Vec3>,
+    pub vup: Option<Vec3>,
+    pub vertical_fov: Option<f64>,
+    pub aperture: Option<f64>,
+    pub focus_dist: Option<f64>,
+    pub shutter: Option<(f64, f64)>,
+}
+
+impl RenderArgs {
+    pub fn new(scene: &str) -> RenderArgs {
+        RenderArgs {
+            scene: String::from(scene),
+            scene_file: None,
+            obj_file: String::from("head.obj"),
+            save_scene: None,
+            output: None,
+            img_width: None,
+            img_height: None,
+            aspect_ratio: None,
+            samples_per_pixel: None,
+            recursive_depth: None,
+            diffuse_depth: None,
+            glossy_depth: None,
+            transmission_depth: None,
+            russian_roulette: None,
+            num_threads: None,
+            bvh_leaf_size: None,
+            background: None,
+            exposure: None,
+            tone_mapping: None,
+            dither: None,
+            look_from: None,
+            look_at: None,
+            vup: None,
+            vertical_fov: None,
+            aperture: None,
+            focus_dist: None,
+            shutter: None,
+        }
+    }
+
+    // Fails when the resolution left by the overrides has no pixels.
+    pub fn apply(&self, scene: &mut Scene) -> Result<(), String> {
+        let settings = &mut scene.settings;
+        settings.resize(self.img_width, self.img_height, self.aspect_ratio)?;
+
+        if let Some(samples_per_pixel) = self.samples_per_pixel {
+            settings.samples_per_pixel = samples_per_pixel;
+        }
+        if let Some(recursive_depth) = self.recursive_depth {
+            settings.recursive_depth = recursive_depth;
+        }
+        if let Some(depth) = self.diffuse_depth {
+            settings.diffuse_depth = Some(depth);
+        }
+        if let Some(depth) = self.glossy_depth {
+            settings.glossy_depth = Some(depth);
+        }
+        if let Some(depth) = self.transmission_depth {
+            settings.transmission_depth = Some(depth);
+        }
+        if let Some(min_bounces) = self.russian_roulette {
+            settings.russian_roulette = Some(min_bounces);
+        }
+        if let Some(num_threads) = self.num_threads {
+            settings.num_threads = num_threads;
+        }
+        if let Some(bvh_leaf_size) = self.bvh_leaf_size {
+            settings.bvh_leaf_size = bvh_leaf_size;
+        }
+        if let Some(background) = self.background {
+            settings.background = background;
+        }
+        if let Some(exposure) = self.exposure {
+            settings.display.exposure = exposure;
+        }
+        if let Some(tone_mapping) = self.tone_mapping {
+            settings.display.tone_mapping = tone_mapping;
+        }
+        if let Some(dither) = self.dither {
+            settings.display.dither = dither;
+        }
+        if let Some(output) = &self.output {
+            settings.output = output.clone();
+        }
+
+        let camera = &mut scene.camera;
+        if let Some(look_from) = self.look_from {
+            camera.look_from = look_from;
+        }
+        if let Some(look_at) = self.look_at {
+            camera.look_at = look_at;
+        }
+        if let Some(vup) = self.vup {
+            camera.vup = vup;
+        }
+        if let Some(vertical_fov) = self.vertical_fov {
+            camera.vertical_fov = vertical_fov;
+        }
+        if let Some(aperture) = self.aperture {
+            camera.aperture = aperture;
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            camera.focus_dist = focus_dist;
+        }
+        if let Some((shutter_open, shutter_close)) = self.shutter {
+            camera.shutter_open = shutter_open;
+            camera.shutter_close = shutter_close;
+        }
+        Ok(())
+    }
+}
+
+// Parses the arguments that follow the program name.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+    let mut render_args = RenderArgs::new("object-loader");
+    let mut scene: Option<String> = None;
+    // The white point can come before or after the tone mapping it belongs to.
+    let mut tone_map: Option<String> = None;
+    let mut white: Option<f64> = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let flag = arg.as_str();
+        match flag {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-l" | "--list-scenes" => return Ok(Command::ListScenes),
+            _ if !flag.starts_with('-') => {
+                if scene.is_some() {
+                    return Err(format!("Unexpected argument '{}'", flag));
+                }
+                scene = Some(String::from(flag));
+                continue;
+            }
+            _ => {}
+        }
+
+        let value = args
+            .next()
+            .ok_or_else(|| format!("Missing value for '{}'", flag))?;
+        match flag {
+            "-s" | "--scene" => {
+                if scene.is_some() {
+                    return Err(format!("Unexpected argument '{}'", flag));
+                }
+                scene = Some(value.clone());
+            }
+            "-f" | "--file" => render_args.scene_file = Some(value.clone()),
+            "--obj" => render_args.obj_file = value.clone(),
+            "-o" | "--output" => render_args.output = Some(value.clone()),
+            "--save-scene" => render_args.save_scene = Some(value.clone()),
+            "--width" => render_args.img_width = Some(parse_positive(flag, value)?),
+            "--height" => render_args.img_height = Some(parse_positive(flag, value)?),
+            "--aspect-ratio" => {
+                render_args.aspect_ratio = Some(parse_positive_number(flag, value)?)
+            }
+            "--samples" => render_args.samples_per_pixel = Some(parse_positive(flag, value)?),
+            "--depth" => render_args.recursive_depth = Some(parse_positive(flag, value)?),
+            "--diffuse-depth" => render_args.diffuse_depth = Some(parse_number(flag, value)?),
+            "--glossy-depth" => render_args.glossy_depth = Some(parse_number(flag, value)?),
+            "--transmission-depth" => {
+                render_args.transmission_depth = Some(parse_number(flag, value)?)
+            }
+            "--roulette" => render_args.russian_roulette = Some(parse_number(flag, value)?),
+            "--threads" => render_args.num_threads = Some(parse_positive(flag, value)?),
+            "--leaf-size" => render_args.bvh_leaf_size = Some(parse_positive(flag, value)?),
+            "--background" => render_args.background = Some(parse_radiance(flag, value)?),
+            "--exposure" => render_args.exposure = Some(parse_finite(flag, value)?),
+            "--tone-map" => tone_map = Some(value.clone()),
+            "--white" => white = Some(parse_positive_number(flag, value)?),
+            "--dither" => {
+                render_args.dither = Some(match value.as_str() {
+                    "true" => true,
+                    "false" => false,
+                    _ => return Err(format!("Invalid value '{}' for '{}'", value, flag)),
+                })
+            }
+            "--look-from" => render_args.look_from = Some(parse_vec3(flag, value)?),
+            "--look-at" => render_args.look_at = Some(parse_vec3(flag, value)?),
+            "--vup" => render_args.vup = Some(parse_vec3(flag, value)?),
+            "--vfov" => {
+                let vertical_fov = parse_finite(flag, value)?;
+                if vertical_fov <= 0.0 || vertical_fov >= 180.0 {
+                    return Err(format!(
+                        "'{}' must be between 0 and 180 degrees, got '{}'",
+                        flag, value
+                    ));
+                }
+                render_args.vertical_fov = Some(vertical_fov);
+            }
+            "--aperture" => {
+                let aperture = parse_finite(flag, value)?;
+                if aperture < 0.0 {
+                    return Err(format!("'{}' must not be negative, got '{}'", flag, value));
+                }
+                render_args.aperture = Some(aperture);
+            }
+            "--focus-dist" => render_args.focus_dist = Some(parse_positive_number(flag, value)?),
+            "--shutter" => render_args.shutter = Some(parse_interval(flag, value)?),
+            _ => return Err(format!("Unknown option '{}'", flag)),
+        }
+    }
+
+    match (tone_map, white) {
+        (Some(name), white) => {
+            render_args.tone_mapping = Some(
+                ToneMapping::from_name(&name, white)
+                    .map_err(|e| format!("Invalid value for '--tone-map': {}", e))?,
+            )
+        }
+        (None, Some(_)) => {
+            return Err(String::from(
+                "'--white' needs '--tone-map extended_reinhard'",
+            ))
+        }
+        (None, None) => {}
+    }
+    if let Some(scene) = scene {
+        if render_args.scene_file.is_some() {
+            return Err(String::from(
+                "A scene name and a scene file cannot both be given",
+            ));
+        }
+        render_args.scene = scene;
+    }
+    Ok(Command::Render(Box::new(render_args)))
+}
+
+fn parse_number<T: FromStr>(flag: &str, value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse::<T>()
+        .map_err(|_| format!("Invalid value '{}' for '{}'", value, flag))
+}
+
+fn parse_finite(flag: &str, value: &str) -> Result<f64, String> {
+    let x: f64 = parse_number(flag, value)?;
+    if !x.is_finite() {
+        return Err(format!("'{}' must be finite, got '{}'", flag, value));
+    }
+    Ok(x)
+}
+
+fn parse_positive_number(flag: &str, value: &str) -> Result<f64, String> {
+    let x = parse_finite(flag, value)?;
+    if x <= 0.0 {
+        return Err(format!("'{}' must be positive, got '{}'", flag, value));
+    }
+    Ok(x)
+}
+
+fn parse_positive(flag: &str, value: &str) -> Result<u32, String> {
+    let n: u32 = parse_number(flag, value)?;
+    if n == 0 {
+        return Err(format!("'{}' must be positive, got '{}'", flag, value));
+    }
+    Ok(n)
+}
+
+fn parse_triple(flag: &str, value: &str) -> Result<(f64, f64, f64), String> {
+    let parts = value.split(',').collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        return Err(format!(
+            "Expected three comma separated numbers for '{}', got '{}'",
+            flag, value
+        ));
+    }
+    Ok((
+        parse_finite(flag, parts[0])?,
+        parse_finite(flag, parts[1])?,
+        parse_finite(flag, parts[2])?,
+    ))
+}
+
+fn parse_interval(flag: &str, value: &str) -> Result<(f64, f64), String> {
+    match value.split(',').collect::<Vec<&str>>()[..] {
+        [open, close] => {
+            let (open, close) = (parse_finite(flag, open)?, parse_finite(flag, close)?);
+            if close < open {
+                return Err(format!(
+                    "'{}' must not close before it opens, got '{}'",
+                    flag, value
+                ));
+            }
+            Ok((open, close))
+        }
+        _ => Err(format!(
+            "Expected two comma separated numbers for '{}', got '{}'",
+            flag, value
+        )),
+    }
+}
+
+fn parse_vec3(flag: &str, value: &str) -> Result<Vec3, String> {
+    let (x, y, z) = parse_triple(flag, value)?;
+    Ok(Vec3::new(x, y, z))
+}
+
+fn parse_radiance(flag: &str, value: &str) -> Result<Color, String> {
+    let (r, g, b) = parse_triple(flag, value)?;
+    for c in [r, g, b] {
+        if !c.is_finite() || c < 0.0 {
+            return Err(format!(
+                "Color components for '{}' must be finite and not negative, got '{}'",
+                flag, value
+            ));
+        }
+    }
+    Ok(Color::new(r, g, b))
+}
+
+#[cfg(test)]
+fn to_args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| String::from(*a)).collect()
+}
+
+#[test]
+fn parse_scene_and_overrides() {
+    let args = to_args(&[
+        "cornell-box",
+        "--width",
+        "120",
+        "--samples",
+        "8",
+        "--roulette",
+        "4",
+        "--diffuse-depth",
+        "2",
+        "--leaf-size",
+        "2",
+        "--background",
+        "0.1,0.2,0.3",
+        "--white",
+        "5",
+        "--tone-map",
+        "extended_reinhard",
+        "--exposure",
+        "-1.5",
+        "--dither",
+        "false",
+        "--look-from",
+        "1,2,3",
+        "--shutter",
+        "0,0.5",
+        "-o",
+        "out.png",
+    ]);
+    let render_args = match parse(&args) {
+        Ok(Command::Render(render_args)) => render_args,
+        _ => panic!("Expected a render command"),
+    };
+
+    assert_eq!(render_args.scene, "cornell-box");
+    assert_eq!(render_args.img_width, Some(120));
+    assert_eq!(render_args.samples_per_pixel, Some(8));
+    assert_eq!(render_args.bvh_leaf_size, Some(2));
+    assert_eq!(render_args.background, Some(Color::new(0.1, 0.2, 0.3)));
+    assert_eq!(render_args.look_from, Some(Vec3::new(1.0, 2.0, 3.0)));
+    assert_eq!(render_args.shutter, Some((0.0, 0.5)));
+    assert_eq!(render_args.output, Some(String::from("out.png")));
+    assert_eq!(render_args.recursive_depth, None);
+    assert_eq!(render_args.russian_roulette, Some(4));
+    assert_eq!(render_args.diffuse_depth, Some(2));
+    assert_eq!(
+        render_args.tone_mapping,
+        Some(ToneMapping::ExtendedReinhard { white: 5.0 })
+    );
+    assert_eq!(render_args.exposure, Some(-1.5));
+    assert_eq!(render_args.dither, Some(false));
+}
+
+#[test]
+fn parse_list_and_help() {
+    assert!(matches!(
+        parse(&to_args(&["--list-scenes"])),
+        Ok(Command::ListScenes)
+    ));
+    assert!(matches!(
+        parse(&to_args(&["--width", "10", "-h"])),
+        Ok(Command::Help)
+    ));
+}
+
+#[test]
+fn parse_errors() {
+    assert!(parse(&to_args(&["--width"])).is_err());
+    assert!(parse(&to_args(&["--width", "abc"])).is_err());
+    assert!(parse(&to_args(&["--threads", "0"])).is_err());
+    assert!(parse(&to_args(&["--background", "-1,0,0"])).is_err());
+    assert!(parse(&to_args(&["--look-at", "1,2"])).is_err());
+    assert!(parse(&to_args(&["--shutter", "1,0"])).is_err());
+    assert!(parse(&to_args(&["--frobnicate", "1"])).is_err());
+    assert!(parse(&to_args(&["--tone-map", "filmic"])).is_err());
+    assert!(parse(&to_args(&["--white", "4"])).is_err());
+    assert!(parse(&to_args(&["--tone-map", "aces", "--white", "4"])).is_err());
+    assert!(parse(&to_args(&["--dither", "yes"])).is_err());
+    assert!(parse(&to_args(&["--exposure", "NaN"])).is_err());
+    assert!(parse(&to_args(&["--exposure", "-inf"])).is_err());
+    assert!(parse(&to_args(&["--vfov", "180"])).is_err());
+    assert!(parse(&to_args(&["--vfov", "NaN"])).is_err());
+    assert!(parse(&to_args(&["--aperture", "-0.1"])).is_err());
+    assert!(parse(&to_args(&["--focus-dist", "inf"])).is_err());
+    assert!(parse(&to_args(&[
+        "--tone-map",
+        "extended_reinhard",
+        "--white",
+        "NaN"
+    ]))
+    .is_err());
+    assert!(parse(&to_args(&["--shutter", "0,inf"])).is_err());
+    assert!(parse(&to_args(&["--look-from", "0,NaN,0"])).is_err());
+    assert!(parse(&to_args(&["--aspect-ratio", "NaN"])).is_err());
+    assert!(parse(&to_args(&["--aspect-ratio", "inf"])).is_err());
+    assert!(parse(&to_args(&["die-box", "cornell-box"])).is_err());
+    assert!(parse(&to_args(&["die-box", "-f", "a.scene"])).is_err());
+}
+
+#[test]
+fn resolutions_without_pixels_are_errors() {
+    use ray_tracing::scene::builtin::builtin_scene;
+
+    let apply = |args: &[&str]| match parse(&to_args(args)) {
+        Ok(Command::Render(render_args)) => {
+            let mut scene = builtin_scene(&render_args.scene, &render_args.obj_file).unwrap();
+            render_args
+                .apply(&mut scene)
+                .map(|_| scene.settings.img_height)
+        }
+        _ => panic!("Expected a render command"),
+    };
+    assert_eq!(apply(&["--width", "8", "--aspect-ratio", "2"]), Ok(4));
+    assert!(apply(&["--width", "8", "--aspect-ratio", "100"]).is_err());
+    assert!(apply(&["--height", "8", "--aspect-ratio", "0.01"]).is_err());
+}
@@ -0,0 +1,160 @@
+use crate::geometry::vec3::{cross, dot, Vec3};
+use std::ops;
+
+// Row major 3x3 matrix for rotations, scales and bases, vectors are columns.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Mat3 {
+    m: [[f64; 3]; 3],
+}
+
+impl Mat3 {
+    pub fn new(m: [[f64; 3]; 3]) -> Mat3 {
+        Mat3 { m }
+    }
+
+    pub fn identity() -> Mat3 {
+        Mat3::scaling(Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn from_columns(x: Vec3, y: Vec3, z: Vec3) -> Mat3 {
+        Mat3::new([
+            [x.x(), y.x(), z.x()],
+            [x.y(), y.y(), z.y()],
+            [x.z(), y.z(), z.z()],
+        ])
+    }
+
+    // Right handed orthonormal basis whose z column points along `forward` and whose y column is
+    // as close to `vup` as possible. The columns are (right, up, forward).
+    pub fn basis(forward: Vec3, vup: Vec3) -> Mat3 {
+        let forward = forward.normalize();
+        let right = cross(&vup, &forward).normalize();
+        let up = cross(&forward, &right);
+        Mat3::from_columns(right, up, forward)
+    }
+
+    pub fn scaling(scale: Vec3) -> Mat3 {
+        Mat3::new([
+            [scale.x(), 0.0, 0.0],
+            [0.0, scale.y(), 0.0],
+            [0.0, 0.0, scale.z()],
+        ])
+    }
+
+    // Counter-clockwise rotation in radians around `axis`, which doesn't need to be normalized.
+    pub fn rotation(axis: Vec3, angle: f64) -> Mat3 {
+        let a = axis.normalize();
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let (sin, cos) = angle.sin_cos();
+        let c = 1.0 - cos;
+        Mat3::new([
+            [cos + x * x * c, x * y * c - z * sin, x * z * c + y * sin],
+            [y * x * c + z * sin, cos + y * y * c, y * z * c - x * sin],
+            [z * x * c - y * sin, z * y * c + x * sin, cos + z * z * c],
+        ])
+    }
+
+    // Rotates around x, then y, then z by the components of `angles`, in radians.
+    pub fn from_euler(angles: Vec3) -> Mat3 {
+        Mat3::rotation(Vec3::new(0.0, 0.0, 1.0), angles.z())
+            * Mat3::rotation(Vec3::new(0.0, 1.0, 0.0), angles.y())
+            * Mat3::rotation(Vec3::new(1.0, 0.0, 0.0), angles.x())
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        self.m[row][column]
+    }
+
+    pub fn row(&self, row: usize) -> Vec3 {
+        let r = self.m[row];
+        Vec3::new(r[0], r[1], r[2])
+    }
+
+    pub fn column(&self, column: usize) -> Vec3 {
+        Vec3::new(self.m[0][column], self.m[1][column], self.m[2][column])
+    }
+
+    pub fn transpose(&self) -> Mat3 {
+        Mat3::from_columns(self.row(0), self.row(1), self.row(2))
+    }
+
+    pub fn determinant(&self) -> f64 {
+        dot(&self.row(0), &cross(&self.row(1), &self.row(2)))
+    }
+
+    // None for singular matrices and matrices with NaN or infinite entries, which make the
+    // determinant non-finite.
+    pub fn inverse(&self) -> Option<Mat3> {
+        let det = self.determinant();
+        if !det.is_finite() || det.abs() < 1e-12 {
+            return None;
+        }
+        // The rows of the inverse are the cross products of the columns.
+        let (c0, c1, c2) = (self.column(0), self.column(1), self.column(2));
+        let (r0, r1, r2) = (cross(&c1, &c2), cross(&c2, &c0), cross(&c0, &c1));
+        Some(Mat3::from_columns(r0, r1, r2).transpose() * (1.0 / det))
+    }
+
+    // Normals are transformed by the inverse transpose, so this is called on the inverse of the
+    // matrix that moves the surface. The result is not normalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        self.transpose() * n
+    }
+}
+
+impl_op_ex!(*|a: &Mat3, b: &Mat3| -> Mat3 {
+    let mut m = [[0.0; 3]; 3];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = dot(&a.row(i), &b.column(j));
+        }
+    }
+    Mat3::new(m)
+});
+
+impl_op_ex!(*|a: &Mat3, v: &Vec3| -> Vec3 {
+    Vec3::new(dot(&a.row(0), v), dot(&a.row(1), v), dot(&a.row(2), v))
+});
+
+impl_op_ex!(*|a: &Mat3, s: f64| -> Mat3 { Mat3::new(a.m.map(|row| row.map(|value| value * s))) });
+
+#[cfg(test)]
+fn assert_near(a: &Vec3, b: &Vec3) {
+    assert!((a - b).len() < 1e-9, "{} != {}", a, b);
+}
+
+#[test]
+fn inverse_and_determinant() {
+    let m = Mat3::rotation(Vec3::new(1.0, 2.0, 3.0), 0.8) * Mat3::scaling(Vec3::new(2.0, 3.0, 4.0));
+    assert!((m.determinant() - 24.0).abs() < 1e-9);
+    let inverse = m.inverse().unwrap();
+    let v = Vec3::new(0.5, -1.0, 3.0);
+    assert_near(&(inverse * (m * v)), &v);
+    assert!(Mat3::scaling(Vec3::new(1.0, 1.0, 0.0)).inverse().is_none());
+
+    // Rotations are orthonormal.
+    let r = Mat3::from_euler(Vec3::new(0.3, -1.2, 2.0));
+    let product = r * r.transpose();
+    for i in 0..3 {
+        assert_near(&product.row(i), &Mat3::identity().row(i));
+    }
+}
+
+#[test]
+fn basis_is_orthonormal() {
+    let basis = Mat3::basis(Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let (right, up, forward) = (basis.column(0), basis.column(1), basis.column(2));
+    assert_near(&forward, &Vec3::new(1.0, 1.0, 0.0).normalize());
+    assert_near(&right, &Vec3::new(0.0, 0.0, -1.0));
+    assert_near(&cross(&right, &up), &forward);
+    assert!((up.len() - 1.0).abs() < 1e-9 && dot(&up, &forward).abs() < 1e-9);
+
+    // Euler angles rotate around x first.
+    let m = Mat3::from_euler(Vec3::new(
+        std::f64::consts::FRAC_PI_2,
+        0.0,
+        std::f64::consts::FRAC_PI_2,
+    ));
+    assert_near(&(m * Vec3::new(0.0, 1.0, 0.0)), &Vec3::new(0.0, 0.0, 1.0));
+    assert_near(&(m * Vec3::new(1.0, 0.0, 0.0)), &Vec3::new(0.0, 1.0, 0.0));
+}
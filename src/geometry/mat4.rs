@@ -0,0 +1,304 @@
+use crate::geometry::mat3::Mat3;
+use crate::geometry::quaternion::Quaternion;
+use crate::geometry::vec3::Vec3;
+use std::ops;
+
+// Row major 4x4 matrix for affine transforms, vectors are columns: `m * p` applies `m` to `p`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Mat4 {
+    m: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    pub fn new(m: [[f64; 4]; 4]) -> Mat4 {
+        Mat4 { m }
+    }
+
+    pub fn identity() -> Mat4 {
+        Mat4::scaling(Vec3::new(1.0, 1.0, 1.0))
+    }
+
+    pub fn translation(offset: Vec3) -> Mat4 {
+        Mat4::new([
+            [1.0, 0.0, 0.0, offset.x()],
+            [0.0, 1.0, 0.0, offset.y()],
+            [0.0, 0.0, 1.0, offset.z()],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn scaling(scale: Vec3) -> Mat4 {
+        Mat4::new([
+            [scale.x(), 0.0, 0.0, 0.0],
+            [0.0, scale.y(), 0.0, 0.0],
+            [0.0, 0.0, scale.z(), 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Counter-clockwise rotations in radians, looking down the axis towards the origin.
+    pub fn rotation_x(angle: f64) -> Mat4 {
+        let (sin, cos) = angle.sin_cos();
+        Mat4::new([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, cos, -sin, 0.0],
+            [0.0, sin, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_y(angle: f64) -> Mat4 {
+        let (sin, cos) = angle.sin_cos();
+        Mat4::new([
+            [cos, 0.0, sin, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [-sin, 0.0, cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn rotation_z(angle: f64) -> Mat4 {
+        let (sin, cos) = angle.sin_cos();
+        Mat4::new([
+            [cos, -sin, 0.0, 0.0],
+            [sin, cos, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    // Applies `linear`, then moves by `translation`.
+    pub fn from_mat3(linear: &Mat3, translation: Vec3) -> Mat4 {
+        let mut m = Mat4::translation(translation).m;
+        for (i, row) in m.iter_mut().take(3).enumerate() {
+            for (j, value) in row.iter_mut().take(3).enumerate() {
+                *value = linear.get(i, j);
+            }
+        }
+        Mat4::new(m)
+    }
+
+    // Counter-clockwise rotation around `axis`, which doesn't need to be normalized.
+    pub fn rotation(axis: Vec3, angle: f64) -> Mat4 {
+        Mat4::from_mat3(&Mat3::rotation(axis, angle), Vec3::origin())
+    }
+
+    pub fn from_quaternion(q: &Quaternion) -> Mat4 {
+        Mat4::from_mat3(&q.to_mat3(), Vec3::origin())
+    }
+
+    // Rotates around x, then y, then z by the components of `angles`, in radians.
+    pub fn from_euler(angles: Vec3) -> Mat4 {
+        Mat4::from_mat3(&Mat3::from_euler(angles), Vec3::origin())
+    }
+
+    // Places an object at `from` with its +z axis pointing at `to` and its +y axis as close to
+    // `vup` as possible.
+    pub fn look_at(from: Vec3, to: Vec3, vup: Vec3) -> Mat4 {
+        Mat4::from_mat3(&Mat3::basis(to - from, vup), from)
+    }
+
+    // The upper left 3x3 part, everything but the translation.
+    pub fn linear(&self) -> Mat3 {
+        let m = &self.m;
+        Mat3::new([
+            [m[0][0], m[0][1], m[0][2]],
+            [m[1][0], m[1][1], m[1][2]],
+            [m[2][0], m[2][1], m[2][2]],
+        ])
+    }
+
+    pub fn get(&self, row: usize, column: usize) -> f64 {
+        self.m[row][column]
+    }
+
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Mat4::new(m)
+    }
+
+    // Gauss-Jordan elimination with partial pivoting, None for singular matrices and matrices with
+    // NaN or infinite entries.
+    pub fn inverse(&self) -> Option<Mat4> {
+        if self.m.iter().flatten().any(|v| !v.is_finite()) {
+            return None;
+        }
+        let mut a = self.m;
+        let mut inverse = Mat4::identity().m;
+        for column in 0..4 {
+            let pivot = (column..4)
+                .max_by(|&i, &j| a[i][column].abs().total_cmp(&a[j][column].abs()))
+                .unwrap();
+            if a[pivot][column].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(column, pivot);
+            inverse.swap(column, pivot);
+
+            let scale = 1.0 / a[column][column];
+            for k in 0..4 {
+                a[column][k] *= scale;
+                inverse[column][k] *= scale;
+            }
+            for row in 0..4 {
+                if row == column {
+                    continue;
+                }
+                let factor = a[row][column];
+                for k in 0..4 {
+                    a[row][k] -= factor * a[column][k];
+                    inverse[row][k] -= factor * inverse[column][k];
+                }
+            }
+        }
+        Some(Mat4::new(inverse))
+    }
+
+    pub fn transform_point(&self, p: &Vec3) -> Vec3 {
+        let m = &self.m;
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+        Vec3::new(
+            (m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3]) / w,
+            (m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3]) / w,
+            (m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3]) / w,
+        )
+    }
+
+    // Directions ignore the translation.
+    pub fn transform_vector(&self, v: &Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+
+    // Normals are transformed by the inverse transpose, so this is called on the inverse of the
+    // transform that moves the surface. The result is not normalized.
+    pub fn transform_normal(&self, n: &Vec3) -> Vec3 {
+        self.transpose().transform_vector(n)
+    }
+}
+
+impl_op_ex!(*|a: &Mat4, b: &Mat4| -> Mat4 {
+    let mut m = [[0.0; 4]; 4];
+    for (i, row) in m.iter_mut().enumerate() {
+        for (j, value) in row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a.m[i][k] * b.m[k][j]).sum();
+        }
+    }
+    Mat4::new(m)
+});
+
+#[cfg(test)]
+fn assert_near(a: &Vec3, b: &Vec3) {
+    assert!((a - b).len() < 1e-9, "{} != {}", a, b);
+}
+
+#[test]
+fn transform_points_and_vectors() {
+    let m = Mat4::translation(Vec3::new(1.0, 2.0, 3.0)) * Mat4::scaling(Vec3::new(2.0, 2.0, 2.0));
+    assert_near(
+        &m.transform_point(&Vec3::new(1.0, 1.0, 1.0)),
+        &Vec3::new(3.0, 4.0, 5.0),
+    );
+    assert_near(
+        &m.transform_vector(&Vec3::new(1.0, 1.0, 1.0)),
+        &Vec3::new(2.0, 2.0, 2.0),
+    );
+
+    let quarter = std::f64::consts::FRAC_PI_2;
+    let x = Vec3::new(1.0, 0.0, 0.0);
+    assert_near(
+        &Mat4::rotation_z(quarter).transform_vector(&x),
+        &Vec3::new(0.0, 1.0, 0.0),
+    );
+    assert_near(
+        &Mat4::rotation_y(quarter).transform_vector(&x),
+        &Vec3::new(0.0, 0.0, -1.0),
+    );
+    assert_near(
+        &Mat4::rotation_x(quarter).transform_vector(&Vec3::new(0.0, 1.0, 0.0)),
+        &Vec3::new(0.0, 0.0, 1.0),
+    );
+}
+
+#[test]
+fn axis_angle_and_look_at() {
+    let angle = 0.4;
+    let m = Mat4::rotation(Vec3::new(0.0, 2.0, 0.0), angle);
+    let expected = Mat4::rotation_y(angle);
+    for i in 0..4 {
+        for j in 0..4 {
+            assert!((m.get(i, j) - expected.get(i, j)).abs() < 1e-12);
+        }
+    }
+
+    let from = Vec3::new(1.0, 2.0, 3.0);
+    let m = Mat4::look_at(from, Vec3::new(1.0, 2.0, 10.0), Vec3::new(0.0, 1.0, 0.0));
+    assert_near(&m.transform_point(&Vec3::origin()), &from);
+    assert_near(
+        &m.transform_point(&Vec3::new(0.0, 0.0, 1.0)),
+        &Vec3::new(1.0, 2.0, 4.0),
+    );
+    let m = Mat4::look_at(
+        Vec3::origin(),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    assert_near(
+        &m.transform_vector(&Vec3::new(0.0, 1.0, 0.0)),
+        &Vec3::new(0.0, 1.0, 0.0),
+    );
+    assert_near(
+        &m.transform_vector(&Vec3::new(1.0, 0.0, 0.0)),
+        &Vec3::new(0.0, 0.0, -1.0),
+    );
+}
+
+#[test]
+fn inverse_undoes_the_transform() {
+    let m = Mat4::translation(Vec3::new(1.0, -2.0, 3.0))
+        * Mat4::rotation_y(0.7)
+        * Mat4::rotation_x(-0.3)
+        * Mat4::scaling(Vec3::new(2.0, 0.5, 3.0));
+    let inverse = m.inverse().unwrap();
+    let p = Vec3::new(0.3, -4.0, 2.5);
+    assert_near(&inverse.transform_point(&m.transform_point(&p)), &p);
+
+    let identity = m * inverse;
+    for i in 0..4 {
+        for j in 0..4 {
+            let expected = if i == j { 1.0 } else { 0.0 };
+            assert!((identity.get(i, j) - expected).abs() < 1e-9);
+        }
+    }
+    assert!(Mat4::scaling(Vec3::new(1.0, 0.0, 1.0)).inverse().is_none());
+    assert!(Mat4::scaling(Vec3::new(f64::NAN, 1.0, 1.0))
+        .inverse()
+        .is_none());
+    assert!(Mat4::translation(Vec3::new(0.0, f64::INFINITY, 0.0))
+        .inverse()
+        .is_none());
+}
+
+#[test]
+fn normals_stay_perpendicular() {
+    let m = Mat4::scaling(Vec3::new(4.0, 1.0, 1.0)) * Mat4::rotation_z(0.5);
+    let inverse = m.inverse().unwrap();
+    // A surface spanned by two tangents, with their cross product as normal.
+    let (t1, t2) = (Vec3::new(1.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
+    let n = crate::geometry::vec3::cross(&t1, &t2);
+
+    let n = inverse.transform_normal(&n);
+    for t in [t1, t2] {
+        let t = m.transform_vector(&t);
+        assert!(crate::geometry::vec3::dot(&n, &t).abs() < 1e-9);
+    }
+}
@@ -1,2 +1,6 @@
+pub mod mat3;
+pub mod mat4;
+pub mod quaternion;
 pub mod ray;
+pub mod sampling;
 pub mod vec3;
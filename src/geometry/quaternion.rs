@@ -0,0 +1,178 @@
+use crate::geometry::mat3::Mat3;
+use crate::geometry::vec3::{cross, dot, Vec3};
+use std::ops;
+
+// Rotation as a unit quaternion w + xi + yj + zk, stored as the scalar `w` and the vector part.
+// Unlike matrices they interpolate smoothly, see `slerp`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Quaternion {
+    w: f64,
+    v: Vec3,
+}
+
+impl Quaternion {
+    pub fn new(w: f64, x: f64, y: f64, z: f64) -> Quaternion {
+        Quaternion {
+            w,
+            v: Vec3::new(x, y, z),
+        }
+    }
+
+    pub fn identity() -> Quaternion {
+        Quaternion::new(1.0, 0.0, 0.0, 0.0)
+    }
+
+    // Counter-clockwise rotation in radians around `axis`, which doesn't need to be normalized.
+    pub fn from_axis_angle(axis: Vec3, angle: f64) -> Quaternion {
+        let (sin, cos) = (angle * 0.5).sin_cos();
+        Quaternion {
+            w: cos,
+            v: axis.normalize() * sin,
+        }
+    }
+
+    // Rotates around x, then y, then z by the components of `angles`, in radians, like
+    // `Mat3::from_euler`.
+    pub fn from_euler(angles: Vec3) -> Quaternion {
+        Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 1.0), angles.z())
+            * Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), angles.y())
+            * Quaternion::from_axis_angle(Vec3::new(1.0, 0.0, 0.0), angles.x())
+    }
+
+    pub fn w(&self) -> f64 {
+        self.w
+    }
+
+    pub fn vector(&self) -> Vec3 {
+        self.v
+    }
+
+    // The axis is arbitrary for the identity.
+    pub fn to_axis_angle(&self) -> (Vec3, f64) {
+        let q = self.normalize();
+        let sin = q.v.len();
+        if sin < 1e-12 {
+            return (Vec3::new(1.0, 0.0, 0.0), 0.0);
+        }
+        (q.v / sin, 2.0 * sin.atan2(q.w))
+    }
+
+    pub fn len(&self) -> f64 {
+        self.dot(self).sqrt()
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        *self * (1.0 / self.len())
+    }
+
+    pub fn dot(&self, other: &Quaternion) -> f64 {
+        self.w * other.w + dot(&self.v, &other.v)
+    }
+
+    // The inverse rotation of a unit quaternion.
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion {
+            w: self.w,
+            v: -self.v,
+        }
+    }
+
+    pub fn inverse(&self) -> Quaternion {
+        self.conjugate() * (1.0 / self.dot(self))
+    }
+
+    pub fn rotate(&self, v: &Vec3) -> Vec3 {
+        // v + 2w(q x v) + 2q x (q x v), for a unit quaternion.
+        let t = 2.0 * cross(&self.v, v);
+        v + self.w * t + cross(&self.v, &t)
+    }
+
+    pub fn to_mat3(&self) -> Mat3 {
+        Mat3::from_columns(
+            self.rotate(&Vec3::new(1.0, 0.0, 0.0)),
+            self.rotate(&Vec3::new(0.0, 1.0, 0.0)),
+            self.rotate(&Vec3::new(0.0, 0.0, 1.0)),
+        )
+    }
+
+    // Spherical linear interpolation along the shorter arc, `t` = 0 gives `self`.
+    pub fn slerp(&self, other: &Quaternion, t: f64) -> Quaternion {
+        let (a, mut b) = (self.normalize(), other.normalize());
+        let mut cos = a.dot(&b);
+        if cos < 0.0 {
+            b = -b;
+            cos = -cos;
+        }
+        // Nearly parallel, the sine below would vanish.
+        if cos > 0.9995 {
+            return (a * (1.0 - t) + b * t).normalize();
+        }
+        let theta = cos.acos();
+        let sin = theta.sin();
+        a * (((1.0 - t) * theta).sin() / sin) + b * ((t * theta).sin() / sin)
+    }
+}
+
+// Composition, `a * b` rotates by `b` first.
+impl_op_ex!(*|a: &Quaternion, b: &Quaternion| -> Quaternion {
+    Quaternion {
+        w: a.w * b.w - dot(&a.v, &b.v),
+        v: a.w * b.v + b.w * a.v + cross(&a.v, &b.v),
+    }
+});
+
+impl_op_ex!(*|a: &Quaternion, s: f64| -> Quaternion {
+    Quaternion {
+        w: a.w * s,
+        v: a.v * s,
+    }
+});
+
+impl_op_ex!(-|a: &Quaternion| -> Quaternion { *a * -1.0 });
+
+impl_op_ex!(+|a: &Quaternion, b: &Quaternion| -> Quaternion {
+    Quaternion {
+        w: a.w + b.w,
+        v: a.v + b.v,
+    }
+});
+
+#[cfg(test)]
+fn assert_near(a: &Vec3, b: &Vec3) {
+    assert!((a - b).len() < 1e-9, "{} != {}", a, b);
+}
+
+#[test]
+fn quaternions_match_matrices() {
+    let axis = Vec3::new(1.0, -2.0, 0.5);
+    let q = Quaternion::from_axis_angle(axis, 1.1);
+    let m = Mat3::rotation(axis, 1.1);
+    let v = Vec3::new(0.3, 2.0, -1.0);
+    assert_near(&q.rotate(&v), &(m * v));
+
+    let angles = Vec3::new(0.4, -0.7, 2.5);
+    let q = Quaternion::from_euler(angles);
+    let m = Mat3::from_euler(angles);
+    for i in 0..3 {
+        assert_near(&q.to_mat3().column(i), &m.column(i));
+    }
+    assert_near(&q.inverse().rotate(&q.rotate(&v)), &v);
+
+    let (axis, angle) = Quaternion::from_axis_angle(Vec3::new(0.0, 0.0, 2.0), 0.5).to_axis_angle();
+    assert_near(&axis, &Vec3::new(0.0, 0.0, 1.0));
+    assert!((angle - 0.5).abs() < 1e-9);
+}
+
+#[test]
+fn slerp_interpolates_the_angle() {
+    let z = Vec3::new(0.0, 0.0, 1.0);
+    let a = Quaternion::from_axis_angle(z, 0.2);
+    let b = Quaternion::from_axis_angle(z, 1.4);
+    assert_near(&a.slerp(&b, 0.0).rotate(&z), &a.rotate(&z));
+    let (_, angle) = a.slerp(&b, 0.25).to_axis_angle();
+    assert!((angle - 0.5).abs() < 1e-9);
+
+    // The negated quaternion is the same rotation, slerp takes the short way.
+    let (_, angle) = a.slerp(&-b, 0.5).to_axis_angle();
+    assert!((angle - 0.8).abs() < 1e-9);
+}
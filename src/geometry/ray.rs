@@ -1,35 +1,41 @@
 use crate::geometry::vec3::Vec3;
 
+#[derive(Copy, Clone)]
 pub struct Ray {
     origin: Vec3,
     direction: Vec3,
+    // When the ray was sent, within the camera's shutter interval. Moving objects are hit where
+    // they are at this time.
+    time: f64,
 }
 
 impl Ray {
-    pub fn new_from_origin(direction: Vec3) -> Ray {
-        Ray {
-            origin: Vec3::origin(),
-            direction,
-        }
-    }
-
     pub fn new(origin: Vec3, direction: Vec3) -> Ray {
-        Ray { origin, direction }
+        Ray::new_at_time(origin, direction, 0.0)
     }
 
-    pub fn from_to(origin: Vec3, destination: Vec3) -> Ray {
+    pub fn new_at_time(origin: Vec3, direction: Vec3, time: f64) -> Ray {
         Ray {
             origin,
-            direction: destination - origin,
+            direction,
+            time,
         }
     }
 
+    pub fn from_to(origin: Vec3, destination: Vec3) -> Ray {
+        Ray::new(origin, destination - origin)
+    }
+
     pub fn origin(&self) -> &Vec3 {
-        return &self.origin;
+        &self.origin
     }
 
     pub fn direction(&self) -> &Vec3 {
-        return &self.direction;
+        &self.direction
+    }
+
+    pub fn time(&self) -> f64 {
+        self.time
     }
 
     pub fn at(&self, t: f64) -> Vec3 {
@@ -47,15 +53,6 @@ fn initialize_ray() {
     assert_eq!(ray.direction, Vec3::new(1.0, 1.0, 1.0));
 }
 
-#[test]
-fn initialize_ray_at_origin() {
-    let direction = Vec3::new(1.0, 1.0, 1.0);
-    let ray = Ray::new_from_origin(direction);
-
-    assert_eq!(ray.origin, Vec3::new(0.0, 0.0, 0.0));
-    assert_eq!(ray.direction, Vec3::new(1.0, 1.0, 1.0));
-}
-
 #[test]
 fn project_ray() {
     let origin = Vec3::new(0.0, 0.0, 0.0);
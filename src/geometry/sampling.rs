@@ -0,0 +1,180 @@
+use crate::geometry::mat3::Mat3;
+use crate::geometry::vec3::Vec3;
+use rand::Rng;
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+// Random points and directions shared by materials, lights and the camera. Directions are picked
+// around the z axis, `frame` turns them to point around any other axis. The pdfs are per unit
+// solid angle for directions and per unit area for points.
+
+// Orthonormal basis whose z column is `axis`, to move directions sampled around z.
+pub fn frame(axis: &Vec3) -> Mat3 {
+    let vup = if axis.x().abs() > 0.9 * axis.len() {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    Mat3::basis(*axis, vup)
+}
+
+pub fn uniform_sphere<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+    let z = 1.0 - 2.0 * rng.gen::<f64>();
+    around_z(z, 2.0 * PI * rng.gen::<f64>())
+}
+
+pub fn uniform_sphere_pdf() -> f64 {
+    1.0 / (4.0 * PI)
+}
+
+// Uniform in the ball of radius 1.
+pub fn uniform_ball<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+    uniform_sphere(rng) * rng.gen::<f64>().cbrt()
+}
+
+// Directions with z >= 0.
+pub fn uniform_hemisphere<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+    around_z(rng.gen::<f64>(), 2.0 * PI * rng.gen::<f64>())
+}
+
+pub fn uniform_hemisphere_pdf() -> f64 {
+    1.0 / (2.0 * PI)
+}
+
+// Directions with z >= 0 and a density proportional to z, the cosine with the axis. Points of
+// the unit disk lifted onto the hemisphere.
+pub fn cosine_hemisphere<R: Rng + ?Sized>(rng: &mut R) -> Vec3 {
+    let (x, y) = concentric_disk(rng);
+    Vec3::new(x, y, (1.0 - x * x - y * y).max(0.0).sqrt())
+}
+
+pub fn cosine_hemisphere_pdf(cos: f64) -> f64 {
+    cos.max(0.0) / PI
+}
+
+// Uniform in the unit disk. Maps squares around the center of the unit square to circles, which
+// keeps neighbouring samples close unlike the polar mapping.
+pub fn concentric_disk<R: Rng + ?Sized>(rng: &mut R) -> (f64, f64) {
+    let a = 2.0 * rng.gen::<f64>() - 1.0;
+    let b = 2.0 * rng.gen::<f64>() - 1.0;
+    if a == 0.0 && b == 0.0 {
+        return (0.0, 0.0);
+    }
+    let (r, theta) = if a.abs() > b.abs() {
+        (a, FRAC_PI_4 * (b / a))
+    } else {
+        (b, FRAC_PI_2 - FRAC_PI_4 * (a / b))
+    };
+    (r * theta.cos(), r * theta.sin())
+}
+
+pub fn concentric_disk_pdf() -> f64 {
+    1.0 / PI
+}
+
+// Directions within the angle with cosine `cos_max` of the z axis.
+pub fn uniform_cone<R: Rng + ?Sized>(rng: &mut R, cos_max: f64) -> Vec3 {
+    let z = 1.0 + rng.gen::<f64>() * (cos_max - 1.0);
+    around_z(z, 2.0 * PI * rng.gen::<f64>())
+}
+
+pub fn uniform_cone_pdf(cos_max: f64) -> f64 {
+    1.0 / (2.0 * PI * (1.0 - cos_max))
+}
+
+// Uniform over the triangle.
+pub fn uniform_triangle<R: Rng + ?Sized>(rng: &mut R, p1: &Vec3, p2: &Vec3, p3: &Vec3) -> Vec3 {
+    let s = rng.gen::<f64>().sqrt();
+    let r = rng.gen::<f64>();
+    (1.0 - s) * p1 + s * (1.0 - r) * p2 + s * r * p3
+}
+
+// Per unit area, for a triangle with area `area`.
+pub fn uniform_triangle_pdf(area: f64) -> f64 {
+    1.0 / area
+}
+
+// The unit vector with height `z` at angle `phi` around the z axis.
+fn around_z(z: f64, phi: f64) -> Vec3 {
+    let r = (1.0 - z * z).max(0.0).sqrt();
+    Vec3::new(r * phi.cos(), r * phi.sin(), z)
+}
+
+#[cfg(test)]
+fn test_rng() -> rand::rngs::StdRng {
+    rand::SeedableRng::seed_from_u64(1)
+}
+
+#[test]
+fn directions_have_their_pdfs() {
+    // The average of 1 / pdf is the solid angle the samples cover, the average of z is known.
+    let mut rng = test_rng();
+    let n = 20000;
+    let cos_max = 0.8;
+    let (mut hemisphere, mut cosine, mut cone) = (0.0, 0.0, 0.0);
+    let (mut sphere_z, mut cosine_z) = (0.0, 0.0);
+    for _ in 0..n {
+        let d = uniform_sphere(&mut rng);
+        assert!((d.len() - 1.0).abs() < 1e-9);
+        sphere_z += d.z() / n as f64;
+
+        let d = uniform_hemisphere(&mut rng);
+        assert!((d.len() - 1.0).abs() < 1e-9 && d.z() >= 0.0);
+        hemisphere += 1.0 / uniform_hemisphere_pdf() / n as f64;
+
+        let d = cosine_hemisphere(&mut rng);
+        assert!((d.len() - 1.0).abs() < 1e-9 && d.z() >= 0.0);
+        cosine += 1.0 / cosine_hemisphere_pdf(d.z()) / n as f64;
+        cosine_z += d.z() / n as f64;
+
+        let d = uniform_cone(&mut rng, cos_max);
+        assert!((d.len() - 1.0).abs() < 1e-9 && d.z() >= cos_max - 1e-12);
+        cone += 1.0 / uniform_cone_pdf(cos_max) / n as f64;
+    }
+    assert!(sphere_z.abs() < 0.02);
+    assert!((hemisphere - 2.0 * PI).abs() < 1e-9);
+    assert!((cosine - 2.0 * PI).abs() < 0.1);
+    assert!((cosine_z - 2.0 / 3.0).abs() < 0.01);
+    assert!((cone - 2.0 * PI * (1.0 - cos_max)).abs() < 1e-9);
+
+    let axis = Vec3::new(1.0, 2.0, -0.5).normalize();
+    let basis = frame(&axis);
+    assert!((basis * Vec3::new(0.0, 0.0, 1.0) - axis).len() < 1e-9);
+}
+
+#[test]
+fn points_are_uniform() {
+    let mut rng = test_rng();
+    let n = 20000;
+    let (mut disk_r2, mut ball_r3) = (0.0, 0.0);
+    let (mut disk, mut triangle) = (0.0, 0.0);
+    let mut centroid = Vec3::origin();
+    let (p1, p2, p3) = (
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(3.0, 0.0, 0.0),
+        Vec3::new(0.0, 3.0, 1.0),
+    );
+    let area = 0.5 * crate::geometry::vec3::cross(&(p2 - p1), &(p3 - p1)).len();
+    for _ in 0..n {
+        let (x, y) = concentric_disk(&mut rng);
+        assert!(x * x + y * y <= 1.0 + 1e-12);
+        disk_r2 += (x * x + y * y) / n as f64;
+        disk += 1.0 / concentric_disk_pdf() / n as f64;
+
+        let p = uniform_ball(&mut rng);
+        assert!(p.len() <= 1.0 + 1e-12);
+        ball_r3 += p.len().powi(3) / n as f64;
+
+        let p = uniform_triangle(&mut rng, &p1, &p2, &p3);
+        assert!(p.x() >= 0.0 && p.y() >= 0.0 && p.x() + p.y() <= 3.0 + 1e-9);
+        assert!((p.z() - p.y() / 3.0).abs() < 1e-9);
+        centroid += p / n as f64;
+        triangle += 1.0 / uniform_triangle_pdf(area) / n as f64;
+    }
+    // The average of 1 / pdf is the area the samples cover.
+    assert!((disk - PI).abs() < 1e-9);
+    assert!((triangle - 90f64.sqrt() / 2.0).abs() < 1e-9);
+    // Uniform in area and volume, the squared and cubed radius are uniform in [0, 1].
+    assert!((disk_r2 - 0.5).abs() < 0.01);
+    assert!((ball_r3 - 0.5).abs() < 0.01);
+    assert!((centroid - Vec3::new(1.0, 1.0, 1.0 / 3.0)).len() < 0.02);
+}
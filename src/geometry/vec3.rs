@@ -4,9 +4,7 @@ use std::ops;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Vec3 {
-    x: f64,
-    y: f64,
-    z: f64,
+    e: [f64; 3],
 }
 
 pub fn dot(v1: &Vec3, v2: &Vec3) -> f64 {
@@ -14,45 +12,41 @@ pub fn dot(v1: &Vec3, v2: &Vec3) -> f64 {
 }
 
 pub fn cross(v1: &Vec3, v2: &Vec3) -> Vec3 {
-    Vec3 {
-        x: v1.y * v2.z - v1.z * v2.y,
-        y: v1.z * v2.x - v1.x * v2.z,
-        z: v1.x * v2.y - v1.y * v2.x,
-    }
+    Vec3::new(
+        v1.y() * v2.z() - v1.z() * v2.y(),
+        v1.z() * v2.x() - v1.x() * v2.z(),
+        v1.x() * v2.y() - v1.y() * v2.x(),
+    )
 }
 
 impl Vec3 {
     pub fn origin() -> Vec3 {
-        Vec3 {
-            x: 0.0,
-            y: 0.0,
-            z: 0.0,
-        }
+        Vec3::new(0.0, 0.0, 0.0)
     }
 
     pub fn random(min: f64, max: f64) -> Vec3 {
         let mut rng = rand::thread_rng();
-        Vec3 {
-            x: rng.gen_range(min..max),
-            y: rng.gen_range(min..max),
-            z: rng.gen_range(min..max),
-        }
+        Vec3::new(
+            rng.gen_range(min..max),
+            rng.gen_range(min..max),
+            rng.gen_range(min..max),
+        )
     }
 
     pub fn new(x: f64, y: f64, z: f64) -> Vec3 {
-        Vec3 { x: x, y: y, z: z }
+        Vec3 { e: [x, y, z] }
     }
 
     pub fn x(&self) -> f64 {
-        self.x
+        self.e[0]
     }
 
     pub fn y(&self) -> f64 {
-        self.y
+        self.e[1]
     }
 
     pub fn z(&self) -> f64 {
-        self.z
+        self.e[2]
     }
 
     pub fn len_squared(&self) -> f64 {
@@ -64,11 +58,7 @@ impl Vec3 {
     }
 
     pub fn normalize(&self) -> Vec3 {
-        return Vec3::new(
-            self.x() / self.len(),
-            self.y() / self.len(),
-            self.z() / self.len(),
-        );
+        self / self.len()
     }
 
     pub fn unit_vector(&self) -> Vec3 {
@@ -85,8 +75,67 @@ impl Vec3 {
             && self.z().abs() < 0.0000000001
     }
 
-    pub fn as_slice(&self) -> Vec<f64> {
-        vec![self.x(), self.y(), self.z()]
+    pub fn as_slice(&self) -> &[f64; 3] {
+        &self.e
+    }
+
+    // Applies `f` to every component.
+    pub fn map(&self, f: impl Fn(f64) -> f64) -> Vec3 {
+        Vec3 { e: self.e.map(f) }
+    }
+
+    pub fn abs(&self) -> Vec3 {
+        self.map(f64::abs)
+    }
+
+    // Component-wise minimum and maximum.
+    pub fn min(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.x().min(other.x()),
+            self.y().min(other.y()),
+            self.z().min(other.z()),
+        )
+    }
+
+    pub fn max(&self, other: &Vec3) -> Vec3 {
+        Vec3::new(
+            self.x().max(other.x()),
+            self.y().max(other.y()),
+            self.z().max(other.z()),
+        )
+    }
+
+    pub fn min_component(&self) -> f64 {
+        self.x().min(self.y()).min(self.z())
+    }
+
+    pub fn max_component(&self) -> f64 {
+        self.x().max(self.y()).max(self.z())
+    }
+
+    // Index of the largest component, 0 for x.
+    pub fn max_axis(&self) -> usize {
+        if self.x() >= self.y() && self.x() >= self.z() {
+            0
+        } else if self.y() >= self.z() {
+            1
+        } else {
+            2
+        }
+    }
+}
+
+impl ops::Index<usize> for Vec3 {
+    type Output = f64;
+
+    fn index(&self, axis: usize) -> &f64 {
+        &self.e[axis]
+    }
+}
+
+impl ops::IndexMut<usize> for Vec3 {
+    fn index_mut(&mut self, axis: usize) -> &mut f64 {
+        &mut self.e[axis]
     }
 }
 
@@ -96,53 +145,36 @@ impl fmt::Display for Vec3 {
     }
 }
 
-impl_op_ex!(+ |a: &Vec3, b: &Vec3 | -> Vec3 {
-    Vec3 {
-        x: a.x() + b.x(),
-        y: a.y() + b.y(),
-        z: a.z() + b.z()
-    }
+impl_op_ex!(+|a: &Vec3, b: &Vec3| -> Vec3 {
+    Vec3::new(a.x() + b.x(), a.y() + b.y(), a.z() + b.z())
 });
 
 impl_op_ex!(-|a: &Vec3, b: &Vec3| -> Vec3 {
-    Vec3 {
-        x: a.x() - b.x(),
-        y: a.y() - b.y(),
-        z: a.z() - b.z(),
-    }
+    Vec3::new(a.x() - b.x(), a.y() - b.y(), a.z() - b.z())
 });
 
-impl_op!(*|a: Vec3, b: f64| -> Vec3 {
-    Vec3 {
-        x: a.x() * b,
-        y: a.y() * b,
-        z: a.z() * b,
-    }
+// Component-wise product and quotient.
+impl_op_ex!(*|a: &Vec3, b: &Vec3| -> Vec3 {
+    Vec3::new(a.x() * b.x(), a.y() * b.y(), a.z() * b.z())
 });
 
-impl_op!(*|a: &Vec3, b: f64| -> Vec3 {
-    Vec3 {
-        x: a.x() * b,
-        y: a.y() * b,
-        z: a.z() * b,
-    }
+impl_op_ex!(/|a: &Vec3, b: &Vec3| -> Vec3 {
+    Vec3::new(a.x() / b.x(), a.y() / b.y(), a.z() / b.z())
 });
 
-impl_op!(*|b: f64, a: &Vec3| -> Vec3 {
-    Vec3 {
-        x: a.x() * b,
-        y: a.y() * b,
-        z: a.z() * b,
-    }
-});
+impl_op_ex_commutative!(*|a: &Vec3, b: f64| -> Vec3 { a.map(|c| c * b) });
 
-impl_op!(*|b: f64, a: Vec3| -> Vec3 {
-    Vec3 {
-        x: a.x() * b,
-        y: a.y() * b,
-        z: a.z() * b,
-    }
-});
+impl_op_ex!(/|a: &Vec3, b: f64| -> Vec3 { a.map(|c| c / b) });
+
+impl_op_ex!(-|a: &Vec3| -> Vec3 { a.map(|c| -c) });
+
+impl_op_ex!(+=|a: &mut Vec3, b: &Vec3| { *a = *a + b });
+
+impl_op_ex!(-=|a: &mut Vec3, b: &Vec3| { *a = *a - b });
+
+impl_op_ex!(*=|a: &mut Vec3, b: f64| { *a = *a * b });
+
+impl_op_ex!(/=|a: &mut Vec3, b: f64| { *a = *a / b });
 
 #[test]
 fn origin_vector() {
@@ -187,3 +219,31 @@ fn mul_vector() {
     assert_eq!(point2.y(), -5.0);
     assert_eq!(point2.z(), 17.5);
 }
+
+#[test]
+fn component_wise_ops() {
+    let a = Vec3::new(1.0, -2.0, 4.0);
+    let b = Vec3::new(2.0, 0.5, -1.0);
+    assert_eq!(-a, Vec3::new(-1.0, 2.0, -4.0));
+    assert_eq!(a * b, Vec3::new(2.0, -1.0, -4.0));
+    assert_eq!(a / b, Vec3::new(0.5, -4.0, -4.0));
+    assert_eq!(a / 2.0, Vec3::new(0.5, -1.0, 2.0));
+    assert_eq!(a.min(&b), Vec3::new(1.0, -2.0, -1.0));
+    assert_eq!(a.max(&b), Vec3::new(2.0, 0.5, 4.0));
+    assert_eq!(a.abs(), Vec3::new(1.0, 2.0, 4.0));
+    assert_eq!(
+        (a.min_component(), a.max_component(), a.max_axis()),
+        (-2.0, 4.0, 2)
+    );
+
+    let mut c = a;
+    c += b;
+    c -= Vec3::new(1.0, 1.0, 1.0);
+    c *= 2.0;
+    c /= 4.0;
+    assert_eq!(c, Vec3::new(1.0, -1.25, 1.0));
+
+    c[1] = 7.0;
+    assert_eq!((c[0], c[1], c[2]), (1.0, 7.0, 1.0));
+    assert_eq!(c.as_slice(), &[1.0, 7.0, 1.0]);
+}
@@ -0,0 +1,216 @@
+use crate::geometry::mat3::Mat3;
+use crate::geometry::mat4::Mat4;
+use crate::geometry::quaternion::Quaternion;
+use crate::geometry::ray::Ray;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::hittable::transformed::hit_in_object_space;
+
+// Number of poses per pair of keyframes used to bound the motion.
+const BOUND_SAMPLES: usize = 32;
+
+// Where an animated object is at `time`: scaled, then rotated, then moved.
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe {
+    pub time: f64,
+    pub translation: Vec3,
+    pub rotation: Quaternion,
+    pub scale: Vec3,
+}
+
+impl Keyframe {
+    pub fn transform(&self) -> Mat4 {
+        Mat4::from_mat3(
+            &(self.rotation.to_mat3() * Mat3::scaling(self.scale)),
+            self.translation,
+        )
+    }
+
+    // Built from the parts instead of inverting the matrix, this runs for every ray.
+    fn inverse_transform(&self) -> Mat4 {
+        let inverse_scale = Vec3::new(1.0, 1.0, 1.0) / self.scale;
+        let linear = Mat3::scaling(inverse_scale) * self.rotation.conjugate().to_mat3();
+        Mat4::from_mat3(&linear, -(linear * self.translation))
+    }
+
+    // Translation and scale are interpolated linearly, the rotation along the shortest arc.
+    fn interpolate(&self, other: &Keyframe, time: f64) -> Keyframe {
+        let s = (time - self.time) / (other.time - self.time);
+        Keyframe {
+            time,
+            translation: self.translation + s * (other.translation - self.translation),
+            rotation: self.rotation.slerp(&other.rotation, s),
+            scale: self.scale + s * (other.scale - self.scale),
+        }
+    }
+}
+
+// Moves any object along keyframed transforms, rays see it in the pose of their time. Before the
+// first and after the last keyframe the object stays still.
+pub struct Animated {
+    object: Box<dyn Hittable + Send + Sync>,
+    // Sorted by time.
+    keyframes: Vec<Keyframe>,
+    bounding_box: AabbBoundingBox,
+}
+
+impl Animated {
+    pub fn new(
+        object: Box<dyn Hittable + Send + Sync>,
+        mut keyframes: Vec<Keyframe>,
+    ) -> Result<Animated, String> {
+        if keyframes.is_empty() {
+            return Err(String::from("an animation needs at least one keyframe"));
+        }
+        if keyframes.iter().any(|k| k.scale.as_slice().contains(&0.0)) {
+            return Err(String::from("keyframe scale must not be zero"));
+        }
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        let bounding_box = bound_motion(&object.get_bounding_box(), &keyframes);
+        Ok(Animated {
+            object,
+            keyframes,
+            bounding_box,
+        })
+    }
+
+    pub fn keyframe(&self, time: f64) -> Keyframe {
+        let next = self.keyframes.partition_point(|k| k.time <= time);
+        if next == 0 {
+            self.keyframes[0]
+        } else if next == self.keyframes.len() {
+            self.keyframes[next - 1]
+        } else {
+            self.keyframes[next - 1].interpolate(&self.keyframes[next], time)
+        }
+    }
+}
+
+// The union of the object's box over poses sampled between the keyframes. Between two samples a
+// corner travels along a gently curved path that strays less than half the distance between the
+// samples from both of them, so the union is padded by that much.
+fn bound_motion(object_box: &AabbBoundingBox, keyframes: &[Keyframe]) -> AabbBoundingBox {
+    let mut poses = vec![keyframes[0]];
+    for pair in keyframes.windows(2) {
+        if pair[1].time > pair[0].time {
+            poses.extend((1..=BOUND_SAMPLES).map(|i| {
+                let s = i as f64 / BOUND_SAMPLES as f64;
+                pair[0].interpolate(&pair[1], pair[0].time + s * (pair[1].time - pair[0].time))
+            }));
+        } else {
+            poses.push(pair[1]);
+        }
+    }
+
+    let corners = |pose: &Keyframe| {
+        let transform = pose.transform();
+        object_box
+            .corners()
+            .map(|corner| transform.transform_point(&corner))
+    };
+
+    let mut bounding_box = object_box.transform(&poses[0].transform());
+    let mut padding: f64 = 0.0;
+    let mut previous = corners(&poses[0]);
+    for pose in poses.iter().skip(1) {
+        let current = corners(pose);
+        for (a, b) in previous.iter().zip(current.iter()) {
+            padding = padding.max(0.5 * (b - a).len());
+        }
+        bounding_box = bounding_box.union(&object_box.transform(&pose.transform()));
+        previous = current;
+    }
+    let padding = Vec3::new(padding, padding, padding);
+    AabbBoundingBox::new(
+        bounding_box.min_point - padding,
+        bounding_box.max_point + padding,
+    )
+}
+
+impl Hittable for Animated {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let inverse = self.keyframe(ray.time()).inverse_transform();
+        hit_in_object_space(self.object.as_ref(), &inverse, ray, t_min, t_max)
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.bounding_box
+    }
+}
+
+#[cfg(test)]
+fn keyframe(time: f64, translation: Vec3, angle: f64) -> Keyframe {
+    Keyframe {
+        time,
+        translation,
+        rotation: Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), angle),
+        scale: Vec3::new(1.0, 1.0, 1.0),
+    }
+}
+
+#[cfg(test)]
+fn unit_cube() -> Box<dyn Hittable + Send + Sync> {
+    use crate::hittable::cube::Cube;
+    use crate::mesh::obj::default_material;
+
+    Box::new(Cube::new(
+        Vec3::origin(),
+        0.5,
+        Vec3::new(0.0, 0.0, -1.0),
+        default_material(),
+    ))
+}
+
+#[test]
+fn keyframes_are_interpolated() {
+    let animated = Animated::new(
+        unit_cube(),
+        vec![
+            keyframe(1.0, Vec3::new(4.0, 0.0, 0.0), std::f64::consts::FRAC_PI_2),
+            keyframe(0.0, Vec3::origin(), 0.0),
+        ],
+    )
+    .unwrap();
+
+    let pose = animated.keyframe(0.5);
+    assert!((pose.translation - Vec3::new(2.0, 0.0, 0.0)).len() < 1e-9);
+    let (_, angle) = pose.rotation.to_axis_angle();
+    assert!((angle - std::f64::consts::FRAC_PI_4).abs() < 1e-9);
+    assert_eq!(animated.keyframe(-1.0).translation, Vec3::origin());
+    assert_eq!(animated.keyframe(2.0).translation, Vec3::new(4.0, 0.0, 0.0));
+
+    // Seen from above at x = 2 the cube is only there halfway through.
+    let down = Vec3::new(0.0, -1.0, 0.0);
+    let origin = Vec3::new(2.0, 5.0, 0.0);
+    assert!(animated
+        .hit(&Ray::new_at_time(origin, down, 0.0), 0.0, f64::MAX)
+        .is_none());
+    let hit = animated
+        .hit(&Ray::new_at_time(origin, down, 0.5), 0.0, f64::MAX)
+        .unwrap();
+    assert!((hit.t - 4.5).abs() < 1e-9);
+    assert!((hit.normal - Vec3::new(0.0, 1.0, 0.0)).len() < 1e-9);
+}
+
+#[test]
+fn bounding_box_encloses_the_motion() {
+    let animated = Animated::new(
+        unit_cube(),
+        vec![
+            keyframe(0.0, Vec3::origin(), 0.0),
+            keyframe(1.0, Vec3::new(0.0, 0.0, 3.0), 2.0),
+            keyframe(2.0, Vec3::new(1.0, 2.0, 3.0), -1.0),
+        ],
+    )
+    .unwrap();
+    let bounding_box = animated.get_bounding_box();
+    let object_box = unit_cube().get_bounding_box();
+    for i in 0..=1000 {
+        let pose = animated.keyframe(i as f64 * 0.002);
+        let b = object_box.transform(&pose.transform());
+        assert_eq!(bounding_box.union(&b), bounding_box, "time {}", pose.time);
+    }
+
+    assert!(Animated::new(unit_cube(), vec![]).is_err());
+}
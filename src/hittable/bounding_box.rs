@@ -1,32 +1,138 @@
+use crate::geometry::mat4::Mat4;
 use crate::geometry::ray::Ray;
 use crate::geometry::vec3::Vec3;
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct AabbBoundingBox {
-    pub(crate) min_point: Vec3,
-    pub(crate) max_point: Vec3,
+    pub min_point: Vec3,
+    pub max_point: Vec3,
 }
 
 impl AabbBoundingBox {
-    pub fn is_hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
-        let min = self.min_point.as_slice();
-        let max = self.min_point.as_slice();
-        let r = ray.direction().as_slice();
-        let o = ray.origin().as_slice();
+    pub fn new(min_point: Vec3, max_point: Vec3) -> AabbBoundingBox {
+        AabbBoundingBox {
+            min_point,
+            max_point,
+        }
+    }
 
-        for a in 0..3 {
-            let p0 = min[a] - o[a] / r[a];
-            let p1 = max[a] - o[a] / r[a];
+    pub fn union(&self, other: &AabbBoundingBox) -> AabbBoundingBox {
+        AabbBoundingBox {
+            min_point: self.min_point.min(&other.min_point),
+            max_point: self.max_point.max(&other.max_point),
+        }
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min_point, self.max_point);
+        [0, 1, 2, 3, 4, 5, 6, 7].map(|corner| {
+            Vec3::new(
+                if corner & 1 == 0 { min.x() } else { max.x() },
+                if corner & 2 == 0 { min.y() } else { max.y() },
+                if corner & 4 == 0 { min.z() } else { max.z() },
+            )
+        })
+    }
+
+    // The box around the transformed corners, which holds everything the original box held.
+    pub fn transform(&self, transform: &Mat4) -> AabbBoundingBox {
+        self.corners()
+            .iter()
+            .map(|corner| {
+                let p = transform.transform_point(corner);
+                AabbBoundingBox::new(p, p)
+            })
+            .reduce(|a, b| a.union(&b))
+            .unwrap()
+    }
 
-            let q0 = p0.min(p1);
-            let q1 = p0.max(p1);
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max_point - self.min_point;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    pub fn is_hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.hit_interval(ray, t_min, t_max).is_some()
+    }
 
-            let r1 = t_min.max(q0);
-            let r2 = t_max.min(q1);
+    // Slab test, returns the part of [t_min, t_max] the ray spends inside the box. A zero
+    // direction component gives infinite slab distances, and the NaN of a ray starting exactly on
+    // such a slab is ignored by `max`/`min`, so both cases need no special handling.
+    pub fn hit_interval(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64)> {
+        let (min, max) = (&self.min_point, &self.max_point);
+        let (d, o) = (ray.direction(), ray.origin());
 
-            if r1 < r2 {
-                return false;
+        let (mut t0, mut t1) = (t_min, t_max);
+        for a in 0..3 {
+            let inv_d = 1.0 / d[a];
+            let mut near = (min[a] - o[a]) * inv_d;
+            let mut far = (max[a] - o[a]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut near, &mut far);
+            }
+
+            t0 = t0.max(near);
+            t1 = t1.min(far);
+            if t0 > t1 {
+                return None;
             }
         }
-        return true;
+        Some((t0, t1))
     }
 }
+
+#[cfg(test)]
+fn unit_box() -> AabbBoundingBox {
+    AabbBoundingBox::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0))
+}
+
+#[test]
+fn slab_test_hits_and_misses() {
+    let b = unit_box();
+    let hit =
+        |origin: Vec3, direction: Vec3| b.hit_interval(&Ray::new(origin, direction), 0.0, f64::MAX);
+
+    assert_eq!(
+        hit(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0)),
+        Some((1.0, 2.0))
+    );
+    assert_eq!(
+        hit(Vec3::new(2.0, 0.5, 0.5), Vec3::new(-2.0, 0.0, 0.0)),
+        Some((0.5, 1.0))
+    );
+    // Diagonal through two corners.
+    assert!(hit(Vec3::new(-1.0, -1.0, -1.0), Vec3::new(1.0, 1.0, 1.0)).is_some());
+    // Parallel to a slab and outside of it.
+    assert!(hit(Vec3::new(-1.0, 2.0, 0.5), Vec3::new(1.0, 0.0, 0.0)).is_none());
+    // Pointing away from the box.
+    assert!(hit(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(-1.0, 0.0, 0.0)).is_none());
+    // Passing next to the box.
+    assert!(hit(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 2.0, 0.0)).is_none());
+    // Starting inside.
+    assert_eq!(
+        hit(Vec3::new(0.5, 0.5, 0.5), Vec3::new(0.0, 0.0, 1.0)),
+        Some((0.0, 0.5))
+    );
+}
+
+#[test]
+fn slab_test_edge_cases() {
+    let b = unit_box();
+
+    // Zero direction components with the origin exactly on a slab boundary produce NaNs.
+    let on_boundary = Ray::new(Vec3::new(0.0, 0.5, -1.0), Vec3::new(0.0, 0.0, 1.0));
+    assert!(b.is_hit(&on_boundary, 0.0, f64::MAX));
+    let on_edge = Ray::new(Vec3::new(1.0, 1.0, -1.0), Vec3::new(0.0, 0.0, 1.0));
+    assert!(b.is_hit(&on_edge, 0.0, f64::MAX));
+
+    // The interval limits the hit.
+    let ray = Ray::new(Vec3::new(-1.0, 0.5, 0.5), Vec3::new(1.0, 0.0, 0.0));
+    assert!(!b.is_hit(&ray, 0.0, 0.5));
+    assert!(!b.is_hit(&ray, 2.5, 10.0));
+    assert_eq!(b.hit_interval(&ray, 1.5, 10.0), Some((1.5, 2.0)));
+
+    // A flat box is still hit.
+    let flat = AabbBoundingBox::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 0.0));
+    let ray = Ray::new(Vec3::new(0.5, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    assert_eq!(flat.hit_interval(&ray, 0.0, f64::MAX), Some((1.0, 1.0)));
+}
@@ -2,152 +2,568 @@ use crate::geometry::ray::Ray;
 use crate::geometry::vec3::Vec3;
 use crate::hittable::bounding_box::AabbBoundingBox;
 use crate::hittable::hittable::{HitRecord, Hittable};
-use image::hdr::HdrImageDecoderIterator;
-use rand::Rng;
-use std::cmp::Ordering::Less;
-use std::rc::Rc;
+use std::fmt;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use crate::material::color::Color;
-use crate::material::lambertian::Lambertian;
-use crate::material::material::Material;
 
 pub static COUNTER: AtomicUsize = AtomicUsize::new(0);
 
-pub struct BoundingBoxTree {
-    aabb_bounding_box: Option<AabbBoundingBox>,
-    left: Option<Box<BoundingBoxTree>>,
-    right: Option<Box<BoundingBoxTree>>,
-    objects: Vec<Arc<Box<dyn Hittable + Send + Sync>>>,
+// Number of buckets the centroids are binned into when evaluating split candidates.
+const NUM_BINS: usize = 16;
+
+// Traversal keeps this many pending nodes on the stack before spilling into a Vec.
+const STACK_SIZE: usize = 64;
+
+// A bounding volume hierarchy over anything with a bounding box, stored as a flat array of
+// nodes in depth first order. Primitives are referred to by their index in the slice the
+// hierarchy was built from.
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    // Primitive indices in leaf order, leaves cover contiguous ranges.
+    primitives: Vec<u32>,
 }
 
-impl BoundingBoxTree {
-    pub fn new(
-        objects: &[Arc<Box<dyn Hittable + Send + Sync>>],
-        leaf_size: usize,
-    ) -> BoundingBoxTree {
-        // Sort by a random axis.
-        let mut sorted_objects = vec![];
-        for object in objects {
-            sorted_objects.push(Arc::clone(&object))
-        }
-        let mut rng = rand::thread_rng();
-        let choose_axis = rng.gen_range(0..3);
-        sorted_objects.sort_by(|a, b| {
-            a.get_bounding_box().min_point.as_slice()[choose_axis]
-                .partial_cmp(&b.get_bounding_box().min_point.as_slice()[choose_axis])
-                .unwrap_or(Less)
-        });
+struct BvhNode {
+    bounding_box: AabbBoundingBox,
+    // Leaves cover `count` entries of `Bvh::primitives` starting at `offset`. Inner nodes have a
+    // count of 0, their first child follows them and the second one is at `offset`.
+    offset: u32,
+    count: u32,
+}
 
-        if objects.len() <= leaf_size {
-            return BoundingBoxTree {
-                aabb_bounding_box: Some(build_bounding_box(&sorted_objects)),
-                objects: sorted_objects,
-                left: None,
-                right: None,
-            };
-        }
+// Shape of a built tree, to compare builds.
+#[derive(Debug, PartialEq)]
+pub struct TreeStats {
+    pub depth: usize,
+    pub node_count: usize,
+    pub leaf_count: usize,
+    pub average_leaf_size: f64,
+}
 
-        let mid = sorted_objects.len() / 2;
-        let left: Option<Box<BoundingBoxTree>> =
-            Some(Box::new(BoundingBoxTree::new(&sorted_objects[0..mid], leaf_size)));
-        let right: Option<Box<BoundingBoxTree>> =
-            Some(Box::new(BoundingBoxTree::new(&sorted_objects[mid..], leaf_size)));
+impl fmt::Display for TreeStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "depth {}, {} nodes, {} leaves, {:.2} objects per leaf",
+            self.depth, self.node_count, self.leaf_count, self.average_leaf_size
+        )
+    }
+}
 
-        BoundingBoxTree {
-            aabb_bounding_box: Some(build_bounding_box(objects)),
-            objects: sorted_objects,
-            left,
-            right,
+// A primitive with its bounding box computed once for the build.
+struct BuildPrimitive {
+    index: u32,
+    bounding_box: AabbBoundingBox,
+    centroid: Vec3,
+}
+
+impl Bvh {
+    // Builds the hierarchy top down with the surface area heuristic: at every node the centroids
+    // are binned along each axis and the split with the lowest `area * count` cost on both sides
+    // is taken. Nodes with at most `leaf_size` primitives become leaves.
+    pub fn new(bounding_boxes: &[AabbBoundingBox], leaf_size: usize) -> Bvh {
+        let mut primitives = bounding_boxes
+            .iter()
+            .enumerate()
+            .map(|(index, &bounding_box)| BuildPrimitive {
+                index: index as u32,
+                bounding_box,
+                centroid: 0.5 * (bounding_box.min_point + bounding_box.max_point),
+            })
+            .collect::<Vec<BuildPrimitive>>();
+
+        let mut nodes = vec![];
+        build(&mut nodes, &mut primitives, 0, leaf_size.max(1));
+        Bvh {
+            nodes,
+            primitives: primitives.iter().map(|p| p.index).collect(),
         }
     }
 
-    pub fn objects(&self) -> &Vec<Arc<Box<dyn Hittable + Send + Sync>>> {
-        &self.objects
+    pub fn bounding_box(&self) -> AabbBoundingBox {
+        self.nodes[0].bounding_box
     }
-}
 
-impl Hittable for BoundingBoxTree {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        if !self.get_bounding_box().is_hit(ray, t_min, t_max) {
+    // Calls `hit` with the index of every primitive whose leaf the ray reaches, nearest leaves
+    // first. `hit` returns the distance and data of a hit within the given interval, the
+    // interval shrinks to the nearest hit so far and its data is returned.
+    pub fn traverse<T>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        t_max: f64,
+        mut hit: impl FnMut(usize, f64, f64) -> Option<(f64, T)>,
+    ) -> Option<T> {
+        if self.primitives.is_empty() {
             return None;
         }
+        let root = &self.nodes[0];
+        let (entry, _) = root.bounding_box.hit_interval(ray, t_min, t_max)?;
 
-        let mut nearest_hit_record: Option<HitRecord> = None;
+        let mut nearest = None;
+        let mut t_max = t_max;
+        let mut stack = NodeStack::new();
+        stack.push(0, entry);
+        while let Some((index, entry)) = stack.pop() {
+            // A closer hit may have been found since the node was pushed.
+            if entry > t_max {
+                continue;
+            }
+            let node = &self.nodes[index];
 
-        if self.left.is_none() && self.right.is_none() {
-            let mut nearest_t = 0.0;
-            for object in self.objects.iter() {
-                COUNTER.fetch_add(1, Ordering::Relaxed);
-                let maybe_hit_record = object.hit(&ray, 0.0001, f64::MAX);
-                if maybe_hit_record.is_none() {
-                    continue;
+            if node.count > 0 {
+                let start = node.offset as usize;
+                for &primitive in self.primitives[start..start + node.count as usize].iter() {
+                    COUNTER.fetch_add(1, Ordering::Relaxed);
+                    if let Some((t, data)) = hit(primitive as usize, t_min, t_max) {
+                        t_max = t;
+                        nearest = Some(data);
+                    }
                 }
+                continue;
+            }
 
-                let hit_record = maybe_hit_record.unwrap();
-                if nearest_hit_record.is_none() || hit_record.t < nearest_t {
-                    nearest_t = hit_record.t;
-                    nearest_hit_record = Some(hit_record);
+            // Push the far child first so the near one is visited first.
+            let (first, second) = (index + 1, node.offset as usize);
+            let first_entry = self.nodes[first]
+                .bounding_box
+                .hit_interval(ray, t_min, t_max);
+            let second_entry = self.nodes[second]
+                .bounding_box
+                .hit_interval(ray, t_min, t_max);
+            match (first_entry, second_entry) {
+                (Some((first_t, _)), Some((second_t, _))) => {
+                    if first_t <= second_t {
+                        stack.push(second, second_t);
+                        stack.push(first, first_t);
+                    } else {
+                        stack.push(first, first_t);
+                        stack.push(second, second_t);
+                    }
                 }
+                (Some((first_t, _)), None) => stack.push(first, first_t),
+                (None, Some((second_t, _))) => stack.push(second, second_t),
+                (None, None) => {}
+            }
+        }
+        nearest
+    }
+
+    pub fn stats(&self) -> TreeStats {
+        let mut stats = TreeStats {
+            depth: 0,
+            node_count: self.nodes.len(),
+            leaf_count: 0,
+            average_leaf_size: 0.0,
+        };
+        if self.primitives.is_empty() {
+            stats.depth = 1;
+            stats.leaf_count = 1;
+            return stats;
+        }
+        let mut stack = vec![(0, 1)];
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            stats.depth = stats.depth.max(depth);
+            if node.count > 0 {
+                stats.leaf_count += 1;
+            } else {
+                stack.push((index + 1, depth + 1));
+                stack.push((node.offset as usize, depth + 1));
             }
-            return nearest_hit_record;
         }
+        stats.average_leaf_size = self.primitives.len() as f64 / stats.leaf_count as f64;
+        stats
+    }
+}
 
-        let left_hit = self.left.as_ref().unwrap().hit(ray, t_min, t_max);
-        let right_hit = self.right.as_ref().unwrap().hit(ray, t_min, t_max);
+// Fixed size stack of (node, entry distance) that only allocates for unusually deep trees.
+struct NodeStack {
+    entries: [(usize, f64); STACK_SIZE],
+    len: usize,
+    overflow: Vec<(usize, f64)>,
+}
 
-        if left_hit.is_none() && right_hit.is_none() {
-            return None;
+impl NodeStack {
+    fn new() -> NodeStack {
+        NodeStack {
+            entries: [(0, 0.0); STACK_SIZE],
+            len: 0,
+            overflow: vec![],
         }
+    }
 
-        if left_hit.is_none() {
-            return right_hit;
+    fn push(&mut self, index: usize, entry: f64) {
+        if self.len < STACK_SIZE {
+            self.entries[self.len] = (index, entry);
+            self.len += 1;
+        } else {
+            self.overflow.push((index, entry));
         }
+    }
 
-        if right_hit.is_none() {
-            return left_hit;
+    fn pop(&mut self) -> Option<(usize, f64)> {
+        if let Some(entry) = self.overflow.pop() {
+            return Some(entry);
         }
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.entries[self.len])
+    }
+}
 
-        let left_hit = left_hit.unwrap();
-        let right_hit = right_hit.unwrap();
+fn build(
+    nodes: &mut Vec<BvhNode>,
+    primitives: &mut [BuildPrimitive],
+    offset: usize,
+    leaf_size: usize,
+) {
+    let index = nodes.len();
+    nodes.push(BvhNode {
+        bounding_box: union(primitives.iter().map(|p| p.bounding_box)),
+        offset: offset as u32,
+        count: primitives.len() as u32,
+    });
+    // An empty hierarchy is a single empty leaf.
+    if primitives.len() <= leaf_size {
+        return;
+    }
 
-        return if left_hit.t < right_hit.t {
-            Some(left_hit)
-        } else {
-            Some(right_hit)
+    let mid = match best_split(primitives) {
+        Some(split) => partition(primitives, |p| split.bin(p) < split.bin),
+        // All centroids coincide, any split is as good as another.
+        None => primitives.len() / 2,
+    };
+    let (left, right) = primitives.split_at_mut(mid);
+    build(nodes, left, offset, leaf_size);
+    let second = nodes.len();
+    build(nodes, right, offset + mid, leaf_size);
+    nodes[index].offset = second as u32;
+    nodes[index].count = 0;
+}
+
+// Primitives whose centroid falls in a bin below `bin` along `axis` go to the left child.
+#[derive(Copy, Clone)]
+struct Split {
+    axis: usize,
+    min: f64,
+    extent: f64,
+    bin: usize,
+}
+
+impl Split {
+    fn bin(&self, primitive: &BuildPrimitive) -> usize {
+        let offset = (primitive.centroid.as_slice()[self.axis] - self.min) / self.extent;
+        ((offset * NUM_BINS as f64) as usize).min(NUM_BINS - 1)
+    }
+}
+
+// Returns the cheapest split, None when the centroids can't be separated.
+fn best_split(primitives: &[BuildPrimitive]) -> Option<Split> {
+    let centroid_bounds = union(
+        primitives
+            .iter()
+            .map(|o| AabbBoundingBox::new(o.centroid, o.centroid)),
+    );
+    let min = centroid_bounds.min_point.as_slice();
+    let max = centroid_bounds.max_point.as_slice();
+
+    let mut best: Option<(f64, Split)> = None;
+    for axis in 0..3 {
+        let extent = max[axis] - min[axis];
+        if extent <= 0.0 {
+            continue;
+        }
+        let mut split = Split {
+            axis,
+            min: min[axis],
+            extent,
+            bin: 0,
         };
+
+        let mut counts = [0usize; NUM_BINS];
+        let mut bounds: [Option<AabbBoundingBox>; NUM_BINS] = [None; NUM_BINS];
+        for primitive in primitives {
+            let bin = split.bin(primitive);
+            counts[bin] += 1;
+            bounds[bin] = Some(match bounds[bin] {
+                Some(b) => b.union(&primitive.bounding_box),
+                None => primitive.bounding_box,
+            });
+        }
+
+        // Sweep from the right to get the cost of every right side, then from the left.
+        let mut right_costs = [0.0; NUM_BINS];
+        let mut right_box: Option<AabbBoundingBox> = None;
+        let mut right_count = 0;
+        for bin in (1..NUM_BINS).rev() {
+            right_count += counts[bin];
+            right_box = union_option(right_box, bounds[bin]);
+            right_costs[bin] = right_box.map_or(0.0, |b| b.surface_area()) * right_count as f64;
+        }
+        let mut left_box: Option<AabbBoundingBox> = None;
+        let mut left_count = 0;
+        for bin in 1..NUM_BINS {
+            left_count += counts[bin - 1];
+            left_box = union_option(left_box, bounds[bin - 1]);
+            if left_count == 0 || left_count == primitives.len() {
+                continue;
+            }
+            let cost =
+                left_box.map_or(0.0, |b| b.surface_area()) * left_count as f64 + right_costs[bin];
+            if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+                split.bin = bin;
+                best = Some((cost, split));
+            }
+        }
     }
+    best.map(|(_, split)| split)
+}
 
-    fn get_bounding_box(&self) -> AabbBoundingBox {
-        let b = self.aabb_bounding_box.as_ref().unwrap();
-        AabbBoundingBox {
-            min_point: Vec3::new(b.min_point.x(), b.min_point.y(), b.min_point.z()),
-            max_point: Vec3::new(b.max_point.x(), b.max_point.y(), b.max_point.z()),
+// Moves the primitives matching `left` to the front and returns how many there are.
+fn partition(primitives: &mut [BuildPrimitive], left: impl Fn(&BuildPrimitive) -> bool) -> usize {
+    let mut mid = 0;
+    for i in 0..primitives.len() {
+        if left(&primitives[i]) {
+            primitives.swap(i, mid);
+            mid += 1;
         }
     }
+    mid
 }
 
-fn build_bounding_box(objects: &[Arc<Box<dyn Hittable + Send + Sync>>]) -> AabbBoundingBox {
-    let mut min_x = objects[0].get_bounding_box().min_point.x();
-    let mut min_y = objects[0].get_bounding_box().min_point.y();
-    let mut min_z = objects[0].get_bounding_box().min_point.z();
+fn union(boxes: impl Iterator<Item = AabbBoundingBox>) -> AabbBoundingBox {
+    boxes
+        .reduce(|a, b| a.union(&b))
+        .unwrap_or_else(|| AabbBoundingBox::new(Vec3::origin(), Vec3::origin()))
+}
 
-    let mut max_x = objects[0].get_bounding_box().max_point.x();
-    let mut max_y = objects[0].get_bounding_box().max_point.y();
-    let mut max_z = objects[0].get_bounding_box().max_point.z();
+fn union_option(a: Option<AabbBoundingBox>, b: Option<AabbBoundingBox>) -> Option<AabbBoundingBox> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.union(&b)),
+        (a, None) => a,
+        (None, b) => b,
+    }
+}
 
-    for o in objects {
-        min_x = min_x.min(o.get_bounding_box().min_point.x());
-        min_y = min_y.min(o.get_bounding_box().min_point.y());
-        min_z = min_z.min(o.get_bounding_box().min_point.z());
+// The world's objects in a `Bvh`.
+pub struct BoundingBoxTree {
+    bvh: Bvh,
+    objects: Vec<Arc<Box<dyn Hittable + Send + Sync>>>,
+}
 
-        max_x = max_x.max(o.get_bounding_box().max_point.x());
-        max_y = max_y.max(o.get_bounding_box().max_point.y());
-        max_z = max_z.max(o.get_bounding_box().max_point.z());
+impl BoundingBoxTree {
+    pub fn new(
+        objects: &[Arc<Box<dyn Hittable + Send + Sync>>],
+        leaf_size: usize,
+    ) -> BoundingBoxTree {
+        let bounding_boxes = objects
+            .iter()
+            .map(|object| object.get_bounding_box())
+            .collect::<Vec<AabbBoundingBox>>();
+        BoundingBoxTree {
+            bvh: Bvh::new(&bounding_boxes, leaf_size),
+            objects: objects.iter().map(Arc::clone).collect(),
+        }
     }
 
-    AabbBoundingBox {
-        min_point: Vec3::new(min_x, min_y, min_z),
-        max_point: Vec3::new(max_x, max_y, max_z),
+    pub fn stats(&self) -> TreeStats {
+        self.bvh.stats()
     }
+
+    pub fn object(&self, index: usize) -> &(dyn Hittable + Send + Sync) {
+        self.objects[index].as_ref().as_ref()
+    }
+
+    // Like `hit`, with the index of the object that was hit.
+    pub fn hit_object(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(usize, HitRecord<'_>)> {
+        self.bvh.traverse(ray, t_min, t_max, |i, t_min, t_max| {
+            self.objects[i]
+                .hit(ray, t_min, t_max)
+                .map(|hit_record| (hit_record.t, (i, hit_record)))
+        })
+    }
+}
+
+impl Hittable for BoundingBoxTree {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.hit_object(ray, t_min, t_max)
+            .map(|(_, hit_record)| hit_record)
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.bvh.bounding_box()
+    }
+
+    // Multiplies the transmittance of every object along the ray. Once the light is blocked the
+    // traversal is ended by reporting a hit at `t_min`.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        self.bvh.traverse(ray, t_min, t_max, |i, t_min, t_max| {
+            transmittance *= self.objects[i].transmittance(ray, t_min, t_max);
+            if transmittance == 0.0 {
+                Some((t_min, ()))
+            } else {
+                None
+            }
+        });
+        transmittance
+    }
+}
+
+#[cfg(test)]
+fn spheres_at(centers: &[Vec3]) -> Vec<Arc<Box<dyn Hittable + Send + Sync>>> {
+    use crate::hittable::sphere::Sphere;
+    use crate::mesh::obj::default_material;
+
+    let grey = default_material();
+    centers
+        .iter()
+        .map(|&center| -> Arc<Box<dyn Hittable + Send + Sync>> {
+            Arc::new(Box::new(Sphere {
+                center,
+                radius: 0.1,
+                material: Arc::clone(&grey),
+            }))
+        })
+        .collect()
+}
+
+#[test]
+fn sah_split_separates_clusters() {
+    // Two tight clusters far apart along x, interleaved in the input.
+    let centers = (0..20)
+        .map(|i| {
+            let x = if i % 2 == 0 { -50.0 } else { 50.0 };
+            Vec3::new(x + (i as f64) * 0.01, (i as f64) * 0.1, 0.0)
+        })
+        .collect::<Vec<Vec3>>();
+    let tree = BoundingBoxTree::new(&spheres_at(&centers), 4);
+
+    let nodes = &tree.bvh.nodes;
+    for child in [1, nodes[0].offset as usize] {
+        let child_box = nodes[child].bounding_box;
+        assert!(child_box.max_point.x() - child_box.min_point.x() < 1.0);
+    }
+}
+
+#[test]
+fn build_is_deterministic_and_respects_leaf_size() {
+    let centers = (0..100)
+        .map(|_| Vec3::random(-10.0, 10.0))
+        .collect::<Vec<Vec3>>();
+    let objects = spheres_at(&centers);
+
+    let stats = BoundingBoxTree::new(&objects, 3).stats();
+    assert_eq!(stats, BoundingBoxTree::new(&objects, 3).stats());
+    assert_eq!(stats.node_count, 2 * stats.leaf_count - 1);
+    assert!(stats.average_leaf_size <= 3.0);
+    assert!((stats.average_leaf_size * stats.leaf_count as f64 - 100.0).abs() < 1e-9);
+
+    // Objects with the same centroid still get split down to the leaf size.
+    let stacked = spheres_at(&[Vec3::origin(); 10]);
+    let stats = BoundingBoxTree::new(&stacked, 2).stats();
+    assert!(stats.average_leaf_size <= 2.0);
+    assert_eq!(stats.depth, 4);
+}
+
+#[cfg(test)]
+fn random_scene(num_objects: usize) -> Vec<Arc<Box<dyn Hittable + Send + Sync>>> {
+    use crate::hittable::cube::Cube;
+    use crate::hittable::quad::Quad;
+    use crate::hittable::sphere::Sphere;
+    use crate::hittable::triangle::Triangle;
+    use crate::mesh::obj::default_material;
+
+    let grey = default_material();
+    (0..num_objects)
+        .map(|i| -> Arc<Box<dyn Hittable + Send + Sync>> {
+            let center = Vec3::random(-10.0, 10.0);
+            let material = Arc::clone(&grey);
+            Arc::new(match i % 4 {
+                0 => Box::new(Sphere {
+                    center,
+                    radius: Vec3::random(0.1, 2.0).x(),
+                    material,
+                }),
+                1 => Box::new(Triangle::new(
+                    center + Vec3::random(-2.0, 2.0),
+                    center + Vec3::random(-2.0, 2.0),
+                    center + Vec3::random(-2.0, 2.0),
+                    material,
+                )),
+                2 => {
+                    let (a, b) = (Vec3::random(-2.0, 2.0), Vec3::random(-2.0, 2.0));
+                    Box::new(Quad::new(
+                        center,
+                        center + a,
+                        center + a + b,
+                        center + b,
+                        &material,
+                    ))
+                }
+                _ => Box::new(Cube::new(
+                    center,
+                    Vec3::random(0.1, 1.0).x(),
+                    center + Vec3::random(-1.0, 1.0),
+                    material,
+                )),
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn tree_hits_match_brute_force() {
+    for leaf_size in [1, 4] {
+        for num_objects in [1, 7, 200] {
+            let objects = random_scene(num_objects);
+            let tree = BoundingBoxTree::new(&objects, leaf_size);
+
+            for _ in 0..500 {
+                let ray = Ray::new(Vec3::random(-15.0, 15.0), Vec3::random(-1.0, 1.0));
+                let t_min = 0.0001;
+                let expected = objects
+                    .iter()
+                    .filter_map(|object| object.hit(&ray, t_min, f64::MAX))
+                    .map(|hit| hit.t)
+                    .fold(None, |nearest: Option<f64>, t| {
+                        Some(nearest.map_or(t, |n| n.min(t)))
+                    });
+                let actual = tree.hit(&ray, t_min, f64::MAX).map(|hit| hit.t);
+                assert_eq!(actual, expected, "{} objects", num_objects);
+
+                // Surfaces block shadow rays completely.
+                let blocked = objects.iter().any(|o| o.hit(&ray, t_min, 10.0).is_some());
+                let expected = if blocked { 0.0 } else { 1.0 };
+                assert_eq!(tree.transmittance(&ray, t_min, 10.0), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn tree_honors_the_ray_interval() {
+    let objects = spheres_at(&[Vec3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, -10.0)]);
+    let tree = BoundingBoxTree::new(&objects, 1);
+    let ray = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+
+    let t = |t_min: f64, t_max: f64| tree.hit(&ray, t_min, t_max).map(|hit| hit.t);
+    assert!((t(0.0, f64::MAX).unwrap() - 4.9).abs() < 1e-9);
+    assert!((t(6.0, f64::MAX).unwrap() - 9.9).abs() < 1e-9);
+    assert_eq!(t(0.0, 4.0), None);
+    assert_eq!(t(5.2, 9.5), None);
+    assert_eq!(tree.transmittance(&ray, 0.0, 4.0), 1.0);
+    assert_eq!(tree.transmittance(&ray, 5.2, 9.5), 1.0);
+    assert_eq!(tree.transmittance(&ray, 0.0, f64::MAX), 0.0);
+}
+
+#[test]
+fn empty_tree_has_no_hits() {
+    let tree = BoundingBoxTree::new(&[], 4);
+    let ray = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+    assert!(tree.hit(&ray, 0.0, f64::MAX).is_none());
+    assert_eq!(tree.stats().node_count, 1);
 }
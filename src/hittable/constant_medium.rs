@@ -0,0 +1,128 @@
+use crate::geometry::ray::Ray;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::material::material::Material;
+use crate::scene::writer::{SceneWriter, WriteScene};
+use rand::Rng;
+use std::sync::Arc;
+
+// Fog or smoke filling a closed boundary, rays scatter at exponentially distributed distances
+// inside it with `density` as the rate per unit length. The boundary must be convex: a ray is
+// only followed from where it enters the boundary to where it first leaves it.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable + Send + Sync>,
+    density: f64,
+    // Usually an `Isotropic` material.
+    phase_function: Arc<Box<dyn Material + Send + Sync>>,
+}
+
+impl ConstantMedium {
+    pub fn new(
+        boundary: Box<dyn Hittable + Send + Sync>,
+        density: f64,
+        phase_function: Arc<Box<dyn Material + Send + Sync>>,
+    ) -> ConstantMedium {
+        ConstantMedium {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        // Where the ray's line enters and leaves the boundary, the ray may start inside.
+        let enter = self.boundary.hit(ray, f64::NEG_INFINITY, f64::INFINITY)?.t;
+        let leave = self.boundary.hit(ray, enter + 0.0001, f64::INFINITY)?.t;
+
+        let enter = enter.max(t_min);
+        let leave = leave.min(t_max);
+        if enter >= leave {
+            return None;
+        }
+
+        let ray_length = ray.direction().len();
+        let distance_inside = (leave - enter) * ray_length;
+        let hit_distance = -rand::thread_rng().gen::<f64>().ln() / self.density;
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = enter + hit_distance / ray_length;
+        // The scattering doesn't depend on the normal.
+        Some(HitRecord {
+            hit_point: ray.at(t),
+            normal: Vec3::new(1.0, 0.0, 0.0),
+            front_face: true,
+            t,
+            u: 0.0,
+            v: 0.0,
+            material: self.phase_function.as_ref().as_ref(),
+        })
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.boundary.get_bounding_box()
+    }
+}
+
+impl WriteScene for ConstantMedium {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let phase = writer.material(&self.phase_function)?;
+        writer.push_modifier(format!("density={} phase={}", self.density, phase));
+        let result = writer.write_object(self.boundary.as_ref());
+        writer.pop_modifier();
+        result
+    }
+}
+
+#[cfg(test)]
+fn fog(density: f64) -> ConstantMedium {
+    use crate::hittable::sphere::Sphere;
+    use crate::material::color::Color;
+    use crate::material::isotropic::Isotropic;
+
+    let phase: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Isotropic::new_from_color(Color::white())));
+    ConstantMedium::new(
+        Box::new(Sphere {
+            center: Vec3::origin(),
+            radius: 1.0,
+            material: Arc::clone(&phase),
+        }),
+        density,
+        phase,
+    )
+}
+
+#[test]
+fn scattering_follows_the_density() {
+    // The chance of crossing 2 units of fog without scattering is exp(-2 * density).
+    let fog = fog(0.5);
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -2.0));
+    let n = 20000;
+    let mut passed = 0;
+    for _ in 0..n {
+        match fog.hit(&ray, 0.0001, f64::MAX) {
+            Some(hit) => {
+                assert!(hit.t >= 2.0 && hit.t <= 3.0);
+                assert!(hit.hit_point.len() <= 1.0 + 1e-9);
+            }
+            None => passed += 1,
+        }
+    }
+    let expected = (-1.0f64).exp();
+    assert!((passed as f64 / n as f64 - expected).abs() < 0.02);
+
+    // Starting inside, only the part in front of the ray counts.
+    let inside = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+    for _ in 0..100 {
+        if let Some(hit) = fog.hit(&inside, 0.0001, f64::MAX) {
+            assert!(hit.t > 0.0 && hit.t <= 1.0);
+        }
+    }
+    // The interval is honored.
+    assert!(fog.hit(&ray, 0.0001, 1.0).is_none());
+}
@@ -1,24 +1,34 @@
+use crate::geometry::mat3::Mat3;
 use crate::geometry::ray::Ray;
-use crate::geometry::vec3::{cross, Vec3};
+use crate::geometry::vec3::Vec3;
 use crate::hittable::bounding_box::AabbBoundingBox;
 use crate::hittable::hittable::{HitRecord, Hittable};
 use crate::hittable::triangle::Triangle;
-use crate::material::color::Color;
+use crate::material::image_texture::load_image;
 use crate::material::lambertian::Lambertian;
 use crate::material::material::Material;
-use crate::material::metal::Metal;
 use crate::material::texture::Texture;
 use crate::material::triangle_image_texture::TriangleImageTexture;
-use image::io::Reader as ImageReader;
+use crate::scene::writer::{format_path, format_vec3, SceneWriter, WriteScene};
 use image::GenericImageView;
 use std::sync::Arc;
 
 pub struct Cube {
-    forward: Vec3,
-    right: Vec3,
-    up: Vec3,
     points: Vec<Vec3>,
     triangles: Vec<Triangle>,
+
+    // How the cube was built, kept to write it back to a scene file.
+    center: Vec3,
+    to: Vec3,
+    width: f64,
+    height: f64,
+    depth: f64,
+    surface: CubeSurface,
+}
+
+enum CubeSurface {
+    Material(Arc<Box<dyn Material + Send + Sync>>),
+    Mapped(String),
 }
 
 impl Cube {
@@ -28,29 +38,11 @@ impl Cube {
         to: Vec3,
         material: Arc<Box<dyn Material + Send + Sync>>,
     ) -> Cube {
-        // TODO(chesetti): Add rotation around (center - to)
-        let vup = Vec3::new(0.0, 1.0, 0.0);
-        let forward = (center - to).normalize();
-        let right: Vec3 = cross(&vup, &forward).normalize();
-        let up: Vec3 = cross(&forward, &right).normalize();
-
-        let forward = forward * scale;
-        let right = right * scale;
-        let up = up * scale;
-
-        let points = build_points(forward, right, up, center);
-        let triangles = build_same_material(&points, material);
-
-        Cube {
-            forward,
-            right,
-            up,
-            triangles,
-            points,
-        }
+        let side = 2.0 * scale;
+        Cube::new_cuboid(center, to, side, side, side, material)
     }
 
-    pub fn newCuboid(
+    pub fn new_cuboid(
         center: Vec3,
         to: Vec3,
         width: f64,
@@ -58,25 +50,24 @@ impl Cube {
         depth: f64,
         material: Arc<Box<dyn Material + Send + Sync>>,
     ) -> Cube {
-        // TODO(chesetti): Add rotation around (center - to)
-        let vup = Vec3::new(0.0, 1.0, 0.0);
-        let forward = (center - to).normalize();
-        let right: Vec3 = cross(&vup, &forward).normalize();
-        let up: Vec3 = cross(&forward, &right).normalize();
-
-        let forward = forward * (depth * 0.5);
-        let right = right * (width * 0.5);
-        let up = up * (height * 0.5);
+        // To rotate around (center - to), wrap the cube in a `Transformed`.
+        let basis = Mat3::basis(center - to, Vec3::new(0.0, 1.0, 0.0));
+        let right = basis.column(0) * (width * 0.5);
+        let up = basis.column(1) * (height * 0.5);
+        let forward = basis.column(2) * (depth * 0.5);
 
         let points = build_points(forward, right, up, center);
-        let triangles = build_same_material(&points, material);
+        let triangles = build_same_material(&points, Arc::clone(&material));
 
         Cube {
-            forward,
-            right,
-            up,
             triangles,
             points,
+            center,
+            to,
+            width,
+            height,
+            depth,
+            surface: CubeSurface::Material(material),
         }
     }
 
@@ -87,36 +78,35 @@ impl Cube {
         width: f64,
         height: f64,
         depth: f64,
-    ) -> Cube {
-        let vup = Vec3::new(0.0, 1.0, 0.0);
-        let forward = (center - to).normalize();
-        let right: Vec3 = cross(&vup, &forward).normalize();
-        let up: Vec3 = cross(&forward, &right).normalize();
-
-        let forward = forward * (depth * 0.5);
-        let right = right * (width * 0.5);
-        let up = up * (height * 0.5);
+    ) -> Result<Cube, String> {
+        let basis = Mat3::basis(center - to, Vec3::new(0.0, 1.0, 0.0));
+        let right = basis.column(0) * (width * 0.5);
+        let up = basis.column(1) * (height * 0.5);
+        let forward = basis.column(2) * (depth * 0.5);
 
         let points = build_points(forward, right, up, center);
-        let triangles = build_die_material(img_path, &points);
+        let triangles = build_die_material(img_path, &points)?;
 
-        Cube {
-            forward,
-            right,
-            up,
+        Ok(Cube {
             triangles,
             points,
-        }
+            center,
+            to,
+            width,
+            height,
+            depth,
+            surface: CubeSurface::Mapped(String::from(img_path)),
+        })
     }
 }
 
 impl Hittable for Cube {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let mut nearest_hit_record: Option<HitRecord> = None;
         let mut nearest_t = 0.0;
 
         for triangle in self.triangles.iter() {
-            let maybe_hit_record = triangle.hit(&ray, t_min, t_max);
+            let maybe_hit_record = triangle.hit(ray, t_min, t_max);
             if maybe_hit_record.is_none() {
                 continue;
             }
@@ -156,8 +146,43 @@ impl Hittable for Cube {
     }
 }
 
+impl WriteScene for Cube {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let shape = format!(
+            "center={} to={} width={} height={} depth={}",
+            format_vec3(&self.center),
+            format_vec3(&self.to),
+            self.width,
+            self.height,
+            self.depth
+        );
+        let statement = match &self.surface {
+            // Cubes with equal sides are written as `cube` with their side length as `size`.
+            CubeSurface::Material(material)
+                if self.width == self.height && self.width == self.depth =>
+            {
+                format!(
+                    "cube center={} size={} to={} material={}",
+                    format_vec3(&self.center),
+                    self.width,
+                    format_vec3(&self.to),
+                    writer.material(material)?
+                )
+            }
+            CubeSurface::Material(material) => {
+                format!("cuboid {} material={}", shape, writer.material(material)?)
+            }
+            CubeSurface::Mapped(img_path) => {
+                format!("mapped_cube image={} {}", format_path(img_path)?, shape)
+            }
+        };
+        writer.object(statement);
+        Ok(())
+    }
+}
+
 fn build_triangle(
-    points: &Vec<Vec3>,
+    points: &[Vec3],
     p1: usize,
     p2: usize,
     p3: usize,
@@ -167,33 +192,33 @@ fn build_triangle(
         points[p1 - 1],
         points[p2 - 1],
         points[p3 - 1],
-        Arc::clone(&material),
+        Arc::clone(material),
     )
 }
 
 fn build_same_material(
-    points: &Vec<Vec3>,
+    points: &[Vec3],
     material: Arc<Box<dyn Material + Send + Sync>>,
 ) -> Vec<Triangle> {
     vec![
         // Face 1
-        build_triangle(&points, 1, 2, 3, &material),
-        build_triangle(&points, 4, 2, 3, &material),
+        build_triangle(points, 1, 2, 3, &material),
+        build_triangle(points, 4, 2, 3, &material),
         // Face 2
-        build_triangle(&points, 5, 6, 7, &material),
-        build_triangle(&points, 8, 6, 7, &material),
+        build_triangle(points, 5, 6, 7, &material),
+        build_triangle(points, 8, 6, 7, &material),
         // Face 3
-        build_triangle(&points, 6, 2, 8, &material),
-        build_triangle(&points, 4, 2, 8, &material),
+        build_triangle(points, 6, 2, 8, &material),
+        build_triangle(points, 4, 2, 8, &material),
         // Face 4
-        build_triangle(&points, 1, 5, 3, &material),
-        build_triangle(&points, 7, 5, 3, &material),
+        build_triangle(points, 1, 5, 3, &material),
+        build_triangle(points, 7, 5, 3, &material),
         // Face 5
-        build_triangle(&points, 1, 5, 2, &material),
-        build_triangle(&points, 6, 5, 2, &material),
+        build_triangle(points, 1, 5, 2, &material),
+        build_triangle(points, 6, 5, 2, &material),
         // Face 6
-        build_triangle(&points, 3, 7, 4, &material),
-        build_triangle(&points, 8, 7, 4, &material),
+        build_triangle(points, 3, 7, 4, &material),
+        build_triangle(points, 8, 7, 4, &material),
     ]
 }
 
@@ -215,9 +240,9 @@ fn build_uv_mapped_triangle(
     x: u32,
     y: u32,
     is_bot: bool,
-) -> Arc<Box<dyn Material + Send + Sync>> {
+) -> Result<Arc<Box<dyn Material + Send + Sync>>, String> {
     // TODO(chesetti): Send reference to image instead of reading everytime?
-    let img = ImageReader::open(img_path).unwrap().decode().unwrap();
+    let img = load_image(img_path)?;
     let width = img.width();
     let height = img.height();
 
@@ -231,117 +256,118 @@ fn build_uv_mapped_triangle(
     let xmax = ((x + 1) * sq_width) as f64;
     let ymax = ((y + 1) * sq_height) as f64;
 
-    let triangle_texture: Box<dyn Texture + Send + Sync>;
-    if is_bot {
-        triangle_texture = Box::new(TriangleImageTexture::new(
+    let triangle_texture: Box<dyn Texture + Send + Sync> = if is_bot {
+        Box::new(TriangleImageTexture::new(
             img_path,
             Vec3::new(xmin, ymax, 0.0),
             Vec3::new(xmax, ymax, 0.0),
             Vec3::new(xmin, ymin, 0.0),
-        ));
+        )?)
     } else {
-        triangle_texture = Box::new(TriangleImageTexture::new(
+        Box::new(TriangleImageTexture::new(
             img_path,
             Vec3::new(xmax, ymin, 0.0),
             Vec3::new(xmax, ymax, 0.0),
             Vec3::new(xmin, ymin, 0.0),
-        ));
-    }
+        )?)
+    };
 
-    Arc::new(Box::new(Lambertian::new_from_texture(triangle_texture)))
+    Ok(Arc::new(Box::new(Lambertian::new_from_texture(
+        triangle_texture,
+    ))))
 }
 
-fn build_die_material(img_path: &str, points: &Vec<Vec3>) -> Vec<Triangle> {
-    vec![
+fn build_die_material(img_path: &str, points: &[Vec3]) -> Result<Vec<Triangle>, String> {
+    Ok(vec![
         // Face 1
         build_triangle(
-            &points,
+            points,
             1,
             3,
             2,
-            &build_uv_mapped_triangle(img_path, 0, 1, false),
+            &build_uv_mapped_triangle(img_path, 0, 1, false)?,
         ),
         build_triangle(
-            &points,
+            points,
             4,
             3,
             2,
-            &build_uv_mapped_triangle(img_path, 0, 1, true),
+            &build_uv_mapped_triangle(img_path, 0, 1, true)?,
         ),
         // Face 2
         build_triangle(
-            &points,
+            points,
             7,
             5,
             8,
-            &build_uv_mapped_triangle(img_path, 1, 1, false),
+            &build_uv_mapped_triangle(img_path, 1, 1, false)?,
         ),
         build_triangle(
-            &points,
+            points,
             6,
             5,
             8,
-            &build_uv_mapped_triangle(img_path, 1, 1, true),
+            &build_uv_mapped_triangle(img_path, 1, 1, true)?,
         ),
         // Face 3
         build_triangle(
-            &points,
+            points,
             6,
             2,
             8,
-            &build_uv_mapped_triangle(img_path, 2, 0, false),
+            &build_uv_mapped_triangle(img_path, 2, 0, false)?,
         ),
         build_triangle(
-            &points,
+            points,
             4,
             2,
             8,
-            &build_uv_mapped_triangle(img_path, 2, 0, true),
+            &build_uv_mapped_triangle(img_path, 2, 0, true)?,
         ),
         // Face 4
         build_triangle(
-            &points,
+            points,
             5,
             1,
             7,
-            &build_uv_mapped_triangle(img_path, 2, 2, false),
+            &build_uv_mapped_triangle(img_path, 2, 2, false)?,
         ),
         build_triangle(
-            &points,
+            points,
             3,
             1,
             7,
-            &build_uv_mapped_triangle(img_path, 2, 2, true),
+            &build_uv_mapped_triangle(img_path, 2, 2, true)?,
         ),
         // Face 5
         build_triangle(
-            &points,
+            points,
             5,
             1,
             6,
-            &build_uv_mapped_triangle(img_path, 2, 1, false),
+            &build_uv_mapped_triangle(img_path, 2, 1, false)?,
         ),
         build_triangle(
-            &points,
+            points,
             2,
             1,
             6,
-            &build_uv_mapped_triangle(img_path, 2, 1, true),
+            &build_uv_mapped_triangle(img_path, 2, 1, true)?,
         ),
         // Face 6
         build_triangle(
-            &points,
+            points,
             3,
             7,
             4,
-            &build_uv_mapped_triangle(img_path, 3, 1, false),
+            &build_uv_mapped_triangle(img_path, 3, 1, false)?,
         ),
         build_triangle(
-            &points,
+            points,
             8,
             7,
             4,
-            &build_uv_mapped_triangle(img_path, 3, 1, true),
+            &build_uv_mapped_triangle(img_path, 3, 1, true)?,
         ),
-    ]
+    ])
 }
@@ -0,0 +1,294 @@
+use crate::geometry::mat4::Mat4;
+use crate::geometry::ray::Ray;
+use crate::geometry::sampling::{uniform_sphere, uniform_sphere_pdf};
+use crate::geometry::vec3::Vec3;
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::material::color::Color;
+use crate::material::material::{Material, ScatterResult};
+use crate::scene::writer::{format_color, SceneWriter, WriteScene};
+use crate::volume::density_field::DensityField;
+use rand::Rng;
+use std::sync::Arc;
+
+// Light given off by a heterogeneous medium, `color` times the value of `field`. Without a field
+// the medium glows where it is dense.
+#[derive(Clone)]
+pub struct Emission {
+    pub color: Color,
+    pub field: Option<Arc<dyn DensityField + Send + Sync>>,
+}
+
+// Smoke, clouds or scanned data whose density varies in space. The density field fills the unit
+// cube centered at the origin, which `transform` moves into the world, and is multiplied by
+// `density_scale` to get the scattering rate per unit length.
+//
+// Rays are followed with delta tracking: tentative collisions are sampled as if the whole volume
+// had the field's maximum density, and each is kept with the probability of the real density
+// over that maximum.
+pub struct HeterogeneousMedium {
+    density: Arc<dyn DensityField + Send + Sync>,
+    density_scale: f64,
+    emission: Option<Emission>,
+    // Unit cube to world and world to unit cube.
+    transform: Mat4,
+    inverse: Mat4,
+    bounding_box: AabbBoundingBox,
+    phase_function: VolumePhase,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        density: Arc<dyn DensityField + Send + Sync>,
+        density_scale: f64,
+        albedo: Color,
+        emission: Option<Emission>,
+        transform: Mat4,
+    ) -> Result<HeterogeneousMedium, String> {
+        let inverse = transform
+            .inverse()
+            .ok_or_else(|| String::from("transform is not invertible"))?;
+        let phase_function = VolumePhase {
+            albedo,
+            emission: emission.as_ref().map(|emission| {
+                let field = emission.field.as_ref().unwrap_or(&density);
+                (Arc::clone(field), emission.color)
+            }),
+            inverse,
+        };
+        Ok(HeterogeneousMedium {
+            density,
+            density_scale,
+            emission,
+            transform,
+            inverse,
+            bounding_box: unit_cube().transform(&transform),
+            phase_function,
+        })
+    }
+
+    // Scattering rate per unit length at a world space point.
+    pub fn density_at(&self, p: &Vec3) -> f64 {
+        self.density_scale * self.density.density(&field_point(&self.inverse, p))
+    }
+
+    fn tracker(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<Tracker> {
+        let majorant = self.density_scale * self.density.max_density();
+        if majorant <= 0.0 {
+            return None;
+        }
+        // The direction isn't normalized so distances along the ray are the same in both spaces.
+        let local_ray = Ray::new(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.direction()),
+        );
+        let (t, t_end) = unit_cube().hit_interval(&local_ray, t_min, t_max)?;
+        Some(Tracker {
+            t,
+            t_end,
+            majorant,
+            speed: ray.direction().len(),
+        })
+    }
+}
+
+// Tentative collisions along a ray, exponentially distributed with the majorant as the rate.
+struct Tracker {
+    t: f64,
+    t_end: f64,
+    majorant: f64,
+    // World space length of the ray's direction.
+    speed: f64,
+}
+
+impl Tracker {
+    fn next_collision(&mut self) -> Option<f64> {
+        let distance = -(1.0 - rand::thread_rng().gen::<f64>()).ln() / self.majorant;
+        self.t += distance / self.speed;
+        if self.t >= self.t_end {
+            None
+        } else {
+            Some(self.t)
+        }
+    }
+}
+
+fn unit_cube() -> AabbBoundingBox {
+    AabbBoundingBox::new(Vec3::new(-0.5, -0.5, -0.5), Vec3::new(0.5, 0.5, 0.5))
+}
+
+// Density fields are defined over [0, 1]^3.
+fn field_point(inverse: &Mat4, p: &Vec3) -> Vec3 {
+    inverse.transform_point(p) + Vec3::new(0.5, 0.5, 0.5)
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut tracker = self.tracker(ray, t_min, t_max)?;
+        let mut rng = rand::thread_rng();
+        while let Some(t) = tracker.next_collision() {
+            let hit_point = ray.at(t);
+            if rng.gen::<f64>() * tracker.majorant < self.density_at(&hit_point) {
+                // The scattering doesn't depend on the normal.
+                return Some(HitRecord {
+                    hit_point,
+                    normal: Vec3::new(1.0, 0.0, 0.0),
+                    front_face: true,
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    material: &self.phase_function,
+                });
+            }
+        }
+        None
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.bounding_box
+    }
+
+    // Estimates the fraction of light passing through the medium between `t_min` and `t_max`
+    // with ratio tracking. Every tentative collision scales the estimate by the chance that it is
+    // not a real one, which has less variance than counting the rays delta tracking lets through.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        let mut transmittance = 1.0;
+        let mut tracker = match self.tracker(ray, t_min, t_max) {
+            Some(tracker) => tracker,
+            None => return transmittance,
+        };
+        while let Some(t) = tracker.next_collision() {
+            transmittance *= 1.0 - self.density_at(&ray.at(t)) / tracker.majorant;
+        }
+        transmittance
+    }
+}
+
+impl WriteScene for HeterogeneousMedium {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let mut statement = format!(
+            "volume {} density={} color={}",
+            writer.field_definition(self.density.as_ref())?,
+            self.density_scale,
+            format_color(&self.phase_function.albedo)
+        );
+        if let Some(emission) = &self.emission {
+            statement.push_str(&format!(" emission={}", format_color(&emission.color)));
+            if let Some(field) = &emission.field {
+                let grid = writer.field_definition(field.as_ref())?;
+                let path = grid.strip_prefix("grid=").ok_or_else(|| {
+                    String::from("only voxel grid emission can be written to a scene file")
+                })?;
+                statement.push_str(&format!(" emission_grid={}", path));
+            }
+        }
+        writer.push_transform(&self.transform);
+        writer.object(statement);
+        writer.pop_transform();
+        Ok(())
+    }
+}
+
+// Scatters the same amount in every direction like `Isotropic`, and glows with the emission field
+// at the scattering point.
+struct VolumePhase {
+    albedo: Color,
+    emission: Option<(Arc<dyn DensityField + Send + Sync>, Color)>,
+    inverse: Mat4,
+}
+
+impl Material for VolumePhase {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> ScatterResult {
+        let emitted = match &self.emission {
+            Some((field, color)) => {
+                let value = field.density(&field_point(&self.inverse, &hit_record.hit_point));
+                *color * value
+            }
+            None => Color::black(),
+        };
+        ScatterResult {
+            scattered_ray: Some(Ray::new_at_time(
+                hit_record.hit_point,
+                uniform_sphere(&mut rand::thread_rng()),
+                ray_in.time(),
+            )),
+            attenuation: self.albedo,
+            emitted,
+        }
+    }
+
+    fn eval(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Color {
+        self.albedo * uniform_sphere_pdf()
+    }
+
+    fn pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Option<f64> {
+        Some(uniform_sphere_pdf())
+    }
+}
+
+#[cfg(test)]
+fn two_voxel_medium(emission: Option<Emission>) -> HeterogeneousMedium {
+    use crate::volume::voxel_grid::VoxelGrid;
+
+    // Twice the size of the unit cube, the left half has density 0.5, the right half 1.5.
+    HeterogeneousMedium::new(
+        Arc::new(VoxelGrid::new([2, 1, 1], vec![1.0, 3.0]).unwrap()),
+        0.5,
+        Color::white(),
+        emission,
+        Mat4::scaling(Vec3::new(2.0, 2.0, 2.0)),
+    )
+    .unwrap()
+}
+
+#[test]
+fn tracking_follows_the_varying_density() {
+    let medium = two_voxel_medium(None);
+    assert_eq!(
+        medium.get_bounding_box().max_point,
+        Vec3::new(1.0, 1.0, 1.0)
+    );
+    assert_eq!(medium.density_at(&Vec3::new(-0.5, 0.0, 0.0)), 0.5);
+
+    // Crossing 2 units of each half, the chance of passing is exp(-2 * density).
+    let up = Vec3::new(0.0, 1.0, 0.0);
+    for (x, density) in [(-0.5, 0.5f64), (0.5, 1.5)] {
+        let ray = Ray::new(Vec3::new(x, -5.0, 0.0), up);
+        let n = 20000;
+        let mut passed = 0;
+        let mut transmittance = 0.0;
+        for _ in 0..n {
+            match medium.hit(&ray, 0.0001, f64::MAX) {
+                Some(hit) => assert!(hit.t >= 4.0 && hit.t <= 6.0),
+                None => passed += 1,
+            }
+            transmittance += medium.transmittance(&ray, 0.0001, f64::MAX);
+        }
+        let expected = (-2.0 * density).exp();
+        assert!((passed as f64 / n as f64 - expected).abs() < 0.02);
+        assert!((transmittance / n as f64 - expected).abs() < 0.02);
+    }
+
+    // Rays missing the volume or ending before it pass untouched.
+    let ray = Ray::new(Vec3::new(3.0, -5.0, 0.0), up);
+    assert!(medium.hit(&ray, 0.0001, f64::MAX).is_none());
+    let ray = Ray::new(Vec3::new(0.5, -5.0, 0.0), up);
+    assert_eq!(medium.transmittance(&ray, 0.0001, 3.9), 1.0);
+}
+
+#[test]
+fn emission_follows_the_field() {
+    let medium = two_voxel_medium(Some(Emission {
+        color: Color::new(0.5, 0.25, 0.0),
+        field: None,
+    }));
+    let ray = Ray::new(Vec3::new(0.5, -5.0, 0.0), Vec3::new(0.0, 1.0, 0.0));
+    let hit = loop {
+        if let Some(hit) = medium.hit(&ray, 0.0001, f64::MAX) {
+            break hit;
+        }
+    };
+    let scatter = hit.material.scatter(&ray, &hit);
+    assert_eq!(scatter.emitted, Color::new(1.5, 0.75, 0.0));
+    assert_eq!(scatter.attenuation, Color::white());
+}
@@ -2,7 +2,7 @@ use crate::geometry::ray::Ray;
 use crate::geometry::vec3::Vec3;
 use crate::hittable::bounding_box::AabbBoundingBox;
 use crate::material::material::Material;
-use std::sync::Arc;
+use std::any::Any;
 
 pub struct HitRecord<'a> {
     pub hit_point: Vec3,
@@ -11,11 +11,47 @@ pub struct HitRecord<'a> {
     pub t: f64,
     pub u: f64,
     pub v: f64,
-    pub material: Arc<&'a Box<dyn Material + Send + Sync>>,
+    pub material: &'a (dyn Material + Send + Sync),
 }
 
-pub trait Hittable {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+// A point picked on a light, with the density of picking it per unit solid angle as seen from
+// the point being lit.
+pub struct LightSample {
+    pub point: Vec3,
+    pub pdf: f64,
+}
+
+// `Any` lets the scene writer find the concrete type behind a trait object.
+pub trait Hittable: Any {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>>;
 
     fn get_bounding_box(&self) -> AabbBoundingBox;
+
+    // Lights are emissive objects that can be sampled directly, `World` registers them and casts
+    // shadow rays at them.
+    fn is_light(&self) -> bool {
+        false
+    }
+
+    // Picks a point of the object that can be seen from `origin`.
+    fn sample_light(&self, _origin: &Vec3) -> Option<LightSample> {
+        None
+    }
+
+    // The density of `sample_light` picking the point the ray from `origin` along `direction`
+    // hits first, 0 when the ray misses.
+    fn light_pdf(&self, _origin: &Vec3, _direction: &Vec3) -> f64 {
+        0.0
+    }
+
+    // The fraction of light going along the ray between `t_min` and `t_max` that gets through
+    // the object, used for shadow rays. Surfaces block it completely, media let part of it
+    // through. The result may be a random estimate.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        if self.hit(ray, t_min, t_max).is_some() {
+            0.0
+        } else {
+            1.0
+        }
+    }
 }
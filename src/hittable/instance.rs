@@ -0,0 +1,228 @@
+use crate::geometry::mat4::Mat4;
+use crate::geometry::ray::Ray;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::{HitRecord, Hittable, LightSample};
+use crate::hittable::transformed::{
+    hit_in_object_space, light_pdf_in_object_space, sample_light_in_object_space,
+};
+use crate::material::material::Material;
+use crate::scene::writer::{SceneWriter, WriteScene};
+use std::sync::Arc;
+
+// One placement of a shared object, usually a `TriangleMesh` with its own BVH. The object is
+// built once and every instance only stores its transform, so many copies cost a few hundred
+// bytes each. The world's bounding box tree over the instances is the top level of the two
+// level hierarchy.
+pub struct Instance {
+    object: Arc<Box<dyn Hittable + Send + Sync>>,
+    // Object to world and world to object.
+    transform: Mat4,
+    inverse: Mat4,
+    // Replaces the object's own materials when set.
+    material: Option<Arc<Box<dyn Material + Send + Sync>>>,
+    bounding_box: AabbBoundingBox,
+}
+
+impl Instance {
+    pub fn new(
+        object: Arc<Box<dyn Hittable + Send + Sync>>,
+        transform: Mat4,
+        material: Option<Arc<Box<dyn Material + Send + Sync>>>,
+    ) -> Result<Instance, String> {
+        let inverse = transform
+            .inverse()
+            .ok_or_else(|| String::from("instance transform is not invertible"))?;
+        let bounding_box = object.get_bounding_box().transform(&transform);
+        Ok(Instance {
+            object,
+            transform,
+            inverse,
+            material,
+            bounding_box,
+        })
+    }
+
+    pub fn transform(&self) -> &Mat4 {
+        &self.transform
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        let mut hit_record = hit_in_object_space(
+            self.object.as_ref().as_ref(),
+            &self.inverse,
+            ray,
+            t_min,
+            t_max,
+        )?;
+        if let Some(material) = &self.material {
+            hit_record.material = material.as_ref().as_ref();
+        }
+        Some(hit_record)
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.bounding_box
+    }
+
+    // The material override decides whether the instance glows.
+    fn is_light(&self) -> bool {
+        match &self.material {
+            Some(material) => material.is_emissive(),
+            None => self.object.is_light(),
+        }
+    }
+
+    fn sample_light(&self, origin: &Vec3) -> Option<LightSample> {
+        sample_light_in_object_space(
+            self.object.as_ref().as_ref(),
+            &self.transform,
+            &self.inverse,
+            origin,
+        )
+    }
+
+    fn light_pdf(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        light_pdf_in_object_space(
+            self.object.as_ref().as_ref(),
+            &self.transform,
+            &self.inverse,
+            origin,
+            direction,
+        )
+    }
+}
+
+impl WriteScene for Instance {
+    // Scene files can't share an object that isn't loaded from a file, so every instance writes
+    // its own transformed copy.
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        if let Some(material) = &self.material {
+            writer.push_material(material)?;
+        }
+        writer.push_transform(&self.transform);
+        let result = writer.write_object(self.object.as_ref().as_ref());
+        writer.pop_transform();
+        if self.material.is_some() {
+            writer.pop_material();
+        }
+        result
+    }
+}
+
+#[test]
+fn instances_move_the_shared_object() {
+    use crate::hittable::sphere::Sphere;
+    use crate::mesh::obj::default_material;
+
+    let sphere: Arc<Box<dyn Hittable + Send + Sync>> = Arc::new(Box::new(Sphere {
+        center: Vec3::origin(),
+        radius: 1.0,
+        material: default_material(),
+    }));
+    let transform =
+        Mat4::translation(Vec3::new(0.0, 0.0, -10.0)) * Mat4::scaling(Vec3::new(1.0, 1.0, 2.0));
+    let instance = Instance::new(Arc::clone(&sphere), transform, None).unwrap();
+
+    let ray = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -1.0));
+    let hit = instance.hit(&ray, 0.001, f64::MAX).unwrap();
+    assert!((hit.t - 8.0).abs() < 1e-9);
+    assert!((hit.hit_point - Vec3::new(0.0, 0.0, -8.0)).len() < 1e-9);
+    assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-9);
+
+    // The ellipsoid is 4 units deep along z.
+    let bounding_box = instance.get_bounding_box();
+    assert!((bounding_box.min_point - Vec3::new(-1.0, -1.0, -12.0)).len() < 1e-9);
+    assert!((bounding_box.max_point - Vec3::new(1.0, 1.0, -8.0)).len() < 1e-9);
+
+    // Missing the scaled sphere, hitting the unscaled one would be wrong.
+    let side = Ray::new(Vec3::new(0.0, 1.5, 0.0), Vec3::new(0.0, 0.0, -1.0));
+    assert!(instance.hit(&side, 0.001, f64::MAX).is_none());
+
+    assert!(Instance::new(sphere, Mat4::scaling(Vec3::origin()), None).is_err());
+}
+
+#[test]
+fn instance_material_override() {
+    use crate::hittable::triangle::Triangle;
+    use crate::mesh::obj::default_material;
+    use crate::scene::writer::SceneWriter;
+
+    let triangle: Arc<Box<dyn Hittable + Send + Sync>> = Arc::new(Box::new(Triangle::new(
+        Vec3::new(-1.0, -1.0, 0.0),
+        Vec3::new(1.0, -1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        default_material(),
+    )));
+    let metal: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        crate::material::metal::Metal::new(crate::material::color::Color::white(), 0.0),
+    ));
+    let instance = Instance::new(
+        triangle,
+        Mat4::rotation_y(std::f64::consts::PI),
+        Some(metal),
+    )
+    .unwrap();
+
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let hit = instance.hit(&ray, 0.001, f64::MAX).unwrap();
+    let definition = SceneWriter::new()
+        .material_definition(hit.material)
+        .unwrap();
+    assert!(definition.starts_with("metal"), "{}", definition);
+    assert!((hit.normal - Vec3::new(0.0, 0.0, 1.0)).len() < 1e-9);
+}
+
+#[test]
+fn instances_are_written_as_transformed_copies() {
+    use crate::hittable::triangle::Triangle;
+    use crate::material::color::Color;
+    use crate::mesh::obj::default_material;
+    use crate::scene::scene::Scene;
+    use crate::scene::writer::scene_to_string;
+    use crate::world::camera::CameraSettings;
+    use crate::world::render_settings::RenderSettings;
+
+    let triangle: Arc<Box<dyn Hittable + Send + Sync>> = Arc::new(Box::new(Triangle::new(
+        Vec3::new(-1.0, -1.0, 0.0),
+        Vec3::new(1.0, -1.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        default_material(),
+    )));
+    let metal: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        crate::material::metal::Metal::new(Color::white(), 0.0),
+    ));
+    let transform = Mat4::translation(Vec3::new(0.0, 2.0, 0.0));
+    let scene = Scene {
+        objects: vec![
+            Box::new(Instance::new(Arc::clone(&triangle), transform, None).unwrap()),
+            Box::new(Instance::new(triangle, transform, Some(metal)).unwrap()),
+        ],
+        camera: CameraSettings {
+            look_from: Vec3::new(0.0, 0.0, 1.0),
+            look_at: Vec3::new(0.0, 0.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            aperture: 0.0,
+            focus_dist: 1.0,
+            vertical_fov: 40.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        settings: RenderSettings::new(1.0, 10, Color::white(), "instances.png"),
+    };
+    let contents = scene_to_string(&scene).unwrap();
+
+    assert!(contents.contains("material material_0 lambertian"));
+    assert!(contents.contains("material material_1 metal"));
+    let triangles = contents
+        .lines()
+        .filter(|line| line.starts_with("triangle "))
+        .collect::<Vec<&str>>();
+    assert_eq!(triangles.len(), 2);
+    assert!(triangles[0].contains("material=material_0"));
+    assert!(triangles[1].contains("material=material_1"));
+    let matrix = format!("matrix={}", crate::scene::writer::format_matrix(&transform));
+    assert!(triangles.iter().all(|line| line.contains(&matrix)));
+}
@@ -1,7 +1,15 @@
-mod bounding_box;
+pub mod animated;
+pub mod bounding_box;
 pub mod bounding_box_tree;
+pub mod constant_medium;
 pub mod cube;
+pub mod heterogeneous_medium;
+#[allow(clippy::module_inception)]
 pub mod hittable;
+pub mod instance;
+pub mod moving_sphere;
 pub mod quad;
 pub mod sphere;
+pub mod transformed;
 pub mod triangle;
+pub mod triangle_mesh;
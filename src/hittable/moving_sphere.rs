@@ -0,0 +1,108 @@
+use crate::geometry::ray::Ray;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::hittable::sphere::hit_sphere;
+use crate::material::material::Material;
+use crate::scene::writer::{format_vec3, SceneWriter, WriteScene};
+use std::sync::Arc;
+
+// A sphere moving in a straight line, at `center0` at `time0` and at `center1` at `time1`. It
+// rests at the nearer end outside of that interval, so any shutter stays inside its bounding box.
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: Arc<Box<dyn Material + Send + Sync>>,
+}
+
+impl MovingSphere {
+    pub fn center(&self, time: f64) -> Vec3 {
+        if self.time1 == self.time0 {
+            return self.center0;
+        }
+        let s = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + s * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_sphere(
+            self.center(ray.time()),
+            self.radius,
+            self.material.as_ref().as_ref(),
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    // Encloses the sphere at every time, it never leaves the segment between the two centers.
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        let start = AabbBoundingBox::new(self.center0 - r, self.center0 + r);
+        let end = AabbBoundingBox::new(self.center1 - r, self.center1 + r);
+        start.union(&end)
+    }
+}
+
+impl WriteScene for MovingSphere {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let material = writer.material(&self.material)?;
+        writer.object(format!(
+            "sphere center={} center1={} time0={} time1={} radius={} material={}",
+            format_vec3(&self.center0),
+            format_vec3(&self.center1),
+            self.time0,
+            self.time1,
+            self.radius,
+            material
+        ));
+        Ok(())
+    }
+}
+
+#[test]
+fn moving_sphere_is_hit_where_it_is_at_the_ray_time() {
+    use crate::mesh::obj::default_material;
+
+    let sphere = MovingSphere {
+        center0: Vec3::new(0.0, 0.0, -5.0),
+        center1: Vec3::new(4.0, 0.0, -5.0),
+        time0: 0.0,
+        time1: 1.0,
+        radius: 1.0,
+        material: default_material(),
+    };
+    let origin = Vec3::new(2.0, 0.0, 0.0);
+    let direction = Vec3::new(0.0, 0.0, -1.0);
+    assert!(sphere
+        .hit(&Ray::new_at_time(origin, direction, 0.0), 0.0, f64::MAX)
+        .is_none());
+    let hit = sphere
+        .hit(&Ray::new_at_time(origin, direction, 0.5), 0.0, f64::MAX)
+        .unwrap();
+    assert!((hit.t - 4.0).abs() < 1e-9);
+    assert!(sphere
+        .hit(&Ray::new_at_time(origin, direction, 1.0), 0.0, f64::MAX)
+        .is_none());
+
+    let bounding_box = sphere.get_bounding_box();
+    assert_eq!(bounding_box.min_point, Vec3::new(-1.0, -1.0, -6.0));
+    assert_eq!(bounding_box.max_point, Vec3::new(5.0, 1.0, -4.0));
+
+    // Shutters reaching past the interval see the sphere resting at its ends.
+    assert_eq!(sphere.center(-1.0), sphere.center0);
+    assert_eq!(sphere.center(2.0), sphere.center1);
+    let hit = sphere
+        .hit(
+            &Ray::new_at_time(Vec3::new(4.0, 0.0, 0.0), direction, 2.0),
+            0.0,
+            f64::MAX,
+        )
+        .unwrap();
+    assert!((hit.t - 4.0).abs() < 1e-9);
+}
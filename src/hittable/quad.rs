@@ -1,13 +1,14 @@
 use crate::geometry::ray::Ray;
-use crate::geometry::vec3::{cross, dot, Vec3};
+use crate::geometry::vec3::Vec3;
 use crate::hittable::bounding_box::AabbBoundingBox;
-use crate::hittable::hittable::{HitRecord, Hittable};
-use crate::hittable::triangle::Triangle;
+use crate::hittable::hittable::{HitRecord, Hittable, LightSample};
+use crate::hittable::triangle::{solid_angle_pdf, Triangle};
 use crate::material::color::Color;
 use crate::material::diffuse_light::DiffuseLight;
 use crate::material::lambertian::Lambertian;
 use crate::material::material::Material;
-use crate::material::metal::Metal;
+use crate::scene::writer::{format_vec3, SceneWriter, WriteScene};
+use rand::Rng;
 use std::sync::Arc;
 
 pub struct Quad {
@@ -25,8 +26,8 @@ impl Quad {
     ) -> Quad {
         // TODO(chesetti): Rotate points in clockwise manner around p1.
         Quad {
-            triangle1: Triangle::new(p1, p2, p3, Arc::clone(&material)),
-            triangle2: Triangle::new(p1, p3, p4, Arc::clone(&material)),
+            triangle1: Triangle::new(p1, p2, p3, Arc::clone(material)),
+            triangle2: Triangle::new(p1, p3, p4, Arc::clone(material)),
         }
     }
 
@@ -51,7 +52,7 @@ impl Quad {
 }
 
 impl Hittable for Quad {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let hit1 = self.triangle1.hit(ray, t_min, t_max);
         let hit2 = self.triangle2.hit(ray, t_min, t_max);
 
@@ -67,11 +68,11 @@ impl Hittable for Quad {
             return hit1;
         }
 
-        return if hit1.as_ref().unwrap().t < hit2.as_ref().unwrap().t {
+        if hit1.as_ref().unwrap().t < hit2.as_ref().unwrap().t {
             hit1
         } else {
             hit2
-        };
+        }
     }
 
     fn get_bounding_box(&self) -> AabbBoundingBox {
@@ -91,4 +92,96 @@ impl Hittable for Quad {
             ),
         }
     }
+
+    fn is_light(&self) -> bool {
+        self.triangle1.material().is_emissive()
+    }
+
+    // Picks a triangle by its share of the area, so points are uniformly distributed over the quad.
+    fn sample_light(&self, origin: &Vec3) -> Option<LightSample> {
+        let (area1, area2) = (self.triangle1.area(), self.triangle2.area());
+        let triangle = if rand::thread_rng().gen::<f64>() * (area1 + area2) < area1 {
+            &self.triangle1
+        } else {
+            &self.triangle2
+        };
+        let point = triangle.random_point();
+        let pdf = solid_angle_pdf(
+            1.0 / (area1 + area2),
+            origin,
+            &point,
+            &triangle.face_normal(),
+        );
+        if pdf.is_finite() {
+            Some(LightSample { point, pdf })
+        } else {
+            None
+        }
+    }
+
+    fn light_pdf(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        let ray = Ray::new(*origin, *direction);
+        let area = self.triangle1.area() + self.triangle2.area();
+        [&self.triangle1, &self.triangle2]
+            .iter()
+            .filter_map(|triangle| {
+                let hit = triangle.hit(&ray, 0.0001, f64::MAX)?;
+                Some((hit.t, hit.hit_point, triangle.face_normal()))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+            .map_or(0.0, |(_, point, normal)| {
+                solid_angle_pdf(1.0 / area, origin, &point, &normal)
+            })
+    }
+}
+
+impl WriteScene for Quad {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let [p1, p2, p3] = self.triangle1.vertices();
+        let [_, _, p4] = self.triangle2.vertices();
+        let material = writer.material(self.triangle1.material())?;
+        writer.object(format!(
+            "quad p1={} p2={} p3={} p4={} material={}",
+            format_vec3(&p1),
+            format_vec3(&p2),
+            format_vec3(&p3),
+            format_vec3(&p4),
+            material
+        ));
+        Ok(())
+    }
+}
+
+#[test]
+fn light_samples_cover_the_solid_angle() {
+    // A 2x2 light 1 away fills a sixth of the sphere of directions, like the face of a cube seen
+    // from its center. The average of 1 / pdf estimates that solid angle.
+    let light = Quad::new_diffuse_light(
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(1.0, -1.0, -1.0),
+        Vec3::new(1.0, 1.0, -1.0),
+        Vec3::new(-1.0, 1.0, -1.0),
+        Color::white(),
+    );
+    assert!(light.is_light());
+    let origin = Vec3::origin();
+    let n = 20000;
+    let mut solid_angle = 0.0;
+    for _ in 0..n {
+        let sample = light.sample_light(&origin).unwrap();
+        assert!((sample.point.z() + 1.0).abs() < 1e-9);
+        let pdf = light.light_pdf(&origin, &(sample.point - origin));
+        assert!((pdf - sample.pdf).abs() < 1e-9 * pdf);
+        solid_angle += 1.0 / sample.pdf / n as f64;
+    }
+    assert!((solid_angle - 4.0 * std::f64::consts::PI / 6.0).abs() < 0.02);
+    assert_eq!(light.light_pdf(&origin, &Vec3::new(0.0, 0.0, 1.0)), 0.0);
+    assert!(!Quad::new_lambertian(
+        Vec3::new(-1.0, -1.0, -1.0),
+        Vec3::new(1.0, -1.0, -1.0),
+        Vec3::new(1.0, 1.0, -1.0),
+        Vec3::new(-1.0, 1.0, -1.0),
+        Color::white(),
+    )
+    .is_light());
 }
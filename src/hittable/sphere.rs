@@ -1,8 +1,10 @@
 use crate::geometry::ray::Ray;
+use crate::geometry::sampling::{frame, uniform_cone, uniform_cone_pdf};
 use crate::geometry::vec3::{dot, Vec3};
 use crate::hittable::bounding_box::AabbBoundingBox;
-use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::hittable::hittable::{HitRecord, Hittable, LightSample};
 use crate::material::material::Material;
+use crate::scene::writer::{format_vec3, SceneWriter, WriteScene};
 use std::f64::consts::PI;
 use std::sync::Arc;
 
@@ -12,74 +14,177 @@ pub struct Sphere {
     pub material: Arc<Box<dyn Material + Send + Sync>>,
 }
 
+impl Sphere {
+    // Cosine of the half angle of the cone the sphere fills seen from `origin`, None from inside.
+    fn cone_cos(&self, origin: &Vec3) -> Option<f64> {
+        let distance_squared = (self.center - origin).len_squared();
+        let radius_squared = self.radius * self.radius;
+        if distance_squared <= radius_squared {
+            return None;
+        }
+        Some((1.0 - radius_squared / distance_squared).sqrt())
+    }
+}
+
 impl Hittable for Sphere {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let oc: Vec3 = ray.origin() - self.center;
-        let a = dot(ray.direction(), ray.direction());
-        let b = 2.0 * dot(&oc, ray.direction());
-        let c = dot(&oc, &oc) - (self.radius * self.radius);
-        let discriminant = b * b - 4.0 * a * c;
-
-        fn build_hit_record<'a, 'b>(
-            ray: &'b Ray,
-            center: Vec3,
-            t: f64,
-            outward_normal: Vec3,
-            material: &'a Box<dyn Material + Send + Sync>,
-        ) -> HitRecord<'a> {
-            let hitting_front_face = dot(ray.direction(), &outward_normal) < 0.0;
-
-            let p = ray.at(t) - center;
-            let theta = (-p.y()).acos();
-            let phi = (-p.z()).atan2(p.x()) + PI;
-
-            HitRecord {
-                hit_point: ray.at(t),
-                normal: if hitting_front_face {
-                    outward_normal
-                } else {
-                    outward_normal * -1.0
-                },
-                front_face: hitting_front_face,
-                t,
-                u: phi / (2.0 * PI),
-                v: theta / PI,
-                material: Arc::new(material),
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_sphere(
+            self.center,
+            self.radius,
+            self.material.as_ref().as_ref(),
+            ray,
+            t_min,
+            t_max,
+        )
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        AabbBoundingBox {
+            min_point: self.center - Vec3::new(self.radius, self.radius, self.radius),
+            max_point: self.center + Vec3::new(self.radius, self.radius, self.radius),
+        }
+    }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    // Picks a direction in the cone of directions from `origin` that hit the sphere, which is
+    // the part of the sphere that can be seen from there.
+    fn sample_light(&self, origin: &Vec3) -> Option<LightSample> {
+        let cos_max = self.cone_cos(origin)?;
+        let direction =
+            frame(&(self.center - origin)) * uniform_cone(&mut rand::thread_rng(), cos_max);
+        let hit = self.hit(&Ray::new(*origin, direction), 0.0, f64::MAX)?;
+        Some(LightSample {
+            point: hit.hit_point,
+            pdf: uniform_cone_pdf(cos_max),
+        })
+    }
+
+    fn light_pdf(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        match self.cone_cos(origin) {
+            Some(cos_max)
+                if self
+                    .hit(&Ray::new(*origin, *direction), 0.0001, f64::MAX)
+                    .is_some() =>
+            {
+                uniform_cone_pdf(cos_max)
             }
+            _ => 0.0,
         }
+    }
+}
 
-        if discriminant > 0.0 {
-            let t1 = (-b - discriminant.sqrt()) * 0.5 / a;
-            let t2 = (-b + discriminant.sqrt()) * 0.5 / a;
-
-            if t1 > t_min && t1 < t_max {
-                Some(build_hit_record(
-                    ray,
-                    self.center,
-                    t1,
-                    (ray.at(t1) - self.center) * (1.0 / self.radius),
-                    &self.material,
-                ))
-            } else if t2 > t_min && t2 < t_max {
-                Some(build_hit_record(
-                    ray,
-                    self.center,
-                    t2,
-                    (ray.at(t2) - self.center) * (1.0 / self.radius),
-                    &self.material,
-                ))
+impl WriteScene for Sphere {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let material = writer.material(&self.material)?;
+        writer.object(format!(
+            "sphere center={} radius={} material={}",
+            format_vec3(&self.center),
+            self.radius,
+            material
+        ));
+        Ok(())
+    }
+}
+
+// Shared with `MovingSphere`, which calls it with its center at the ray's time.
+pub(crate) fn hit_sphere<'a>(
+    center: Vec3,
+    radius: f64,
+    material: &'a (dyn Material + Send + Sync),
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord<'a>> {
+    let oc: Vec3 = ray.origin() - center;
+    let a = dot(ray.direction(), ray.direction());
+    let b = 2.0 * dot(&oc, ray.direction());
+    let c = dot(&oc, &oc) - (radius * radius);
+    let discriminant = b * b - 4.0 * a * c;
+
+    fn build_hit_record<'a>(
+        ray: &Ray,
+        center: Vec3,
+        t: f64,
+        outward_normal: Vec3,
+        material: &'a (dyn Material + Send + Sync),
+    ) -> HitRecord<'a> {
+        let hitting_front_face = dot(ray.direction(), &outward_normal) < 0.0;
+
+        let p = ray.at(t) - center;
+        let theta = (-p.y()).acos();
+        let phi = (-p.z()).atan2(p.x()) + PI;
+
+        HitRecord {
+            hit_point: ray.at(t),
+            normal: if hitting_front_face {
+                outward_normal
             } else {
-                None
-            }
+                -outward_normal
+            },
+            front_face: hitting_front_face,
+            t,
+            u: phi / (2.0 * PI),
+            v: theta / PI,
+            material,
+        }
+    }
+
+    if discriminant > 0.0 {
+        let t1 = (-b - discriminant.sqrt()) * 0.5 / a;
+        let t2 = (-b + discriminant.sqrt()) * 0.5 / a;
+
+        if t1 > t_min && t1 < t_max {
+            Some(build_hit_record(
+                ray,
+                center,
+                t1,
+                (ray.at(t1) - center) * (1.0 / radius),
+                material,
+            ))
+        } else if t2 > t_min && t2 < t_max {
+            Some(build_hit_record(
+                ray,
+                center,
+                t2,
+                (ray.at(t2) - center) * (1.0 / radius),
+                material,
+            ))
         } else {
             None
         }
+    } else {
+        None
     }
+}
 
-    fn get_bounding_box(&self) -> AabbBoundingBox {
-        AabbBoundingBox {
-            min_point: self.center - Vec3::new(self.radius, self.radius, self.radius),
-            max_point: self.center + Vec3::new(self.radius, self.radius, self.radius),
-        }
+#[test]
+fn light_samples_are_visible_from_the_origin() {
+    use crate::material::color::Color;
+    use crate::material::diffuse_light::DiffuseLight;
+
+    let light = Sphere {
+        center: Vec3::new(0.0, 3.0, -4.0),
+        radius: 1.0,
+        material: Arc::new(Box::new(DiffuseLight::new(Color::white()))),
+    };
+    assert!(light.is_light());
+    let origin = Vec3::origin();
+    // The cone from 5 away has a half angle with sine 1 / 5.
+    let expected_pdf = 1.0 / (2.0 * PI * (1.0 - (24.0f64 / 25.0).sqrt()));
+    for _ in 0..1000 {
+        let sample = light.sample_light(&origin).unwrap();
+        let to_center = sample.point - light.center;
+        assert!((to_center.len() - 1.0).abs() < 1e-9);
+        assert!(dot(&to_center, &(origin - sample.point)) >= -1e-9);
+        assert!((sample.pdf - expected_pdf).abs() < 1e-9 * expected_pdf);
+        assert_eq!(
+            light.light_pdf(&origin, &(sample.point - origin)),
+            sample.pdf
+        );
     }
+    assert_eq!(light.light_pdf(&origin, &Vec3::new(0.0, -1.0, 0.0)), 0.0);
+    assert!(light.sample_light(&light.center).is_none());
 }
@@ -0,0 +1,272 @@
+use crate::geometry::mat4::Mat4;
+use crate::geometry::ray::Ray;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::{HitRecord, Hittable, LightSample};
+use crate::scene::writer::{SceneWriter, WriteScene};
+
+// Moves, rotates or scales the object it owns, a scaled sphere becomes an ellipsoid. Build the
+// matrix from `Mat4::translation`, `rotation`, `scaling` and `look_at`, the rightmost factor of
+// a product is applied first.
+pub struct Transformed {
+    object: Box<dyn Hittable + Send + Sync>,
+    // Object to world and world to object.
+    transform: Mat4,
+    inverse: Mat4,
+    bounding_box: AabbBoundingBox,
+}
+
+impl Transformed {
+    pub fn new(
+        object: Box<dyn Hittable + Send + Sync>,
+        transform: Mat4,
+    ) -> Result<Transformed, String> {
+        let inverse = transform
+            .inverse()
+            .ok_or_else(|| String::from("transform is not invertible"))?;
+        let bounding_box = object.get_bounding_box().transform(&transform);
+        Ok(Transformed {
+            object,
+            transform,
+            inverse,
+            bounding_box,
+        })
+    }
+
+    pub fn transform(&self) -> &Mat4 {
+        &self.transform
+    }
+}
+
+// Intersects `object` with the ray moved into object space by `inverse`, and moves the hit back.
+pub(crate) fn hit_in_object_space<'a>(
+    object: &'a (dyn Hittable + Send + Sync),
+    inverse: &Mat4,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord<'a>> {
+    // The direction isn't normalized so distances along the ray are the same in both spaces.
+    let object_ray = Ray::new_at_time(
+        inverse.transform_point(ray.origin()),
+        inverse.transform_vector(ray.direction()),
+        ray.time(),
+    );
+    let mut hit_record = object.hit(&object_ray, t_min, t_max)?;
+
+    hit_record.hit_point = ray.at(hit_record.t);
+    hit_record.normal = inverse.transform_normal(&hit_record.normal).normalize();
+    Some(hit_record)
+}
+
+// Samples `object` as a light from `origin` moved into object space, and moves the point back.
+pub(crate) fn sample_light_in_object_space(
+    object: &(dyn Hittable + Send + Sync),
+    transform: &Mat4,
+    inverse: &Mat4,
+    origin: &Vec3,
+) -> Option<LightSample> {
+    let object_origin = inverse.transform_point(origin);
+    let sample = object.sample_light(&object_origin)?;
+    Some(LightSample {
+        point: transform.transform_point(&sample.point),
+        pdf: world_pdf(transform, &(sample.point - object_origin), sample.pdf),
+    })
+}
+
+pub(crate) fn light_pdf_in_object_space(
+    object: &(dyn Hittable + Send + Sync),
+    transform: &Mat4,
+    inverse: &Mat4,
+    origin: &Vec3,
+    direction: &Vec3,
+) -> f64 {
+    let object_direction = inverse.transform_vector(direction);
+    let pdf = object.light_pdf(&inverse.transform_point(origin), &object_direction);
+    world_pdf(transform, &object_direction, pdf)
+}
+
+// Converts a density per unit solid angle around `object_direction` to one around the
+// transformed direction. The linear part A stretches a small cone of directions ω into one of
+// |det A| / |Aω|³ times the solid angle.
+fn world_pdf(transform: &Mat4, object_direction: &Vec3, pdf: f64) -> f64 {
+    let stretch = transform
+        .transform_vector(&object_direction.normalize())
+        .len();
+    pdf * stretch.powi(3) / transform.linear().determinant().abs()
+}
+
+impl Hittable for Transformed {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        hit_in_object_space(self.object.as_ref(), &self.inverse, ray, t_min, t_max)
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.bounding_box
+    }
+
+    fn is_light(&self) -> bool {
+        self.object.is_light()
+    }
+
+    fn sample_light(&self, origin: &Vec3) -> Option<LightSample> {
+        sample_light_in_object_space(self.object.as_ref(), &self.transform, &self.inverse, origin)
+    }
+
+    fn light_pdf(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        light_pdf_in_object_space(
+            self.object.as_ref(),
+            &self.transform,
+            &self.inverse,
+            origin,
+            direction,
+        )
+    }
+}
+
+impl WriteScene for Transformed {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        writer.push_transform(&self.transform);
+        let result = writer.write_object(self.object.as_ref());
+        writer.pop_transform();
+        result
+    }
+}
+
+#[cfg(test)]
+fn unit_sphere() -> Box<dyn Hittable + Send + Sync> {
+    use crate::geometry::vec3::Vec3;
+    use crate::hittable::sphere::Sphere;
+    use crate::mesh::obj::default_material;
+
+    Box::new(Sphere {
+        center: Vec3::origin(),
+        radius: 1.0,
+        material: default_material(),
+    })
+}
+
+#[test]
+fn scaled_sphere_is_an_ellipsoid() {
+    use crate::geometry::vec3::Vec3;
+
+    let ellipsoid = Transformed::new(
+        unit_sphere(),
+        Mat4::translation(Vec3::new(0.0, 1.0, 0.0))
+            * Mat4::rotation_z(std::f64::consts::FRAC_PI_4)
+            * Mat4::scaling(Vec3::new(2.0, 0.5, 0.5)),
+    )
+    .unwrap();
+
+    // Sample the surface by shooting rays at the center from all around, every hit point must
+    // satisfy the ellipsoid's implicit equation and its normal must be the equation's gradient.
+    let inverse = ellipsoid.transform().inverse().unwrap();
+    let center = Vec3::new(0.0, 1.0, 0.0);
+    for _ in 0..100 {
+        let origin = center + 5.0 * Vec3::random(-1.0, 1.0).normalize();
+        let ray = Ray::new(origin, center - origin);
+        let hit = ellipsoid.hit(&ray, 0.0, f64::MAX).unwrap();
+
+        let p = inverse.transform_point(&hit.hit_point);
+        assert!((p.len() - 1.0).abs() < 1e-9);
+        let gradient = inverse.transform_normal(&p).normalize();
+        assert!((hit.normal - gradient).len() < 1e-9);
+
+        let bounding_box = ellipsoid.get_bounding_box();
+        for (value, min, max) in [
+            (
+                hit.hit_point.x(),
+                bounding_box.min_point.x(),
+                bounding_box.max_point.x(),
+            ),
+            (
+                hit.hit_point.y(),
+                bounding_box.min_point.y(),
+                bounding_box.max_point.y(),
+            ),
+            (
+                hit.hit_point.z(),
+                bounding_box.min_point.z(),
+                bounding_box.max_point.z(),
+            ),
+        ] {
+            assert!(min <= value && value <= max);
+        }
+    }
+}
+
+#[test]
+fn transformed_ray_interval() {
+    use crate::geometry::vec3::Vec3;
+
+    let sphere = Transformed::new(
+        unit_sphere(),
+        Mat4::translation(Vec3::new(0.0, 0.0, -5.0)) * Mat4::scaling(Vec3::new(3.0, 3.0, 3.0)),
+    )
+    .unwrap();
+    let ray = Ray::new(Vec3::origin(), Vec3::new(0.0, 0.0, -2.0));
+    assert!((sphere.hit(&ray, 0.0, f64::MAX).unwrap().t - 1.0).abs() < 1e-9);
+    // Starting inside, the far side is hit.
+    assert!((sphere.hit(&ray, 1.5, f64::MAX).unwrap().t - 4.0).abs() < 1e-9);
+    assert!(sphere.hit(&ray, 0.0, 0.9).is_none());
+}
+
+#[test]
+fn transformed_lights_are_sampled_in_world_space() {
+    use crate::geometry::sampling::{frame, uniform_cone, uniform_cone_pdf};
+    use crate::geometry::vec3::Vec3;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::color::Color;
+    use crate::material::diffuse_light::DiffuseLight;
+    use crate::world::world::World;
+    use rand::SeedableRng;
+
+    let lamp = || -> Box<dyn Hittable + Send + Sync> {
+        Box::new(Sphere {
+            center: Vec3::origin(),
+            radius: 1.0,
+            material: std::sync::Arc::new(Box::new(DiffuseLight::new(Color::white()))),
+        })
+    };
+    let transform = Mat4::translation(Vec3::new(0.0, 0.0, -10.0))
+        * Mat4::rotation_y(0.3)
+        * Mat4::scaling(Vec3::new(3.0, 1.0, 2.0));
+    let light = Transformed::new(lamp(), transform).unwrap();
+    assert!(light.is_light());
+    assert!(!Transformed::new(unit_sphere(), transform)
+        .unwrap()
+        .is_light());
+    assert_eq!(World::new(vec![Box::new(light)]).lights().len(), 1);
+
+    let light = Transformed::new(lamp(), transform).unwrap();
+    let origin = Vec3::new(1.0, 0.5, 0.0);
+    for _ in 0..100 {
+        let sample = light.sample_light(&origin).unwrap();
+        let direction = sample.point - origin;
+        let hit = light
+            .hit(&Ray::new(origin, direction), 0.0001, f64::MAX)
+            .unwrap();
+        assert!((hit.t - 1.0).abs() < 1e-6);
+        let pdf = light.light_pdf(&origin, &direction);
+        assert!(
+            (pdf - sample.pdf).abs() < 1e-9 * pdf,
+            "{} {}",
+            pdf,
+            sample.pdf
+        );
+    }
+
+    // Over all directions the density integrates to one. The stretched lamp fits in a ball of
+    // radius 3, so only directions in the cone around that ball are sampled.
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let to_light = Vec3::new(0.0, 0.0, -10.0) - origin;
+    let cos_max = (1.0 - (3.0 / to_light.len()).powi(2)).sqrt();
+    let basis = frame(&to_light);
+    let n = 100000;
+    let mut integral = 0.0;
+    for _ in 0..n {
+        let direction = basis * uniform_cone(&mut rng, cos_max);
+        integral += light.light_pdf(&origin, &direction) / uniform_cone_pdf(cos_max) / n as f64;
+    }
+    assert!((integral - 1.0).abs() < 0.02, "{}", integral);
+}
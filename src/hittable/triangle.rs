@@ -1,8 +1,11 @@
 use crate::geometry::ray::Ray;
+use crate::geometry::sampling::{uniform_triangle, uniform_triangle_pdf};
 use crate::geometry::vec3::{cross, dot, Vec3};
 use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::hittable::LightSample;
 use crate::hittable::hittable::{HitRecord, Hittable};
 use crate::material::material::Material;
+use crate::scene::writer::{format_vec3, SceneWriter, WriteScene};
 use std::sync::Arc;
 
 pub struct Triangle {
@@ -10,6 +13,11 @@ pub struct Triangle {
     p2: Vec3,
     p3: Vec3,
     normal: Vec3,
+    // Per-vertex (u, v), when absent the hit record gets the barycentric coordinates instead.
+    tex_coords: Option<[(f64, f64); 3]>,
+    // Per-vertex shading normals, interpolated across the face. `normal` is still used for the
+    // intersection itself.
+    vertex_normals: Option<[Vec3; 3]>,
     material: Arc<Box<dyn Material + Send + Sync>>,
 }
 
@@ -31,13 +39,90 @@ impl Triangle {
             p2,
             p3,
             normal,
+            tex_coords: None,
+            vertex_normals: None,
             material,
         }
     }
+
+    pub fn new_with_tex_coords(
+        p1: Vec3,
+        p2: Vec3,
+        p3: Vec3,
+        tex_coords: [(f64, f64); 3],
+        material: Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Triangle {
+        Triangle {
+            tex_coords: Some(tex_coords),
+            ..Triangle::new(p1, p2, p3, material)
+        }
+    }
+
+    // A smooth shaded triangle, the normals don't need to be normalized. Zero normals are replaced
+    // by the face normal.
+    pub fn new_smooth(
+        p1: Vec3,
+        p2: Vec3,
+        p3: Vec3,
+        vertex_normals: [Vec3; 3],
+        tex_coords: Option<[(f64, f64); 3]>,
+        material: Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Triangle {
+        let triangle = Triangle::new(p1, p2, p3, material);
+        let normal = triangle.normal;
+        Triangle {
+            tex_coords,
+            vertex_normals: Some(vertex_normals.map(|n| {
+                if n.near_zero() {
+                    normal
+                } else {
+                    n.normalize()
+                }
+            })),
+            ..triangle
+        }
+    }
+
+    pub fn vertices(&self) -> [Vec3; 3] {
+        [self.p1, self.p2, self.p3]
+    }
+
+    pub fn tex_coords(&self) -> Option<[(f64, f64); 3]> {
+        self.tex_coords
+    }
+
+    pub fn vertex_normals(&self) -> Option<[Vec3; 3]> {
+        self.vertex_normals
+    }
+
+    pub fn material(&self) -> &Arc<Box<dyn Material + Send + Sync>> {
+        &self.material
+    }
+
+    pub fn area(&self) -> f64 {
+        0.5 * cross(&(self.p2 - self.p1), &(self.p3 - self.p1)).len()
+    }
+
+    // Uniformly distributed over the triangle.
+    pub fn random_point(&self) -> Vec3 {
+        uniform_triangle(&mut rand::thread_rng(), &self.p1, &self.p2, &self.p3)
+    }
+
+    pub fn face_normal(&self) -> Vec3 {
+        self.normal
+    }
+}
+
+// Converts the density of picking `point` per unit area of a surface with `normal` to the density
+// per unit solid angle seen from `origin`. Infinite for surfaces seen edge on.
+pub(crate) fn solid_angle_pdf(area_pdf: f64, origin: &Vec3, point: &Vec3, normal: &Vec3) -> f64 {
+    let direction = point - origin;
+    let cos = dot(normal, &direction).abs() / direction.len();
+    area_pdf * direction.len_squared() / cos
 }
 
 impl Hittable for Triangle {
-    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
         let den = dot(ray.direction(), &self.normal);
         if den.abs() < 1e-6 {
             return None;
@@ -68,25 +153,46 @@ impl Hittable for Triangle {
         let a1 = cross(&v1, &v3).len();
         let a2 = cross(&v2, &v3).len();
 
-        let u = a1 / area;
-        let v = a2 / area;
+        let mut u = a1 / area;
+        let mut v = a2 / area;
+        // u and v are the barycentric weights of p3 and p2.
+        let (w1, w2, w3) = (1.0 - u - v, v, u);
+        if let Some([t1, t2, t3]) = self.tex_coords {
+            u = w1 * t1.0 + w2 * t2.0 + w3 * t3.0;
+            v = w1 * t1.1 + w2 * t2.1 + w3 * t3.1;
+        }
 
         if same_side(self.p1, hit_point, self.p2, self.p3)
             && same_side(self.p2, hit_point, self.p3, self.p1)
             && same_side(self.p3, hit_point, self.p1, self.p2)
         {
+            // The ray hits the front when it goes against the normal given by the winding.
+            let front_face = den < 0.0;
+            let face_normal = if front_face {
+                self.normal
+            } else {
+                -self.normal
+            };
+            let normal = match self.vertex_normals {
+                Some([n1, n2, n3]) => {
+                    let n = (w1 * n1 + w2 * n2 + w3 * n3).normalize();
+                    // Keep the shading normal on the side the ray came from.
+                    if dot(&n, &face_normal) < 0.0 {
+                        -n
+                    } else {
+                        n
+                    }
+                }
+                None => face_normal,
+            };
             Some(HitRecord {
                 hit_point,
-                normal: if den < 0.0 {
-                    self.normal
-                } else {
-                    self.normal * -1.0
-                },
-                front_face: true,
+                normal,
+                front_face,
                 t,
                 u,
                 v,
-                material: Arc::new(&Box::new(&self.material)),
+                material: self.material.as_ref().as_ref(),
             })
         } else {
             None
@@ -127,4 +233,64 @@ impl Hittable for Triangle {
             max_point: Vec3::new(max_x, max_y, max_z),
         }
     }
+
+    fn is_light(&self) -> bool {
+        self.material.is_emissive()
+    }
+
+    fn sample_light(&self, origin: &Vec3) -> Option<LightSample> {
+        let point = self.random_point();
+        let pdf = solid_angle_pdf(
+            uniform_triangle_pdf(self.area()),
+            origin,
+            &point,
+            &self.normal,
+        );
+        if pdf.is_finite() {
+            Some(LightSample { point, pdf })
+        } else {
+            None
+        }
+    }
+
+    fn light_pdf(&self, origin: &Vec3, direction: &Vec3) -> f64 {
+        match self.hit(&Ray::new(*origin, *direction), 0.0001, f64::MAX) {
+            Some(hit) => solid_angle_pdf(
+                uniform_triangle_pdf(self.area()),
+                origin,
+                &hit.hit_point,
+                &self.normal,
+            ),
+            None => 0.0,
+        }
+    }
+}
+
+impl WriteScene for Triangle {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        let material = writer.material(&self.material)?;
+        let mut statement = format!(
+            "triangle p1={} p2={} p3={}",
+            format_vec3(&self.p1),
+            format_vec3(&self.p2),
+            format_vec3(&self.p3),
+        );
+        if let Some([n1, n2, n3]) = self.vertex_normals {
+            statement.push_str(&format!(
+                " n1={} n2={} n3={}",
+                format_vec3(&n1),
+                format_vec3(&n2),
+                format_vec3(&n3)
+            ));
+        }
+        if let Some([t1, t2, t3]) = self.tex_coords {
+            statement.push_str(&format!(
+                " uv1={},{} uv2={},{} uv3={},{}",
+                t1.0, t1.1, t2.0, t2.1, t3.0, t3.1
+            ));
+        }
+        statement.push_str(&format!(" material={}", material));
+        writer.object(statement);
+        Ok(())
+    }
 }
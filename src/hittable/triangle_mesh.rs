@@ -0,0 +1,433 @@
+use crate::geometry::ray::Ray;
+use crate::geometry::vec3::{cross, dot, Vec3};
+use crate::hittable::bounding_box::AabbBoundingBox;
+use crate::hittable::bounding_box_tree::Bvh;
+use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::hittable::triangle::Triangle;
+use crate::material::material::Material;
+use crate::scene::writer::{SceneWriter, WriteScene};
+use std::sync::Arc;
+
+const LEAF_SIZE: usize = 4;
+
+// An indexed triangle mesh. Vertices are stored once and shared by the faces that use them, and
+// the faces are kept in a `Bvh` of their own so the whole mesh is a single object in the world's
+// tree.
+pub struct TriangleMesh {
+    positions: Vec<Vec3>,
+    // Per-vertex shading normals. A zero normal on any vertex of a face makes that face flat.
+    normals: Option<Vec<Vec3>>,
+    // Per-vertex (u, v), when absent the hit record gets the barycentric coordinates instead.
+    tex_coords: Option<Vec<(f64, f64)>>,
+    faces: Vec<[u32; 3]>,
+    // Index into `materials` for every face.
+    face_materials: Vec<u32>,
+    materials: Vec<Arc<Box<dyn Material + Send + Sync>>>,
+    bvh: Bvh,
+}
+
+impl TriangleMesh {
+    // Degenerate faces are dropped. `normals` and `tex_coords` must have one entry per position,
+    // normals don't need to be normalized.
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Option<Vec<Vec3>>,
+        tex_coords: Option<Vec<(f64, f64)>>,
+        faces: Vec<[u32; 3]>,
+        face_materials: Vec<u32>,
+        materials: Vec<Arc<Box<dyn Material + Send + Sync>>>,
+    ) -> Result<TriangleMesh, String> {
+        if faces.len() != face_materials.len() {
+            return Err(format!(
+                "{} faces but {} face materials",
+                faces.len(),
+                face_materials.len()
+            ));
+        }
+        for (name, len) in [
+            ("normals", normals.as_ref().map(|n| n.len())),
+            ("texture coordinates", tex_coords.as_ref().map(|t| t.len())),
+        ] {
+            if let Some(len) = len {
+                if len != positions.len() {
+                    return Err(format!(
+                        "{} {} for {} positions",
+                        len,
+                        name,
+                        positions.len()
+                    ));
+                }
+            }
+        }
+        if let Some(index) = faces
+            .iter()
+            .flatten()
+            .find(|&&i| i as usize >= positions.len())
+        {
+            return Err(format!(
+                "vertex index {} is out of range, {} positions",
+                index,
+                positions.len()
+            ));
+        }
+        if let Some(id) = face_materials
+            .iter()
+            .find(|&&id| id as usize >= materials.len())
+        {
+            return Err(format!(
+                "material id {} is out of range, {} materials",
+                id,
+                materials.len()
+            ));
+        }
+
+        let (faces, face_materials): (Vec<[u32; 3]>, Vec<u32>) = faces
+            .into_iter()
+            .zip(face_materials)
+            .filter(|(face, _)| {
+                let [p1, p2, p3] = face.map(|i| positions[i as usize]);
+                !cross(&(p2 - p1), &(p3 - p1)).near_zero()
+            })
+            .unzip();
+
+        let normals = normals.map(|normals| {
+            normals
+                .into_iter()
+                .map(|n| if n.near_zero() { n } else { n.normalize() })
+                .collect()
+        });
+        let bounding_boxes = faces
+            .iter()
+            .map(|face| {
+                let [p1, p2, p3] = face.map(|i| positions[i as usize]);
+                AabbBoundingBox::new(p1, p1)
+                    .union(&AabbBoundingBox::new(p2, p2))
+                    .union(&AabbBoundingBox::new(p3, p3))
+            })
+            .collect::<Vec<AabbBoundingBox>>();
+        Ok(TriangleMesh {
+            bvh: Bvh::new(&bounding_boxes, LEAF_SIZE),
+            positions,
+            normals,
+            tex_coords,
+            faces,
+            face_materials,
+            materials,
+        })
+    }
+
+    pub fn num_faces(&self) -> usize {
+        self.faces.len()
+    }
+
+    // Builds a standalone `Triangle` for one face.
+    pub fn triangle(&self, face: usize) -> Triangle {
+        let [p1, p2, p3] = self.face_positions(face);
+        let material = Arc::clone(&self.materials[self.face_materials[face] as usize]);
+        let indices = self.faces[face].map(|i| i as usize);
+        let tex_coords = self
+            .tex_coords
+            .as_ref()
+            .map(|tex_coords| indices.map(|i| tex_coords[i]));
+        match self.face_normals(face) {
+            Some(normals) => Triangle::new_smooth(p1, p2, p3, normals, tex_coords, material),
+            None => match tex_coords {
+                Some(tex_coords) => Triangle::new_with_tex_coords(p1, p2, p3, tex_coords, material),
+                None => Triangle::new(p1, p2, p3, material),
+            },
+        }
+    }
+
+    fn face_positions(&self, face: usize) -> [Vec3; 3] {
+        self.faces[face].map(|i| self.positions[i as usize])
+    }
+
+    fn face_normals(&self, face: usize) -> Option<[Vec3; 3]> {
+        let normals = self.normals.as_ref()?;
+        let normals = self.faces[face].map(|i| normals[i as usize]);
+        if normals.iter().any(|n| n.near_zero()) {
+            return None;
+        }
+        Some(normals)
+    }
+
+    // Möller-Trumbore, returns t and the barycentric weights of the second and third vertex.
+    fn hit_face(&self, face: usize, ray: &Ray, t_min: f64, t_max: f64) -> Option<(f64, f64, f64)> {
+        let [p1, p2, p3] = self.face_positions(face);
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+        let p = cross(ray.direction(), &e2);
+        let det = dot(&e1, &p);
+        if det.abs() < 1e-12 {
+            return None;
+        }
+        let inv_det = 1.0 / det;
+
+        let s = ray.origin() - p1;
+        let b2 = dot(&s, &p) * inv_det;
+        if !(0.0..=1.0).contains(&b2) {
+            return None;
+        }
+        let q = cross(&s, &e1);
+        let b3 = dot(ray.direction(), &q) * inv_det;
+        if b3 < 0.0 || b2 + b3 > 1.0 {
+            return None;
+        }
+
+        let t = dot(&e2, &q) * inv_det;
+        if t < t_min || t > t_max {
+            return None;
+        }
+        Some((t, b2, b3))
+    }
+
+    fn hit_record(&self, face: usize, ray: &Ray, t: f64, b2: f64, b3: f64) -> HitRecord<'_> {
+        let [p1, p2, p3] = self.face_positions(face);
+        let b1 = 1.0 - b2 - b3;
+
+        let face_normal = cross(&(p2 - p1), &(p3 - p1)).normalize();
+        let front_face = dot(ray.direction(), &face_normal) < 0.0;
+        let face_normal = if front_face {
+            face_normal
+        } else {
+            -face_normal
+        };
+        let normal = match self.face_normals(face) {
+            Some([n1, n2, n3]) => {
+                let n = (b1 * n1 + b2 * n2 + b3 * n3).normalize();
+                // Keep the shading normal on the side the ray came from.
+                if dot(&n, &face_normal) < 0.0 {
+                    -n
+                } else {
+                    n
+                }
+            }
+            None => face_normal,
+        };
+
+        // Same convention as `Triangle`: without texture coordinates u and v are the weights of
+        // the third and second vertex.
+        let (u, v) = match &self.tex_coords {
+            Some(tex_coords) => {
+                let [t1, t2, t3] = self.faces[face].map(|i| tex_coords[i as usize]);
+                (
+                    b1 * t1.0 + b2 * t2.0 + b3 * t3.0,
+                    b1 * t1.1 + b2 * t2.1 + b3 * t3.1,
+                )
+            }
+            None => (b3, b2),
+        };
+
+        HitRecord {
+            hit_point: ray.at(t),
+            normal,
+            front_face,
+            t,
+            u,
+            v,
+            material: self.materials[self.face_materials[face] as usize]
+                .as_ref()
+                .as_ref(),
+        }
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.bvh
+            .traverse(ray, t_min, t_max, |face, t_min, t_max| {
+                self.hit_face(face, ray, t_min, t_max)
+                    .map(|(t, b2, b3)| (t, (face, t, b2, b3)))
+            })
+            .map(|(face, t, b2, b3)| self.hit_record(face, ray, t, b2, b3))
+    }
+
+    fn get_bounding_box(&self) -> AabbBoundingBox {
+        self.bvh.bounding_box()
+    }
+}
+
+impl WriteScene for TriangleMesh {
+    // Meshes are written face by face.
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String> {
+        for face in 0..self.faces.len() {
+            self.triangle(face).write_scene(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn random_mesh(num_faces: usize) -> TriangleMesh {
+    use crate::mesh::obj::default_material;
+
+    let mut positions = vec![];
+    let mut faces = vec![];
+    for i in 0..num_faces {
+        let center = Vec3::random(-5.0, 5.0);
+        positions.push(center + Vec3::random(-1.0, 1.0));
+        positions.push(center + Vec3::random(-1.0, 1.0));
+        positions.push(center + Vec3::random(-1.0, 1.0));
+        let first = 3 * i as u32;
+        faces.push([first, first + 1, first + 2]);
+    }
+    TriangleMesh::new(
+        positions,
+        None,
+        None,
+        faces,
+        vec![0; num_faces],
+        vec![default_material()],
+    )
+    .unwrap()
+}
+
+#[test]
+fn mesh_hits_match_individual_triangles() {
+    let mesh = random_mesh(200);
+    let triangles = (0..mesh.num_faces())
+        .map(|face| mesh.triangle(face))
+        .collect::<Vec<Triangle>>();
+
+    for _ in 0..500 {
+        let ray = Ray::new(Vec3::random(-8.0, 8.0), Vec3::random(-1.0, 1.0));
+        let expected = triangles
+            .iter()
+            .filter_map(|triangle| triangle.hit(&ray, 0.001, f64::MAX))
+            .map(|hit| hit.t)
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let actual = mesh.hit(&ray, 0.001, f64::MAX).map(|hit| hit.t);
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => assert!((expected - actual).abs() < 1e-6),
+            (None, None) => {}
+            // Rays grazing an edge can be decided differently by the two intersection tests.
+            (expected, actual) => {
+                let t = expected.or(actual).unwrap();
+                let grazing = triangles.iter().any(|triangle| {
+                    let [p1, p2, p3] = triangle.vertices();
+                    let p = ray.at(t);
+                    let area = cross(&(p2 - p1), &(p3 - p1)).len();
+                    let sum = cross(&(p2 - p), &(p3 - p)).len()
+                        + cross(&(p3 - p), &(p1 - p)).len()
+                        + cross(&(p1 - p), &(p2 - p)).len();
+                    (sum - area).abs() < 1e-6 * area.max(1.0)
+                });
+                assert!(grazing, "{:?} != {:?}", expected, actual);
+            }
+        }
+    }
+}
+
+#[test]
+fn mesh_drops_degenerate_faces_and_checks_indices() {
+    let grey: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        crate::material::lambertian::Lambertian::new_from_color(
+            crate::material::color::Color::new(0.5, 0.5, 0.5),
+        ),
+    ));
+    let positions = vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+        Vec3::new(2.0, 0.0, 0.0),
+    ];
+    let mesh = TriangleMesh::new(
+        positions.clone(),
+        None,
+        None,
+        vec![[0, 1, 2], [0, 1, 3]],
+        vec![0, 0],
+        vec![Arc::clone(&grey)],
+    )
+    .unwrap();
+    assert_eq!(mesh.num_faces(), 1);
+
+    assert!(TriangleMesh::new(
+        positions.clone(),
+        None,
+        None,
+        vec![[0, 1, 4]],
+        vec![0],
+        vec![Arc::clone(&grey)],
+    )
+    .is_err());
+    assert!(
+        TriangleMesh::new(positions, None, None, vec![[0, 1, 2]], vec![1], vec![grey]).is_err()
+    );
+}
+
+#[test]
+fn mesh_interpolates_normals_and_tex_coords() {
+    let grey: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        crate::material::lambertian::Lambertian::new_from_color(
+            crate::material::color::Color::new(0.5, 0.5, 0.5),
+        ),
+    ));
+    let mesh = TriangleMesh::new(
+        vec![
+            Vec3::new(0.0, 0.0, 0.0),
+            Vec3::new(1.0, 0.0, 0.0),
+            Vec3::new(0.0, 1.0, 0.0),
+        ],
+        Some(vec![
+            Vec3::new(0.0, 0.0, 1.0),
+            Vec3::new(1.0, 0.0, 1.0),
+            Vec3::new(0.0, 1.0, 1.0),
+        ]),
+        Some(vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]),
+        vec![[0, 1, 2]],
+        vec![0],
+        vec![grey],
+    )
+    .unwrap();
+
+    let ray = Ray::new(Vec3::new(0.25, 0.5, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let hit = mesh.hit(&ray, 0.001, f64::MAX).unwrap();
+    assert!((hit.t - 1.0).abs() < 1e-9);
+    assert!((hit.u - 0.25).abs() < 1e-9 && (hit.v - 0.5).abs() < 1e-9);
+    let expected = (0.25 * Vec3::new(0.0, 0.0, 1.0)
+        + 0.25 * Vec3::new(1.0, 0.0, 1.0).normalize()
+        + 0.5 * Vec3::new(0.0, 1.0, 1.0).normalize())
+    .normalize();
+    assert!((hit.normal - expected).len() < 1e-9, "{}", hit.normal);
+}
+
+#[test]
+fn hits_report_which_side_was_hit() {
+    use crate::hittable::triangle::Triangle;
+
+    let grey: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        crate::material::lambertian::Lambertian::new_from_color(
+            crate::material::color::Color::new(0.5, 0.5, 0.5),
+        ),
+    ));
+    let positions = vec![
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(1.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    ];
+    let mesh = TriangleMesh::new(
+        positions.clone(),
+        None,
+        None,
+        vec![[0, 1, 2]],
+        vec![0],
+        vec![Arc::clone(&grey)],
+    )
+    .unwrap();
+    let triangle = Triangle::new(positions[0], positions[1], positions[2], grey);
+
+    // Counter-clockwise seen from +z, so the front faces +z.
+    let from_front = Ray::new(Vec3::new(0.25, 0.25, 1.0), Vec3::new(0.0, 0.0, -1.0));
+    let from_back = Ray::new(Vec3::new(0.25, 0.25, -1.0), Vec3::new(0.0, 0.0, 1.0));
+    for object in [&mesh as &dyn Hittable, &triangle] {
+        let hit = object.hit(&from_front, 0.001, f64::MAX).unwrap();
+        assert!(hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, 1.0));
+        let hit = object.hit(&from_back, 0.001, f64::MAX).unwrap();
+        assert!(!hit.front_face);
+        assert_eq!(hit.normal, Vec3::new(0.0, 0.0, -1.0));
+    }
+}
@@ -0,0 +1,9 @@
+use crate::geometry::ray::Ray;
+use crate::material::color::Color;
+use crate::world::world::World;
+
+// The light transport algorithm `render` runs for every camera ray. Each call is one random
+// estimate of the light arriving at the camera along the ray, pixels average many of them.
+pub trait Integrator {
+    fn radiance(&self, world: &World, ray: &Ray) -> Color;
+}
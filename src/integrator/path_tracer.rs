@@ -0,0 +1,245 @@
+use crate::geometry::ray::Ray;
+use crate::hittable::hittable::HitRecord;
+use crate::integrator::integrator::Integrator;
+use crate::material::color::Color;
+use crate::material::material::BounceKind;
+use crate::world::render_settings::RenderSettings;
+use crate::world::world::World;
+use rand::Rng;
+
+// Follows each camera ray from bounce to bounce, adding up the light found at every hit weighted
+// by the throughput, the fraction of it that makes it back to the camera.
+//
+// At every hit a registered light is also sampled directly. When the path's next bounce happens
+// to hit a light, the two estimates of its light are weighted with the power heuristic.
+pub struct PathTracer {
+    max_depth: u32,
+    diffuse_depth: Option<u32>,
+    glossy_depth: Option<u32>,
+    transmission_depth: Option<u32>,
+    russian_roulette: Option<u32>,
+    background: Color,
+}
+
+impl PathTracer {
+    pub fn new(settings: &RenderSettings) -> PathTracer {
+        PathTracer {
+            max_depth: settings.recursive_depth,
+            diffuse_depth: settings.diffuse_depth,
+            glossy_depth: settings.glossy_depth,
+            transmission_depth: settings.transmission_depth,
+            russian_roulette: settings.russian_roulette,
+            background: settings.background,
+        }
+    }
+
+    fn depth_limit(&self, kind: BounceKind) -> Option<u32> {
+        match kind {
+            BounceKind::Diffuse => self.diffuse_depth,
+            BounceKind::Glossy => self.glossy_depth,
+            BounceKind::Transmission => self.transmission_depth,
+        }
+    }
+}
+
+impl Integrator for PathTracer {
+    fn radiance(&self, world: &World, camera_ray: &Ray) -> Color {
+        let mut radiance = Color::black();
+        let mut throughput = Color::white();
+        let mut ray = *camera_ray;
+        // The density with which the previous hit picked the ray, None for camera rays and
+        // mirror-like bounces.
+        let mut scatter_pdf = None;
+        // Bounces taken of each kind.
+        let mut bounces = [0; 3];
+
+        for depth in 0..self.max_depth {
+            // Hits closer than 0.0001 are the surface the ray left from.
+            let (object, hit_record) = match world.hit_object(&ray, 0.0001, f64::MAX) {
+                Some(hit) => hit,
+                None => {
+                    radiance += throughput * self.background;
+                    break;
+                }
+            };
+            let material = hit_record.material;
+            let scatter_result = material.scatter(&ray, &hit_record);
+            let mut emitted = scatter_result.emitted;
+            if let (Some(scatter_pdf), true) = (scatter_pdf, material.is_emissive()) {
+                if let Some(light_pdf) = world.light_pdf(&ray, object) {
+                    emitted *= power_heuristic(scatter_pdf, light_pdf);
+                }
+            }
+            radiance += throughput * (emitted + direct_light(world, &ray, &hit_record));
+
+            let scattered_ray = match scatter_result.scattered_ray {
+                Some(scattered_ray) => scattered_ray,
+                None => break,
+            };
+            let kind = material.bounce_kind(&ray, &hit_record, scattered_ray.direction());
+            bounces[kind as usize] += 1;
+            if self
+                .depth_limit(kind)
+                .is_some_and(|limit| bounces[kind as usize] > limit)
+            {
+                break;
+            }
+
+            throughput *= scatter_result.attenuation;
+            // Paths are stopped at random, more often the less light they carry. The ones that go
+            // on are weighted up by one over the chance of surviving, which keeps the average.
+            if let Some(min_bounces) = self.russian_roulette {
+                if depth + 1 >= min_bounces {
+                    let survival = throughput.max_component().min(1.0);
+                    if rand::thread_rng().gen::<f64>() >= survival {
+                        break;
+                    }
+                    throughput /= survival;
+                }
+            }
+            scatter_pdf = material.pdf(&ray, &hit_record, scattered_ray.direction());
+            ray = scattered_ray;
+        }
+        radiance
+    }
+}
+
+// Light reaching a hit straight from a random registered light, checked with a shadow ray towards
+// a point picked on it. Materials without a pdf only scatter towards lights by chance.
+fn direct_light(world: &World, ray: &Ray, hit_record: &HitRecord) -> Color {
+    let lights = world.lights();
+    if lights.is_empty() {
+        return Color::black();
+    }
+    let light = &lights[rand::thread_rng().gen_range(0..lights.len())];
+    let sample = match light.sample_light(&hit_record.hit_point) {
+        Some(sample) => sample,
+        None => return Color::black(),
+    };
+
+    let direction = sample.point - hit_record.hit_point;
+    let material = hit_record.material;
+    let scatter_pdf = match material.pdf(ray, hit_record, &direction) {
+        Some(pdf) => pdf,
+        None => return Color::black(),
+    };
+    let scattered = material.eval(ray, hit_record, &direction);
+    if scattered.is_black() {
+        return Color::black();
+    }
+    // The shadow ray reaches the light at t = 1, anything before that blocks or dims it.
+    let shadow_ray = Ray::new_at_time(hit_record.hit_point, direction, ray.time());
+    let radiance = match light.hit(&shadow_ray, 0.0001, f64::MAX) {
+        Some(hit) if (hit.t - 1.0).abs() < 1e-6 => hit.material.scatter(&shadow_ray, &hit).emitted,
+        _ => return Color::black(),
+    };
+    let transmittance = world.transmittance(&shadow_ray, 0.0001, 1.0 - 1e-6);
+    if transmittance == 0.0 {
+        return Color::black();
+    }
+
+    // The light was picked with probability 1 / lights.
+    let light_pdf = sample.pdf / lights.len() as f64;
+    let weight = power_heuristic(light_pdf, scatter_pdf) / light_pdf;
+    radiance * scattered * transmittance * weight
+}
+
+// Weight of a sample taken with density `pdf` when `other_pdf` could also have produced it.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {
+        return 0.0;
+    }
+    a / (a + b)
+}
+
+#[cfg(test)]
+fn mean_radiance(
+    tracer: &PathTracer,
+    objects: Vec<Box<dyn crate::hittable::hittable::Hittable + Send + Sync>>,
+    ray: &Ray,
+) -> f64 {
+    let world = World::new(objects);
+    let n = 20000;
+    (0..n)
+        .map(|_| tracer.radiance(&world, ray).r())
+        .sum::<f64>()
+        / n as f64
+}
+
+#[cfg(test)]
+fn test_tracer(configure: impl Fn(&mut RenderSettings)) -> PathTracer {
+    let mut settings = RenderSettings::new(1.0, 10, Color::white(), "test.png");
+    configure(&mut settings);
+    PathTracer::new(&settings)
+}
+
+#[cfg(test)]
+fn ground(
+    material: std::sync::Arc<Box<dyn crate::material::material::Material + Send + Sync>>,
+) -> Box<dyn crate::hittable::hittable::Hittable + Send + Sync> {
+    Box::new(crate::hittable::sphere::Sphere {
+        center: crate::geometry::vec3::Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material,
+    })
+}
+
+#[test]
+fn russian_roulette_keeps_the_average() {
+    use crate::geometry::vec3::Vec3;
+    use crate::mesh::obj::default_material;
+
+    // Under a white sky every ray leaving the ground escapes, half the light comes back.
+    let down = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    for russian_roulette in [None, Some(0)] {
+        let tracer = test_tracer(|settings| settings.russian_roulette = russian_roulette);
+        let mean = mean_radiance(&tracer, vec![ground(default_material())], &down);
+        assert!((mean - 0.5).abs() < 0.02, "{}", mean);
+    }
+}
+
+#[test]
+fn depth_limits_count_each_kind_of_bounce() {
+    use crate::geometry::vec3::Vec3;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::dielectric::Dielectric;
+    use crate::material::metal::Metal;
+    use crate::mesh::obj::default_material;
+
+    let down = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    let mirror: std::sync::Arc<Box<dyn crate::material::material::Material + Send + Sync>> =
+        std::sync::Arc::new(Box::new(Metal::new(Color::new(0.5, 0.5, 0.5), 0.0)));
+
+    let no_diffuse = test_tracer(|settings| settings.diffuse_depth = Some(0));
+    let no_glossy = test_tracer(|settings| settings.glossy_depth = Some(0));
+    assert_eq!(
+        mean_radiance(&no_diffuse, vec![ground(default_material())], &down),
+        0.0
+    );
+    assert!(
+        (mean_radiance(&no_glossy, vec![ground(default_material())], &down) - 0.5).abs() < 0.02
+    );
+    assert_eq!(
+        mean_radiance(&no_diffuse, vec![ground(mirror.clone())], &down),
+        0.5
+    );
+    assert_eq!(
+        mean_radiance(&no_glossy, vec![ground(mirror.clone())], &down),
+        0.0
+    );
+
+    // Straight through a glass ball light is only lost to the depth limit. Without transmission
+    // only the 4% reflected off the front is left.
+    let glass = || -> Vec<Box<dyn crate::hittable::hittable::Hittable + Send + Sync>> {
+        vec![Box::new(Sphere {
+            center: Vec3::origin(),
+            radius: 0.5,
+            material: std::sync::Arc::new(Box::new(Dielectric::new(1.5))),
+        })]
+    };
+    let all = test_tracer(|_| {});
+    let no_transmission = test_tracer(|settings| settings.transmission_depth = Some(0));
+    assert!((mean_radiance(&all, glass(), &down) - 1.0).abs() < 1e-9);
+    assert!((mean_radiance(&no_transmission, glass(), &down) - 0.04).abs() < 0.01);
+}
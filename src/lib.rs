@@ -0,0 +1,89 @@
+//! A ray tracer based on Peter Shirley's Ray Tracing in One Weekend series.
+//!
+//! Build a list of objects, wrap them in a `World` and render it through a `Camera`:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use ray_tracing::{render, CameraSettings, Color, Hittable, Lambertian, Material, RenderSettings, Sphere, Vec3, World};
+//!
+//! let grey: Arc<Box<dyn Material + Send + Sync>> =
+//!     Arc::new(Box::new(Lambertian::new_from_color(Color::new(0.5, 0.5, 0.5))));
+//! let objects: Vec<Box<dyn Hittable + Send + Sync>> = vec![Box::new(Sphere {
+//!     center: Vec3::new(0.0, 0.0, -1.0),
+//!     radius: 0.5,
+//!     material: grey,
+//! })];
+//!
+//! let settings = RenderSettings::new(3.0 / 2.0, 300, Color::white(), "render.png");
+//! let camera = CameraSettings {
+//!     look_from: Vec3::new(0.0, 0.0, 1.0),
+//!     look_at: Vec3::new(0.0, 0.0, -1.0),
+//!     vup: Vec3::new(0.0, 1.0, 0.0),
+//!     aperture: 0.0,
+//!     focus_dist: 2.0,
+//!     vertical_fov: 50.0,
+//!     shutter_open: 0.0,
+//!     shutter_close: 0.0,
+//! }
+//! .build(settings.img_width, settings.img_height);
+//!
+//! render(World::new(objects), &camera, &settings).unwrap();
+//! ```
+
+#[macro_use]
+extern crate impl_ops;
+
+pub mod geometry;
+pub mod hittable;
+pub mod integrator;
+pub mod material;
+pub mod mesh;
+pub mod output;
+pub mod scene;
+#[cfg(test)]
+mod testing;
+pub mod volume;
+pub mod world;
+
+pub use geometry::mat3::Mat3;
+pub use geometry::mat4::Mat4;
+pub use geometry::quaternion::Quaternion;
+pub use geometry::ray::Ray;
+pub use geometry::vec3::{cross, dot, Vec3};
+pub use hittable::animated::{Animated, Keyframe};
+pub use hittable::bounding_box::AabbBoundingBox;
+pub use hittable::bounding_box_tree::{BoundingBoxTree, Bvh, TreeStats};
+pub use hittable::constant_medium::ConstantMedium;
+pub use hittable::cube::Cube;
+pub use hittable::heterogeneous_medium::{Emission, HeterogeneousMedium};
+pub use hittable::hittable::{HitRecord, Hittable};
+pub use hittable::instance::Instance;
+pub use hittable::moving_sphere::MovingSphere;
+pub use hittable::quad::Quad;
+pub use hittable::sphere::Sphere;
+pub use hittable::transformed::Transformed;
+pub use hittable::triangle::Triangle;
+pub use hittable::triangle_mesh::TriangleMesh;
+pub use integrator::integrator::Integrator;
+pub use integrator::path_tracer::PathTracer;
+pub use material::checkered_texture::CheckeredTexture;
+pub use material::color::Color;
+pub use material::dielectric::Dielectric;
+pub use material::diffuse_light::DiffuseLight;
+pub use material::image_texture::ImageTexture;
+pub use material::isotropic::Isotropic;
+pub use material::lambertian::Lambertian;
+pub use material::material::{BounceKind, Material, ScatterResult};
+pub use material::metal::Metal;
+pub use material::solid_color_texture::SolidColorTexture;
+pub use material::texture::Texture;
+pub use material::triangle_image_texture::TriangleImageTexture;
+pub use output::display::{DisplaySettings, ToneMapping};
+pub use output::framebuffer::Framebuffer;
+pub use scene::scene::Scene;
+pub use volume::density_field::DensityField;
+pub use volume::noise::{NoiseField, Perlin};
+pub use volume::voxel_grid::VoxelGrid;
+pub use world::camera::{Camera, CameraSettings};
+pub use world::render_settings::RenderSettings;
+pub use world::world::{render, render_with_integrator, World};
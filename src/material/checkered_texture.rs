@@ -1,6 +1,7 @@
 use crate::geometry::vec3::Vec3;
 use crate::material::color::Color;
 use crate::material::texture::Texture;
+use crate::scene::writer::{format_color, SceneDefinition, SceneWriter};
 
 pub struct CheckeredTexture {
     even_color: Color,
@@ -30,3 +31,14 @@ impl Texture for CheckeredTexture {
         }
     }
 }
+
+impl SceneDefinition for CheckeredTexture {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!(
+            "checkered even={} odd={} size={}",
+            format_color(&self.even_color),
+            format_color(&self.odd_color),
+            self.size_factor
+        ))
+    }
+}
@@ -1,6 +1,8 @@
 use crate::geometry::vec3::Vec3;
 use std::ops;
 
+// Linear RGB. Reflectances are in [0, 1], light can be as bright as it likes, only the display
+// settings limit it when an 8 bit image is written.
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub struct Color {
     rgb: Vec3,
@@ -8,16 +10,6 @@ pub struct Color {
 
 impl Color {
     pub fn new(r: f64, g: f64, b: f64) -> Color {
-        if r < 0.0 || r > 1.0 {
-            panic!("Not a valid color")
-        }
-        if g < 0.0 || b > 1.0 {
-            panic!("Not a valid color")
-        }
-        if g < 0.0 || b > 1.0 {
-            panic!("Not a valid color")
-        }
-
         Color {
             rgb: Vec3::new(r, g, b),
         }
@@ -58,39 +50,35 @@ impl Color {
         }
     }
 
-    pub fn attenuate(&self, factor: Color) -> Color {
-        Color {
-            rgb: Vec3::new(
-                self.r() * factor.r(),
-                self.g() * factor.g(),
-                self.b() * factor.b(),
-            ),
-        }
+    // Brightness as the eye sees it, with the Rec. 709 weights.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.r() + 0.7152 * self.g() + 0.0722 * self.b()
     }
 
-    pub fn image_pixel(&self) -> image::Rgb<u8> {
-        let r = self.r() * 256.0;
-        let g = self.g() * 256.0;
-        let b = self.b() * 256.0;
-        image::Rgb([r as u8, g as u8, b as u8])
+    pub fn max_component(&self) -> f64 {
+        self.rgb.max_component()
     }
 
-    pub fn gamma_corrected(&self) -> Color {
-        Color::new(self.r().sqrt(), self.g().sqrt(), self.b().sqrt())
+    pub fn is_black(&self) -> bool {
+        self.rgb.as_slice().iter().all(|c| *c == 0.0)
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.rgb.as_slice().iter().all(|c| c.is_finite())
+    }
+
+    pub fn has_nan(&self) -> bool {
+        self.rgb.as_slice().iter().any(|c| c.is_nan())
     }
 
     pub fn average_color<'a>(colors: impl Iterator<Item = &'a Color>) -> Color {
-        let mut total_rgb = Vec3::origin();
+        let mut total = Color::black();
         let mut count = 0.0;
         for color in colors {
-            total_rgb = total_rgb + color.rgb;
-            count = count + 1.0
+            total += color;
+            count += 1.0
         }
-        Color::new(
-            total_rgb.x() / count,
-            total_rgb.y() / count,
-            total_rgb.z() / count,
-        )
+        total / count
     }
 
     pub fn as_vector(&self) -> Vec3 {
@@ -98,14 +86,49 @@ impl Color {
     }
 }
 
-impl_op_ex!(+ |a: &Color, b: &Color | -> Color {
-    let rgb = a.rgb + b.rgb;
-    let rgb = Vec3::new(
-        rgb.x().clamp(0.0, 256.0),
-        rgb.y().clamp(0.0, 256.0),
-        rgb.z().clamp(0.0, 256.0),
-    );
-    Color {
-        rgb
-    }
-});
+impl_op_ex!(+|a: &Color, b: &Color| -> Color { Color { rgb: a.rgb + b.rgb } });
+
+impl_op_ex!(-|a: &Color, b: &Color| -> Color { Color { rgb: a.rgb - b.rgb } });
+
+// Component-wise product, light reflected off a surface is the light times its color.
+impl_op_ex!(*|a: &Color, b: &Color| -> Color { Color { rgb: a.rgb * b.rgb } });
+
+impl_op_ex!(/|a: &Color, b: &Color| -> Color { Color { rgb: a.rgb / b.rgb } });
+
+impl_op_ex_commutative!(*|a: &Color, b: f64| -> Color { Color { rgb: a.rgb * b } });
+
+impl_op_ex!(/|a: &Color, b: f64| -> Color { Color { rgb: a.rgb / b } });
+
+impl_op_ex!(+=|a: &mut Color, b: &Color| { a.rgb += b.rgb });
+
+impl_op_ex!(*=|a: &mut Color, b: &Color| { a.rgb = a.rgb * b.rgb });
+
+impl_op_ex!(*=|a: &mut Color, b: f64| { a.rgb *= b });
+
+impl_op_ex!(/=|a: &mut Color, b: f64| { a.rgb /= b });
+
+#[test]
+fn colors_are_not_limited() {
+    let light = Color::new(15.0, 10.0, 5.0);
+    let albedo = Color::new(0.5, 0.2, 1.0);
+    assert_eq!(light * albedo, Color::new(7.5, 2.0, 5.0));
+    assert_eq!(light + light, light * 2.0);
+    assert_eq!(2.0 * light / 4.0, Color::new(7.5, 5.0, 2.5));
+    assert_eq!(light - light, Color::black());
+    assert_eq!(light.max_component(), 15.0);
+    assert!((Color::white().luminance() - 1.0).abs() < 1e-12);
+    assert_eq!(Color::new(0.0, 1.0, 0.0).luminance(), 0.7152);
+
+    let mut sum = Color::black();
+    sum += light;
+    sum *= albedo;
+    sum /= 2.0;
+    assert_eq!(sum, Color::new(3.75, 1.0, 2.5));
+
+    let average = Color::average_color([light, Color::black()].iter());
+    assert_eq!(average, Color::new(7.5, 5.0, 2.5));
+
+    assert!(light.is_finite() && !light.has_nan() && !light.is_black());
+    assert!(!Color::new(f64::INFINITY, 0.0, 0.0).is_finite());
+    assert!(Color::new(0.0, f64::NAN, 0.0).has_nan());
+}
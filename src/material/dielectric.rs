@@ -2,7 +2,8 @@ use crate::geometry::ray::Ray;
 use crate::geometry::vec3::{dot, Vec3};
 use crate::hittable::hittable::HitRecord;
 use crate::material::color::Color;
-use crate::material::material::{Material, ScatterResult};
+use crate::material::material::{BounceKind, Material, ScatterResult};
+use crate::scene::writer::{SceneDefinition, SceneWriter};
 use rand::Rng;
 
 pub struct Dielectric {
@@ -25,36 +26,53 @@ impl Material for Dielectric {
         };
 
         fn refract(uv: &Vec3, normal: &Vec3, etai_over_etat: f64) -> Vec3 {
-            let cos = (dot(&(uv * -1.0), normal)).min(1.0);
+            let cos = (dot(&(-uv), normal)).min(1.0);
             let r_out_perp = etai_over_etat * (uv + cos * normal);
-            let r_out_parallel = -1.0 * ((1.0 - r_out_perp.len_squared()).sqrt()) * normal;
+            let r_out_parallel = -((1.0 - r_out_perp.len_squared()).sqrt()) * normal;
             r_out_perp + r_out_parallel
         }
 
         fn reflectance(cos: f64, ref_idx: f64) -> f64 {
             let mut r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
             r0 = r0 * r0;
-            return r0 + (1.0 - r0) * ((1.0 - cos).powf(5.0));
+            r0 + (1.0 - r0) * ((1.0 - cos).powf(5.0))
         }
 
         let unit_direction = ray_in.direction().normalize();
-        let cos = (dot(&(unit_direction * -1.0), &hit_record.normal)).min(1.0);
+        let cos = (dot(&(-unit_direction), &hit_record.normal)).min(1.0);
         let sin = (1.0 - cos * cos).sqrt();
 
         let cannot_refract = (refraction_ratio * sin) > 1.0;
-        let direction;
-
-        if cannot_refract || reflectance(cos, refraction_ratio) > random_double() {
-            direction = unit_direction.reflect(&hit_record.normal);
+        let direction = if cannot_refract || reflectance(cos, refraction_ratio) > random_double() {
+            unit_direction.reflect(&hit_record.normal)
         } else {
-            direction = refract(&unit_direction, &hit_record.normal, refraction_ratio);
-        }
+            refract(&unit_direction, &hit_record.normal, refraction_ratio)
+        };
         ScatterResult {
-            scattered_ray: Some(Ray::new(hit_record.hit_point, direction)),
+            scattered_ray: Some(Ray::new_at_time(
+                hit_record.hit_point,
+                direction,
+                ray_in.time(),
+            )),
             attenuation: Color::white(),
             emitted: Color::black(),
         }
     }
+
+    // The normal faces the incoming ray, refracted rays leave on the other side.
+    fn bounce_kind(&self, _ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> BounceKind {
+        if dot(direction, &hit_record.normal) < 0.0 {
+            BounceKind::Transmission
+        } else {
+            BounceKind::Glossy
+        }
+    }
+}
+
+impl SceneDefinition for Dielectric {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!("dielectric ior={}", self.index_of_refraction))
+    }
 }
 
 fn random_double() -> f64 {
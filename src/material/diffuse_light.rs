@@ -2,6 +2,7 @@ use crate::geometry::ray::Ray;
 use crate::hittable::hittable::HitRecord;
 use crate::material::color::Color;
 use crate::material::material::{Material, ScatterResult};
+use crate::scene::writer::{format_color, SceneDefinition, SceneWriter};
 
 pub struct DiffuseLight {
     emit_color: Color,
@@ -21,4 +22,17 @@ impl Material for DiffuseLight {
             emitted: self.emit_color,
         }
     }
+
+    fn is_emissive(&self) -> bool {
+        true
+    }
+}
+
+impl SceneDefinition for DiffuseLight {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!(
+            "diffuse_light color={}",
+            format_color(&self.emit_color)
+        ))
+    }
 }
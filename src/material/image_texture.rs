@@ -1,33 +1,51 @@
 use crate::geometry::vec3::Vec3;
 use crate::material::color::Color;
 use crate::material::texture::Texture;
+use crate::scene::writer::{format_path, SceneDefinition, SceneWriter};
 use image::io::Reader as ImageReader;
 use image::{DynamicImage, GenericImageView, Pixel};
-use std::path::Path;
 
+#[derive(Clone)]
 pub struct ImageTexture {
+    path: String,
     img: DynamicImage,
 }
 
 impl ImageTexture {
-    pub fn new(path: &str) -> ImageTexture {
-        ImageTexture {
-            img: ImageReader::open(path).unwrap().decode().unwrap(),
-        }
+    pub fn new(path: &str) -> Result<ImageTexture, String> {
+        Ok(ImageTexture {
+            path: String::from(path),
+            img: load_image(path)?,
+        })
     }
 }
 
+pub fn load_image(path: &str) -> Result<DynamicImage, String> {
+    ImageReader::open(path)
+        .map_err(|e| format!("Could not open {}: {}", path, e))?
+        .decode()
+        .map_err(|e| format!("Could not decode {}: {}", path, e))
+}
+
 impl Texture for ImageTexture {
     fn get_color(&self, u: f64, v: f64, _point: Vec3) -> Color {
-        let x = (u * self.img.width() as f64) as u32;
-        let y = (v * self.img.height() as f64) as u32;
+        // Coordinates outside [0, 1] repeat the image, as OBJ texture coordinates expect.
+        let (width, height) = self.img.dimensions();
+        let x = ((u.rem_euclid(1.0) * width as f64) as u32).min(width - 1);
+        let y = ((v.rem_euclid(1.0) * height as f64) as u32).min(height - 1);
 
         let color = self.img.get_pixel(x, y).to_rgb();
 
         Color::new(
-            (color[0] as f64 / 256.0),
+            color[0] as f64 / 256.0,
             color[1] as f64 / 256.0,
             color[2] as f64 / 256.0,
         )
     }
 }
+
+impl SceneDefinition for ImageTexture {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!("image path={}", format_path(&self.path)?))
+    }
+}
@@ -0,0 +1,69 @@
+use crate::geometry::ray::Ray;
+use crate::geometry::sampling::{uniform_sphere, uniform_sphere_pdf};
+use crate::geometry::vec3::Vec3;
+use crate::hittable::hittable::HitRecord;
+use crate::material::color::Color;
+use crate::material::material::{Material, ScatterResult};
+use crate::material::solid_color_texture::SolidColorTexture;
+use crate::material::texture::Texture;
+use crate::scene::writer::{SceneDefinition, SceneWriter};
+
+// Phase function of a participating medium, scatters the same amount in every direction. The
+// texture is the medium's albedo.
+pub struct Isotropic {
+    texture: Box<dyn Texture + Send + Sync>,
+}
+
+impl Isotropic {
+    pub fn new_from_color(color: Color) -> Isotropic {
+        Isotropic {
+            texture: Box::new(SolidColorTexture::new(color)),
+        }
+    }
+
+    pub fn new_from_texture(texture: Box<dyn Texture + Send + Sync>) -> Isotropic {
+        Isotropic { texture }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> ScatterResult {
+        ScatterResult {
+            scattered_ray: Some(Ray::new_at_time(
+                hit_record.hit_point,
+                uniform_sphere(&mut rand::thread_rng()),
+                ray_in.time(),
+            )),
+            attenuation: self
+                .texture
+                .get_color(hit_record.u, hit_record.v, hit_record.hit_point),
+            emitted: Color::black(),
+        }
+    }
+
+    fn eval(&self, _ray_in: &Ray, hit_record: &HitRecord, _direction: &Vec3) -> Color {
+        let albedo = self
+            .texture
+            .get_color(hit_record.u, hit_record.v, hit_record.hit_point);
+        albedo * uniform_sphere_pdf()
+    }
+
+    fn pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Option<f64> {
+        Some(uniform_sphere_pdf())
+    }
+}
+
+impl SceneDefinition for Isotropic {
+    fn scene_definition(&self, writer: &mut SceneWriter) -> Result<String, String> {
+        if let Some(color) = writer
+            .texture_definition(self.texture.as_ref())?
+            .strip_prefix("solid ")
+        {
+            return Ok(format!("isotropic {}", color));
+        }
+        Ok(format!(
+            "isotropic texture={}",
+            writer.texture(self.texture.as_ref())?
+        ))
+    }
+}
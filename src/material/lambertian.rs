@@ -1,10 +1,12 @@
 use crate::geometry::ray::Ray;
-use crate::geometry::vec3::Vec3;
+use crate::geometry::sampling::{cosine_hemisphere, cosine_hemisphere_pdf, frame};
+use crate::geometry::vec3::{dot, Vec3};
 use crate::hittable::hittable::HitRecord;
 use crate::material::color::Color;
 use crate::material::material::{Material, ScatterResult};
 use crate::material::solid_color_texture::SolidColorTexture;
 use crate::material::texture::Texture;
+use crate::scene::writer::{SceneDefinition, SceneWriter};
 
 pub struct Lambertian {
     texture: Box<dyn Texture + Send + Sync>,
@@ -22,27 +24,52 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord) -> ScatterResult {
-        let mut scatter_direction = hit_record.normal + random_in_unit_sphere();
-        if scatter_direction.near_zero() {
-            scatter_direction = hit_record.normal;
-        }
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> ScatterResult {
+        let scatter_direction =
+            frame(&hit_record.normal) * cosine_hemisphere(&mut rand::thread_rng());
 
         ScatterResult {
-            scattered_ray: Some(Ray::new(hit_record.hit_point, scatter_direction)),
+            scattered_ray: Some(Ray::new_at_time(
+                hit_record.hit_point,
+                scatter_direction,
+                ray_in.time(),
+            )),
             attenuation: self
                 .texture
                 .get_color(hit_record.u, hit_record.v, hit_record.hit_point),
             emitted: Color::black(),
         }
     }
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Color {
+        let albedo = self
+            .texture
+            .get_color(hit_record.u, hit_record.v, hit_record.hit_point);
+        let pdf = self.pdf(ray_in, hit_record, direction).unwrap_or(0.0);
+        albedo * pdf
+    }
+
+    // Cosine weighted, so the attenuation is the albedo.
+    fn pdf(&self, _ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<f64> {
+        Some(cosine_hemisphere_pdf(dot(
+            &hit_record.normal,
+            &direction.normalize(),
+        )))
+    }
 }
 
-fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = Vec3::random(-1.0, 1.0);
-        if p.len_squared() < 1.0 {
-            return p;
+impl SceneDefinition for Lambertian {
+    fn scene_definition(&self, writer: &mut SceneWriter) -> Result<String, String> {
+        // Solid colors are written inline as `color=r,g,b` instead of as a separate texture.
+        if let Some(color) = writer
+            .texture_definition(self.texture.as_ref())?
+            .strip_prefix("solid ")
+        {
+            return Ok(format!("lambertian {}", color));
         }
+        Ok(format!(
+            "lambertian texture={}",
+            writer.texture(self.texture.as_ref())?
+        ))
     }
 }
@@ -1,10 +1,8 @@
 use crate::geometry::ray::Ray;
-use crate::geometry::vec3::{dot, Vec3};
+use crate::geometry::vec3::Vec3;
 use crate::hittable::hittable::HitRecord;
 use crate::material::color::Color;
-use crate::material::lambertian::Lambertian;
-use crate::material::texture::Texture;
-use rand::Rng;
+use std::any::Any;
 
 pub struct ScatterResult {
     pub scattered_ray: Option<Ray>,
@@ -12,6 +10,41 @@ pub struct ScatterResult {
     pub emitted: Color,
 }
 
-pub trait Material {
+// The kind of bounce a scattered ray makes, the render settings can limit how many of each a path
+// takes.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum BounceKind {
+    Diffuse,
+    Glossy,
+    Transmission,
+}
+
+// `Any` lets the scene writer find the concrete type behind a trait object.
+pub trait Material: Any {
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> ScatterResult;
+
+    // Objects with emissive materials are registered as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    // Light arriving from `direction` that is scattered back along the ray, per unit solid angle
+    // and with the cosine included. For the directions `scatter` picks the attenuation is
+    // eval / pdf.
+    fn eval(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Color {
+        Color::black()
+    }
+
+    // The density of `scatter` picking `direction`, per unit solid angle. None for materials that
+    // scatter into single directions, like mirrors and glass, which can't be sampled towards
+    // lights.
+    fn pdf(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> Option<f64> {
+        None
+    }
+
+    // Rough surfaces and media scatter diffusely, mirror-like reflections are glossy and rays
+    // passing through a surface are transmitted.
+    fn bounce_kind(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> BounceKind {
+        BounceKind::Diffuse
+    }
 }
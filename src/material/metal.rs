@@ -1,8 +1,11 @@
 use crate::geometry::ray::Ray;
+use crate::geometry::sampling::uniform_ball;
 use crate::geometry::vec3::{dot, Vec3};
 use crate::hittable::hittable::HitRecord;
 use crate::material::color::Color;
-use crate::material::material::{Material, ScatterResult};
+use crate::material::material::{BounceKind, Material, ScatterResult};
+use crate::scene::writer::{format_color, SceneDefinition, SceneWriter};
+use std::f64::consts::PI;
 
 pub struct Metal {
     albedo: Color,
@@ -11,25 +14,20 @@ pub struct Metal {
 
 impl Metal {
     pub fn new(albedo: Color, fz: f64) -> Metal {
-        let mut fuzz = fz;
-
-        if fz > 1.0 {
-            fuzz = 1.0;
-        }
-        if fz < 0.0 {
-            fuzz = 0.0;
+        Metal {
+            albedo,
+            fuzz: fz.clamp(0.0, 1.0),
         }
-
-        Metal { albedo, fuzz }
     }
 }
 
 impl Material for Metal {
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord) -> ScatterResult {
         let unit_vector = ray_in.direction().unit_vector();
-        let scatter_direction =
-            unit_vector.reflect(&hit_record.normal) + self.fuzz * random_in_unit_sphere();
-        let scattered_ray = Ray::new(hit_record.hit_point, scatter_direction);
+        let scatter_direction = unit_vector.reflect(&hit_record.normal)
+            + self.fuzz * uniform_ball(&mut rand::thread_rng());
+        let scattered_ray =
+            Ray::new_at_time(hit_record.hit_point, scatter_direction, ray_in.time());
         if dot(scattered_ray.direction(), &hit_record.normal) > 0.0 {
             ScatterResult {
                 scattered_ray: Some(scattered_ray),
@@ -44,12 +42,84 @@ impl Material for Metal {
             }
         }
     }
-}
-fn random_in_unit_sphere() -> Vec3 {
-    loop {
-        let p = Vec3::random(-1.0, 1.0);
-        if p.len_squared() < 1.0 {
-            return p;
+
+    fn eval(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Color {
+        if dot(direction, &hit_record.normal) <= 0.0 {
+            return Color::black();
+        }
+        let pdf = self.pdf(ray_in, hit_record, direction).unwrap_or(0.0);
+        self.albedo * pdf
+    }
+
+    // The scattered direction points at a uniformly distributed point of the ball with radius
+    // `fuzz` around the tip of the reflected unit vector. The density of a direction is the part
+    // of the ball along it, each point weighted by the squared distance.
+    fn pdf(&self, ray_in: &Ray, hit_record: &HitRecord, direction: &Vec3) -> Option<f64> {
+        if self.fuzz == 0.0 {
+            return None;
+        }
+        let reflected = ray_in.direction().unit_vector().reflect(&hit_record.normal);
+        let direction = direction.normalize();
+        // Where the line along `direction` enters and leaves the ball.
+        let b = dot(&direction, &reflected);
+        let discriminant = b * b - (1.0 - self.fuzz * self.fuzz);
+        if discriminant <= 0.0 {
+            return Some(0.0);
         }
+        let leave = b + discriminant.sqrt();
+        let enter = (b - discriminant.sqrt()).max(0.0);
+        if leave <= 0.0 {
+            return Some(0.0);
+        }
+        Some((leave.powi(3) - enter.powi(3)) / (4.0 * PI * self.fuzz.powi(3)))
+    }
+
+    fn bounce_kind(&self, _ray_in: &Ray, _hit_record: &HitRecord, _direction: &Vec3) -> BounceKind {
+        BounceKind::Glossy
     }
 }
+
+impl SceneDefinition for Metal {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!(
+            "metal color={} fuzz={}",
+            format_color(&self.albedo),
+            self.fuzz
+        ))
+    }
+}
+#[test]
+fn fuzzy_reflection_pdf_matches_scatter() {
+    // Straight down onto the surface, with fuzz 0.5 the directions fill a cone of half angle 30
+    // degrees around the normal. The average of 1 / pdf over scattered directions is its solid
+    // angle.
+    let metal = Metal::new(Color::new(0.8, 0.6, 0.4), 0.5);
+    let hit_record = HitRecord {
+        hit_point: Vec3::origin(),
+        normal: Vec3::new(0.0, 1.0, 0.0),
+        front_face: true,
+        t: 1.0,
+        u: 0.0,
+        v: 0.0,
+        material: &metal,
+    };
+    let ray = Ray::new(Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+    let n = 20000;
+    let mut solid_angle = 0.0;
+    for _ in 0..n {
+        let scattered = metal.scatter(&ray, &hit_record).scattered_ray.unwrap();
+        let direction = scattered.direction();
+        let pdf = metal.pdf(&ray, &hit_record, direction).unwrap();
+        let ratio = (metal.eval(&ray, &hit_record, direction) / pdf).as_vector();
+        assert!((ratio - metal.albedo.as_vector()).len() < 1e-9);
+        solid_angle += 1.0 / pdf / n as f64;
+    }
+    let expected = 2.0 * PI * (1.0 - 30f64.to_radians().cos());
+    assert!((solid_angle - expected).abs() < 0.02 * expected);
+
+    let outside = Vec3::new(1.0, 1.0, 0.0);
+    assert_eq!(metal.pdf(&ray, &hit_record, &outside), Some(0.0));
+    assert!(Metal::new(Color::white(), 0.0)
+        .pdf(&ray, &hit_record, &outside)
+        .is_none());
+}
@@ -1,11 +1,13 @@
 pub mod checkered_texture;
-pub(crate) mod color;
-pub(crate) mod dielectric;
-pub(crate) mod diffuse_light;
+pub mod color;
+pub mod dielectric;
+pub mod diffuse_light;
 pub mod image_texture;
-pub(crate) mod lambertian;
-pub(crate) mod material;
-pub(crate) mod metal;
+pub mod isotropic;
+pub mod lambertian;
+#[allow(clippy::module_inception)]
+pub mod material;
+pub mod metal;
 pub mod solid_color_texture;
 pub mod texture;
 pub mod triangle_image_texture;
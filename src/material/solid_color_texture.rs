@@ -1,6 +1,7 @@
 use crate::geometry::vec3::Vec3;
 use crate::material::color::Color;
 use crate::material::texture::Texture;
+use crate::scene::writer::{format_color, SceneDefinition, SceneWriter};
 
 pub struct SolidColorTexture {
     color: Color,
@@ -17,3 +18,9 @@ impl Texture for SolidColorTexture {
         self.color
     }
 }
+
+impl SceneDefinition for SolidColorTexture {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!("solid color={}", format_color(&self.color)))
+    }
+}
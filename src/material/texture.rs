@@ -1,6 +1,8 @@
 use crate::geometry::vec3::Vec3;
 use crate::material::color::Color;
+use std::any::Any;
 
-pub trait Texture {
+// `Any` lets the scene writer find the concrete type behind a trait object.
+pub trait Texture: Any {
     fn get_color(&self, u: f64, v: f64, point: Vec3) -> Color;
 }
@@ -1,8 +1,7 @@
 use crate::geometry::vec3::Vec3;
 use crate::material::color::Color;
-use crate::material::image_texture::ImageTexture;
+use crate::material::image_texture::load_image;
 use crate::material::texture::Texture;
-use image::io::Reader as ImageReader;
 use image::{DynamicImage, GenericImageView, Pixel};
 
 pub struct TriangleImageTexture {
@@ -14,13 +13,13 @@ pub struct TriangleImageTexture {
 }
 
 impl TriangleImageTexture {
-    pub fn new(path: &str, p1: Vec3, p2: Vec3, p3: Vec3) -> TriangleImageTexture {
-        TriangleImageTexture {
-            img: ImageReader::open(path).unwrap().decode().unwrap(),
+    pub fn new(path: &str, p1: Vec3, p2: Vec3, p3: Vec3) -> Result<TriangleImageTexture, String> {
+        Ok(TriangleImageTexture {
+            img: load_image(path)?,
             v1: p2 - p1,
             v2: p3 - p1,
             p1,
-        }
+        })
     }
 }
 
@@ -34,7 +33,7 @@ impl Texture for TriangleImageTexture {
         let color = self.img.get_pixel(x, y).to_rgb();
 
         Color::new(
-            (color[0] as f64 / 256.0),
+            color[0] as f64 / 256.0,
             color[1] as f64 / 256.0,
             color[2] as f64 / 256.0,
         )
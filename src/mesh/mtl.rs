@@ -0,0 +1,342 @@
+use std::fs;
+use std::path::Path;
+
+use crate::geometry::vec3::Vec3;
+use crate::material::color::Color;
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::image_texture::ImageTexture;
+use crate::material::lambertian::Lambertian;
+use crate::material::material::Material;
+use crate::material::metal::Metal;
+use crate::mesh::obj::ObjError;
+
+// A material from a Wavefront MTL library. Only the statements that map onto the built-in
+// materials are kept, everything else is ignored.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MtlMaterial {
+    pub name: String,
+    pub diffuse: Option<Vec3>,
+    pub specular: Option<Vec3>,
+    pub emission: Option<Vec3>,
+    pub shininess: Option<f64>,
+    pub index_of_refraction: Option<f64>,
+    // 1.0 is fully opaque, `Tr` is stored as 1 - Tr.
+    pub dissolve: Option<f64>,
+    pub illum: Option<u32>,
+    pub diffuse_map: Option<String>,
+    // Line of the `map_Kd` statement, errors loading the texture point there.
+    pub diffuse_map_line: usize,
+}
+
+impl MtlMaterial {
+    fn new(name: &str) -> MtlMaterial {
+        MtlMaterial {
+            name: String::from(name),
+            diffuse: None,
+            specular: None,
+            emission: None,
+            shininess: None,
+            index_of_refraction: None,
+            dissolve: None,
+            illum: None,
+            diffuse_map: None,
+            diffuse_map_line: 0,
+        }
+    }
+
+    // Picks the closest built-in material:
+    //  - a non-black `Ke` becomes a DiffuseLight,
+    //  - transparent materials (`d` < 1 or illum 4, 6, 7, 9) become a Dielectric with index `Ni`,
+    //  - reflective materials (illum 3, 5, 8) become a Metal colored by `Ks` with a fuzz derived
+    //    from `Ns`,
+    //  - everything else is a Lambertian textured by `map_Kd` or colored by `Kd`.
+    // Relative texture paths are resolved against `dir`.
+    pub fn to_material(&self, dir: &Path) -> Result<Box<dyn Material + Send + Sync>, String> {
+        if let Some(emission) = self.emission {
+            if !emission.near_zero() {
+                return Ok(Box::new(DiffuseLight::new(Color::new_from_vector(
+                    emission.max(&Vec3::origin()),
+                ))));
+            }
+        }
+
+        let illum = self.illum.unwrap_or(2);
+        if self.dissolve.unwrap_or(1.0) < 1.0 || [4, 6, 7, 9].contains(&illum) {
+            return Ok(Box::new(Dielectric::new(
+                self.index_of_refraction.unwrap_or(1.5),
+            )));
+        }
+
+        if [3, 5, 8].contains(&illum) {
+            let specular = self.specular.unwrap_or(Vec3::new(1.0, 1.0, 1.0));
+            let fuzz = (2.0 / (self.shininess.unwrap_or(0.0).max(0.0) + 2.0)).sqrt();
+            return Ok(Box::new(Metal::new(to_color(specular), fuzz)));
+        }
+
+        if let Some(diffuse_map) = &self.diffuse_map {
+            let path = dir.join(diffuse_map);
+            if !path.is_file() {
+                return Err(format!(
+                    "map_Kd '{}' of material '{}' does not exist",
+                    path.display(),
+                    self.name
+                ));
+            }
+            let path = path
+                .to_str()
+                .ok_or_else(|| format!("map_Kd of material '{}' is not valid UTF-8", self.name))?;
+            return Ok(Box::new(Lambertian::new_from_texture(Box::new(
+                ImageTexture::new(path)?,
+            ))));
+        }
+
+        let diffuse = self.diffuse.unwrap_or(Vec3::new(0.5, 0.5, 0.5));
+        Ok(Box::new(Lambertian::new_from_color(to_color(diffuse))))
+    }
+}
+
+// Surface colors are clamped to [0, 1].
+fn to_color(rgb: Vec3) -> Color {
+    Color::new(
+        rgb.x().clamp(0.0, 1.0),
+        rgb.y().clamp(0.0, 1.0),
+        rgb.z().clamp(0.0, 1.0),
+    )
+}
+
+pub fn read_mtl(file: &str) -> Result<Vec<MtlMaterial>, ObjError> {
+    let contents = fs::read_to_string(file).map_err(|e| ObjError {
+        file: String::from(file),
+        line: 0,
+        message: format!("could not read MTL file: {}", e),
+    })?;
+    parse_mtl(&contents, file)
+}
+
+// `file` is only used for error messages.
+pub fn parse_mtl(contents: &str, file: &str) -> Result<Vec<MtlMaterial>, ObjError> {
+    let mut materials: Vec<MtlMaterial> = vec![];
+
+    for (i, line) in contents.lines().enumerate() {
+        let error = |message: String| ObjError {
+            file: String::from(file),
+            line: i + 1,
+            message,
+        };
+
+        let line = match line.find('#') {
+            Some(i) => &line[..i],
+            None => line,
+        };
+        let mut tokens = line.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<&str>>();
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(error(String::from("newmtl needs a name")));
+            }
+            materials.push(MtlMaterial::new(&args.join(" ")));
+            continue;
+        }
+
+        let material = match materials.last_mut() {
+            Some(material) => material,
+            // Nothing but comments may come before the first newmtl.
+            None => return Err(error(format!("'{}' before the first newmtl", keyword))),
+        };
+        match keyword {
+            "Kd" => material.diffuse = Some(parse_rgb(&args).map_err(error)?),
+            "Ks" => material.specular = Some(parse_rgb(&args).map_err(error)?),
+            "Ke" => material.emission = Some(parse_rgb(&args).map_err(error)?),
+            "Ns" => material.shininess = Some(parse_single(&args).map_err(error)?),
+            "Ni" => material.index_of_refraction = Some(parse_single(&args).map_err(error)?),
+            "d" => {
+                // `d -halo 0.5` is the only option of d.
+                let args = if args.first() == Some(&"-halo") {
+                    &args[1..]
+                } else {
+                    &args[..]
+                };
+                material.dissolve = Some(parse_single(args).map_err(error)?);
+            }
+            "Tr" => material.dissolve = Some(1.0 - parse_single(&args).map_err(error)?),
+            "illum" => {
+                let illum = parse_single(&args).map_err(error)?;
+                if !(0.0..=10.0).contains(&illum) || illum.fract() != 0.0 {
+                    return Err(error(format!("invalid illum '{}'", args[0])));
+                }
+                material.illum = Some(illum as u32);
+            }
+            // Options such as `-s 1 1 1` come before the file name, which may contain spaces.
+            "map_Kd" => {
+                material.diffuse_map = Some(parse_map_file(&args).map_err(error)?);
+                material.diffuse_map_line = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    Ok(materials)
+}
+
+fn parse_f64(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .map_err(|_| format!("invalid number '{}'", value))
+}
+
+fn parse_single(args: &[&str]) -> Result<f64, String> {
+    match args {
+        [value] => parse_f64(value),
+        _ => Err(format!("expected a single number, got {}", args.len())),
+    }
+}
+
+// `Kd r g b`, a single value is used for all three channels. `spectral` and `xyz` colors are
+// not supported.
+fn parse_rgb(args: &[&str]) -> Result<Vec3, String> {
+    match args {
+        [v] => {
+            let v = parse_f64(v)?;
+            Ok(Vec3::new(v, v, v))
+        }
+        [r, g, b] => Ok(Vec3::new(parse_f64(r)?, parse_f64(g)?, parse_f64(b)?)),
+        _ => Err(format!("expected r g b, got '{}'", args.join(" "))),
+    }
+}
+
+fn parse_map_file(args: &[&str]) -> Result<String, String> {
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') {
+        let option = args[i];
+        i += 1;
+        match option {
+            // -o, -s and -t take one to three numbers.
+            "-o" | "-s" | "-t" => {
+                let start = i;
+                while i < args.len() && i - start < 3 && parse_f64(args[i]).is_ok() {
+                    i += 1;
+                }
+                if i == start {
+                    return Err(format!("missing value for map option '{}'", option));
+                }
+            }
+            "-mm" => i += 2,
+            _ => i += 1,
+        }
+    }
+    if i >= args.len() {
+        return Err(String::from("map_Kd needs a file name"));
+    }
+    Ok(args[i..].join(" "))
+}
+
+#[test]
+fn parse_materials() {
+    let materials = parse_mtl(
+        "# Exported
+        newmtl skin
+        Kd 0.8 0.6 0.5
+        Ks 0.1 0.1 0.1
+        Ns 10
+        illum 2
+
+        newmtl chrome
+        Ks 0.9 0.9 0.9
+        Ns 900
+        illum 3
+
+        newmtl glass
+        Ni 1.45
+        d 0.1
+
+        newmtl lamp
+        Ke 4 4 4
+
+        newmtl wood
+        Kd 1
+        map_Kd -s 2 2 -clamp on textures/wood grain.png
+        Tr 0",
+        "test.mtl",
+    )
+    .unwrap();
+
+    assert_eq!(materials.len(), 5);
+    assert_eq!(materials[0].name, "skin");
+    assert_eq!(materials[0].diffuse, Some(Vec3::new(0.8, 0.6, 0.5)));
+    assert_eq!(materials[0].illum, Some(2));
+    assert_eq!(materials[1].shininess, Some(900.0));
+    assert_eq!(materials[2].index_of_refraction, Some(1.45));
+    assert_eq!(materials[2].dissolve, Some(0.1));
+    assert_eq!(materials[3].emission, Some(Vec3::new(4.0, 4.0, 4.0)));
+    assert_eq!(materials[4].diffuse, Some(Vec3::new(1.0, 1.0, 1.0)));
+    assert_eq!(
+        materials[4].diffuse_map.as_deref(),
+        Some("textures/wood grain.png")
+    );
+    assert_eq!(materials[4].dissolve, Some(1.0));
+}
+
+#[test]
+fn map_materials_to_built_in_materials() {
+    let materials = parse_mtl(
+        "newmtl skin
+        Kd 0.8 0.6 0.5
+        newmtl chrome
+        Ks 0.9 0.9 0.9
+        illum 3
+        newmtl glass
+        d 0.5
+        newmtl lamp
+        Ke 4 4 4
+        newmtl missing_texture
+        map_Kd missing.png",
+        "test.mtl",
+    )
+    .unwrap();
+
+    let definitions = materials
+        .iter()
+        .take(4)
+        .map(|m| {
+            let material = m.to_material(Path::new(".")).unwrap();
+            let mut writer = crate::scene::writer::SceneWriter::new();
+            writer.material_definition(material.as_ref()).unwrap()
+        })
+        .collect::<Vec<String>>();
+    assert_eq!(
+        definitions,
+        vec![
+            "lambertian color=0.8,0.6,0.5",
+            "metal color=0.9,0.9,0.9 fuzz=1",
+            "dielectric ior=1.5",
+            "diffuse_light color=4,4,4",
+        ]
+    );
+    assert!(materials[4].to_material(Path::new(".")).is_err());
+}
+
+#[test]
+fn parse_mtl_errors_report_line() {
+    let cases = [
+        ("Kd 1 1 1", 1),
+        ("newmtl a\nKd 1 1", 2),
+        ("newmtl a\n\nNs x", 3),
+        ("newmtl a\nillum 12", 2),
+        ("newmtl a\nmap_Kd -s 1 1 1", 2),
+        ("newmtl a\nmap_Kd -s wood.png", 2),
+        ("newmtl", 1),
+    ];
+
+    for (contents, line) in cases.iter() {
+        let error = parse_mtl(contents, "bad.mtl")
+            .err()
+            .unwrap_or_else(|| panic!("Expected an error for '{}'", contents));
+        assert_eq!(error.line, *line, "{}", error);
+    }
+}
@@ -0,0 +1,704 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::geometry::vec3::{cross, dot, Vec3};
+use crate::hittable::triangle_mesh::TriangleMesh;
+use crate::material::color::Color;
+use crate::material::lambertian::Lambertian;
+use crate::material::material::Material;
+use crate::mesh::mtl::read_mtl;
+
+// Wavefront OBJ reader. Polygons are fan triangulated, negative indices are resolved against the
+// elements read so far and statements that don't describe geometry (`s`, `l`, curves...) are
+// ignored. Materials come from the `mtllib` libraries, see `mesh::mtl`.
+
+#[derive(Debug)]
+pub struct ObjError {
+    pub file: String,
+    // 1-based, 0 when the error is not tied to a line.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line > 0 {
+            write!(f, "{}:{}: {}", self.file, self.line, self.message)
+        } else {
+            write!(f, "{}: {}", self.file, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+// Indices into the position, texture coordinate and normal lists of an `ObjMesh`.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct ObjVertex {
+    pub position: usize,
+    pub tex_coord: Option<usize>,
+    pub normal: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjTriangle {
+    pub vertices: [ObjVertex; 3],
+    // Index into `ObjMesh::groups`.
+    pub group: usize,
+    // Index into `ObjMesh::materials`, None before the first `usemtl`.
+    pub material: Option<usize>,
+}
+
+// The `o` object and `g` group names in effect for a run of triangles.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ObjGroup {
+    pub object: String,
+    pub group: String,
+}
+
+pub struct ObjMesh {
+    pub positions: Vec<Vec3>,
+    pub tex_coords: Vec<(f64, f64)>,
+    pub normals: Vec<Vec3>,
+    pub triangles: Vec<ObjTriangle>,
+    pub groups: Vec<ObjGroup>,
+    // `mtllib` paths as written in the file, relative to the OBJ file.
+    pub material_libraries: Vec<String>,
+    // `usemtl` names in order of first use.
+    pub materials: Vec<String>,
+}
+
+impl ObjMesh {
+    // Builds an indexed `TriangleMesh`, every distinct position/texture coordinate/normal
+    // combination becomes one mesh vertex. Faces with a normal on every vertex are smooth shaded.
+    // Texture coordinates are flipped vertically to match the top-left origin used by image
+    // textures. `materials` holds the material of each entry of `self.materials`, `default` is
+    // used for faces without `usemtl`.
+    pub fn triangle_mesh(
+        &self,
+        materials: &[Arc<Box<dyn Material + Send + Sync>>],
+        default: &Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Result<TriangleMesh, String> {
+        let has_tex_coords = self
+            .triangles
+            .iter()
+            .any(|t| t.vertices.iter().any(|v| v.tex_coord.is_some()));
+        let has_normals = self
+            .triangles
+            .iter()
+            .any(|t| t.vertices.iter().any(|v| v.normal.is_some()));
+
+        let mut vertices: HashMap<(usize, Option<usize>, Option<usize>), u32> = HashMap::new();
+        let mut positions = vec![];
+        let mut tex_coords = vec![];
+        let mut normals = vec![];
+        let mut faces = vec![];
+        let mut face_materials = vec![];
+        for triangle in self.triangles.iter() {
+            let face = triangle.vertices.map(|v| {
+                *vertices
+                    .entry((v.position, v.tex_coord, v.normal))
+                    .or_insert_with(|| {
+                        positions.push(self.positions[v.position]);
+                        // Missing values are zero, a zero normal makes the face flat.
+                        let (u, tv) = v.tex_coord.map_or((0.0, 1.0), |i| self.tex_coords[i]);
+                        tex_coords.push((u, 1.0 - tv));
+                        normals.push(v.normal.map_or(Vec3::origin(), |i| self.normals[i]));
+                        positions.len() as u32 - 1
+                    })
+            });
+            faces.push(face);
+            // Material 0 is the default, the others follow the order of `self.materials`.
+            face_materials.push(triangle.material.map_or(0, |i| i as u32 + 1));
+        }
+
+        let mut mesh_materials = vec![Arc::clone(default)];
+        mesh_materials.extend(materials.iter().map(Arc::clone));
+        TriangleMesh::new(
+            positions,
+            if has_normals { Some(normals) } else { None },
+            if has_tex_coords {
+                Some(tex_coords)
+            } else {
+                None
+            },
+            faces,
+            face_materials,
+            mesh_materials,
+        )
+    }
+
+    // Gives every face vertex without a `vn` the average of the normals of the faces sharing its
+    // position, weighted by the angle of each face at that vertex. Faces that already have normals
+    // are left alone.
+    pub fn compute_smooth_normals(&mut self) {
+        let mut sums = vec![Vec3::new(0.0, 0.0, 0.0); self.positions.len()];
+        for triangle in self.triangles.iter() {
+            let p = triangle.vertices.map(|v| self.positions[v.position]);
+            let normal = cross(&(p[1] - p[0]), &(p[2] - p[0]));
+            if normal.near_zero() {
+                continue;
+            }
+            let normal = normal.normalize();
+            for k in 0..3 {
+                let e1 = p[(k + 1) % 3] - p[k];
+                let e2 = p[(k + 2) % 3] - p[k];
+                let cos = dot(&e1, &e2) / (e1.len() * e2.len());
+                let angle = cos.clamp(-1.0, 1.0).acos();
+                let sum = &mut sums[triangle.vertices[k].position];
+                *sum += angle * normal;
+            }
+        }
+
+        // Smooth normals are appended after the file's normals, one per position.
+        let offset = self.normals.len();
+        self.normals.extend(sums);
+        for triangle in self.triangles.iter_mut() {
+            for vertex in triangle.vertices.iter_mut() {
+                if vertex.normal.is_none() {
+                    vertex.normal = Some(offset + vertex.position);
+                }
+            }
+        }
+    }
+}
+
+// Used for faces without a material when the caller has none to offer, the same grey as an MTL
+// material without `Kd`.
+pub fn default_material() -> Arc<Box<dyn Material + Send + Sync>> {
+    Arc::new(Box::new(Lambertian::new_from_color(Color::new(
+        0.5, 0.5, 0.5,
+    ))))
+}
+
+// Loads an OBJ file as a single mesh with the materials of its `mtllib` libraries. `material` is
+// used for faces without `usemtl` and for material names that no library defines. With
+// `smooth_normals`, faces without `vn` normals get computed ones instead of being flat shaded.
+pub fn load_obj(
+    file: &str,
+    material: &Arc<Box<dyn Material + Send + Sync>>,
+    smooth_normals: bool,
+) -> Result<TriangleMesh, ObjError> {
+    let mut mesh = read_obj(file)?;
+    if smooth_normals {
+        mesh.compute_smooth_normals();
+    }
+
+    let dir = Path::new(file).parent().unwrap_or_else(|| Path::new(""));
+    let mut library: HashMap<String, Arc<Box<dyn Material + Send + Sync>>> = HashMap::new();
+    for path in mesh.material_libraries.iter() {
+        let path = dir.join(path);
+        let path = path.to_string_lossy();
+        for mtl in read_mtl(&path)? {
+            // The first definition of a name wins.
+            if library.contains_key(&mtl.name) {
+                continue;
+            }
+            // Texture maps are relative to the library.
+            let mtl_dir = Path::new(path.as_ref()).parent().unwrap_or(dir);
+            // Only loading `map_Kd` can fail.
+            let converted = mtl.to_material(mtl_dir).map_err(|message| ObjError {
+                file: String::from(path.as_ref()),
+                line: mtl.diffuse_map_line,
+                message,
+            })?;
+            library.insert(mtl.name, Arc::new(converted));
+        }
+    }
+
+    let materials = mesh
+        .materials
+        .iter()
+        .map(|name| Arc::clone(library.get(name).unwrap_or(material)))
+        .collect::<Vec<Arc<Box<dyn Material + Send + Sync>>>>();
+    mesh.triangle_mesh(&materials, material)
+        .map_err(|message| ObjError {
+            file: String::from(file),
+            line: 0,
+            message,
+        })
+}
+
+pub fn read_obj(file: &str) -> Result<ObjMesh, ObjError> {
+    let contents = fs::read_to_string(file).map_err(|e| ObjError {
+        file: String::from(file),
+        line: 0,
+        message: format!("could not read OBJ file: {}", e),
+    })?;
+    parse_obj(&contents, file)
+}
+
+// `file` is only used for error messages.
+pub fn parse_obj(contents: &str, file: &str) -> Result<ObjMesh, ObjError> {
+    let mut mesh = ObjMesh {
+        positions: vec![],
+        tex_coords: vec![],
+        normals: vec![],
+        triangles: vec![],
+        groups: vec![ObjGroup {
+            object: String::new(),
+            group: String::from("default"),
+        }],
+        material_libraries: vec![],
+        materials: vec![],
+    };
+    let mut material = None;
+
+    let mut lines = contents.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let line_number = i + 1;
+        let error = |message: String| ObjError {
+            file: String::from(file),
+            line: line_number,
+            message,
+        };
+
+        // A trailing backslash continues the statement on the next line.
+        let mut statement = String::from(strip_comment(line));
+        while statement.ends_with('\\') {
+            statement.pop();
+            match lines.next() {
+                Some((_, next)) => {
+                    statement.push(' ');
+                    statement.push_str(strip_comment(next));
+                }
+                None => break,
+            }
+        }
+
+        let mut tokens = statement.split_whitespace();
+        let keyword = match tokens.next() {
+            Some(keyword) => keyword,
+            None => continue,
+        };
+        let args = tokens.collect::<Vec<&str>>();
+
+        match keyword {
+            "v" => {
+                if args.len() < 3 {
+                    return Err(error(format!("expected 3 coordinates, got {}", args.len())));
+                }
+                // A 4th value is the rational weight, 6 values are a position and a color.
+                mesh.positions.push(parse_vec3(&args).map_err(error)?);
+            }
+            "vt" => {
+                if args.is_empty() {
+                    return Err(error(String::from("expected a texture coordinate")));
+                }
+                let u = parse_f64(args[0]).map_err(error)?;
+                let v = match args.get(1) {
+                    Some(v) => parse_f64(v).map_err(error)?,
+                    None => 0.0,
+                };
+                mesh.tex_coords.push((u, v));
+            }
+            "vn" => {
+                if args.len() != 3 {
+                    return Err(error(format!("expected 3 coordinates, got {}", args.len())));
+                }
+                mesh.normals.push(parse_vec3(&args).map_err(error)?);
+            }
+            "f" => {
+                if args.len() < 3 {
+                    return Err(error(format!(
+                        "a face needs at least 3 vertices, got {}",
+                        args.len()
+                    )));
+                }
+                let vertices = args
+                    .iter()
+                    .map(|arg| parse_vertex(arg, &mesh))
+                    .collect::<Result<Vec<ObjVertex>, String>>()
+                    .map_err(error)?;
+
+                let group = mesh.groups.len() - 1;
+                for k in 1..vertices.len() - 1 {
+                    mesh.triangles.push(ObjTriangle {
+                        vertices: [vertices[0], vertices[k], vertices[k + 1]],
+                        group,
+                        material,
+                    });
+                }
+            }
+            "o" => {
+                let group = mesh.groups.last().unwrap().group.clone();
+                start_group(&mut mesh, args.join(" "), group);
+            }
+            "g" => {
+                let object = mesh.groups.last().unwrap().object.clone();
+                let group = if args.is_empty() {
+                    String::from("default")
+                } else {
+                    args.join(" ")
+                };
+                start_group(&mut mesh, object, group);
+            }
+            "mtllib" => {
+                if args.is_empty() {
+                    return Err(error(String::from("mtllib needs a file name")));
+                }
+                mesh.material_libraries
+                    .extend(args.iter().map(|arg| String::from(*arg)));
+            }
+            "usemtl" => {
+                if args.is_empty() {
+                    return Err(error(String::from("usemtl needs a material name")));
+                }
+                let name = args.join(" ");
+                material = Some(match mesh.materials.iter().position(|m| *m == name) {
+                    Some(i) => i,
+                    None => {
+                        mesh.materials.push(name);
+                        mesh.materials.len() - 1
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(mesh)
+}
+
+// Groups that ended up without triangles are replaced instead of kept around.
+fn start_group(mesh: &mut ObjMesh, object: String, group: String) {
+    let current = mesh.groups.len() - 1;
+    if mesh.triangles.last().map(|t| t.group) != Some(current) {
+        mesh.groups.pop();
+    }
+    mesh.groups.push(ObjGroup { object, group });
+}
+
+fn strip_comment(line: &str) -> &str {
+    match line.find('#') {
+        Some(i) => line[..i].trim_end(),
+        None => line.trim_end(),
+    }
+}
+
+fn parse_f64(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .map_err(|_| format!("invalid number '{}'", value))
+}
+
+fn parse_vec3(args: &[&str]) -> Result<Vec3, String> {
+    Ok(Vec3::new(
+        parse_f64(args[0])?,
+        parse_f64(args[1])?,
+        parse_f64(args[2])?,
+    ))
+}
+
+// Parses `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+fn parse_vertex(arg: &str, mesh: &ObjMesh) -> Result<ObjVertex, String> {
+    let parts = arg.split('/').collect::<Vec<&str>>();
+    if parts.len() > 3 {
+        return Err(format!("invalid face vertex '{}'", arg));
+    }
+
+    let position = resolve_index(parts[0], mesh.positions.len(), "vertex")?;
+    let tex_coord = match parts.get(1) {
+        Some(index) if !index.is_empty() => Some(resolve_index(
+            index,
+            mesh.tex_coords.len(),
+            "texture coordinate",
+        )?),
+        _ => None,
+    };
+    let normal = match parts.get(2) {
+        Some(index) if !index.is_empty() => {
+            Some(resolve_index(index, mesh.normals.len(), "normal")?)
+        }
+        _ => None,
+    };
+
+    Ok(ObjVertex {
+        position,
+        tex_coord,
+        normal,
+    })
+}
+
+// OBJ indices start at 1, negative indices count back from the last element read.
+fn resolve_index(index: &str, count: usize, kind: &str) -> Result<usize, String> {
+    let i = index
+        .parse::<i64>()
+        .map_err(|_| format!("invalid {} index '{}'", kind, index))?;
+    let resolved = if i > 0 { i - 1 } else { count as i64 + i };
+    if i == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!(
+            "{} index {} is out of range, {} defined so far",
+            kind, i, count
+        ));
+    }
+    Ok(resolved as usize)
+}
+
+#[test]
+fn parse_triangulates_polygons() {
+    let mesh = parse_obj(
+        "# A unit square and a pentagon
+        v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 0 1 0
+        v 0.5 1.5 0
+
+        f 1 2 3 4
+        f  1   2 3   5 4   # five vertices, extra blanks",
+        "test.obj",
+    )
+    .unwrap();
+
+    assert_eq!(mesh.positions.len(), 5);
+    assert_eq!(mesh.triangles.len(), 2 + 3);
+    let positions = mesh.triangles[1]
+        .vertices
+        .iter()
+        .map(|v| v.position)
+        .collect::<Vec<usize>>();
+    assert_eq!(positions, vec![0, 2, 3]);
+}
+
+#[test]
+fn parse_tex_coords_normals_and_relative_indices() {
+    let mesh = parse_obj(
+        "v 0 0 0
+        v 1 0 0
+        v 0 1 0
+        vt 0 0
+        vt 1 0
+        vt 0 1
+        vn 0 0 1
+        f -3/-3/-1 -2/-2/-1 -1/-1/-1
+        f 1//1 2//1 3//1
+        f 1/1 2/2 3/3",
+        "test.obj",
+    )
+    .unwrap();
+
+    assert_eq!(mesh.tex_coords, vec![(0.0, 0.0), (1.0, 0.0), (0.0, 1.0)]);
+    assert_eq!(mesh.normals, vec![Vec3::new(0.0, 0.0, 1.0)]);
+    assert_eq!(mesh.triangles.len(), 3);
+    assert_eq!(
+        mesh.triangles[0].vertices[2],
+        ObjVertex {
+            position: 2,
+            tex_coord: Some(2),
+            normal: Some(0),
+        }
+    );
+    assert_eq!(mesh.triangles[1].vertices[1].tex_coord, None);
+    assert_eq!(mesh.triangles[1].vertices[1].normal, Some(0));
+    assert_eq!(mesh.triangles[2].vertices[0].normal, None);
+}
+
+#[test]
+fn parse_groups_and_objects() {
+    let mesh = parse_obj(
+        "o cube
+        v 0 0 0
+        v 1 0 0
+        v 0 1 0
+        g top
+        f 1 2 3
+        g bottom side
+        f 1 2 3
+        o other
+        f 1 2 3",
+        "test.obj",
+    )
+    .unwrap();
+
+    let groups = mesh
+        .triangles
+        .iter()
+        .map(|t| {
+            let g = &mesh.groups[t.group];
+            (g.object.as_str(), g.group.as_str())
+        })
+        .collect::<Vec<(&str, &str)>>();
+    assert_eq!(
+        groups,
+        vec![
+            ("cube", "top"),
+            ("cube", "bottom side"),
+            ("other", "bottom side")
+        ]
+    );
+}
+
+#[test]
+fn parse_errors_report_line() {
+    let cases = [
+        ("v 1 2", 1),
+        ("v 0 0 0\nv 1 x 0", 2),
+        ("v 0 0 0\nv 1 0 0\nv 0 1 0\n\nf 1 2 4", 5),
+        ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 0", 4),
+        ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf -4 2 3", 4),
+        ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2 3", 4),
+        ("v 0 0 0\nv 1 0 0\nf 1 2", 3),
+    ];
+
+    for (contents, line) in cases.iter() {
+        let error = parse_obj(contents, "bad.obj")
+            .err()
+            .unwrap_or_else(|| panic!("Expected an error for '{}'", contents));
+        assert_eq!(error.line, *line, "{}", error);
+        assert!(error
+            .to_string()
+            .starts_with(&format!("bad.obj:{}: ", line)));
+    }
+}
+
+#[test]
+fn load_head_obj() {
+    let mesh = read_obj("head.obj").unwrap();
+    assert_eq!(mesh.positions.len(), 1258);
+    assert_eq!(mesh.tex_coords.len(), 1339);
+    assert_eq!(mesh.normals.len(), 1258);
+    assert_eq!(mesh.triangles.len(), 2492);
+}
+
+#[test]
+fn parse_material_libraries_and_usemtl() {
+    let mesh = parse_obj(
+        "mtllib a.mtl b.mtl
+        v 0 0 0
+        v 1 0 0
+        v 0 1 0
+        f 1 2 3
+        usemtl red
+        f 1 2 3
+        usemtl dark glass
+        f 1 2 3
+        usemtl red
+        f 1 2 3",
+        "test.obj",
+    )
+    .unwrap();
+
+    assert_eq!(mesh.material_libraries, vec!["a.mtl", "b.mtl"]);
+    assert_eq!(mesh.materials, vec!["red", "dark glass"]);
+    let materials = mesh
+        .triangles
+        .iter()
+        .map(|t| t.material)
+        .collect::<Vec<Option<usize>>>();
+    assert_eq!(materials, vec![None, Some(0), Some(1), Some(0)]);
+}
+
+#[test]
+fn load_obj_with_material_library() {
+    use crate::material::color::Color;
+    use crate::scene::scene::Scene;
+    use crate::scene::writer::scene_to_string;
+    use crate::testing::TempDir;
+    use crate::world::camera::CameraSettings;
+    use crate::world::render_settings::RenderSettings;
+
+    let dir = TempDir::new("obj-mtl");
+    fs::write(
+        dir.join("materials.mtl"),
+        "newmtl chrome\nKs 0.9 0.9 0.9\nNs 1000\nillum 3\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("mesh.obj"),
+        "mtllib materials.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\nusemtl chrome\nf 1 2 3\nusemtl undefined\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let objects = load_obj(
+        &dir.join("mesh.obj").to_string_lossy(),
+        &default_material(),
+        false,
+    );
+
+    let scene = Scene {
+        objects: vec![Box::new(objects.unwrap())],
+        camera: CameraSettings {
+            look_from: Vec3::new(0.0, 0.0, 1.0),
+            look_at: Vec3::new(0.0, 0.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            aperture: 0.0,
+            focus_dist: 1.0,
+            vertical_fov: 40.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        settings: RenderSettings::new(1.0, 10, Color::white(), "mesh.png"),
+    };
+    let contents = scene_to_string(&scene).unwrap();
+
+    assert!(contents.contains("material material_0 lambertian color=0.5,0.5,0.5"));
+    assert!(contents.contains("material material_1 metal"));
+    let references = contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("triangle "))
+        .map(|line| line.rsplit('=').next().unwrap())
+        .collect::<Vec<&str>>();
+    assert_eq!(references, vec!["material_0", "material_1", "material_0"]);
+}
+
+#[test]
+fn compute_angle_weighted_smooth_normals() {
+    // Two faces of a unit cube meeting at the x = 1 edge, the second one has its own normals.
+    let mut mesh = parse_obj(
+        "v 0 0 0
+        v 1 0 0
+        v 1 1 0
+        v 1 0 -1
+        v 1 1 -1
+        vn 1 0 0
+        f 1 2 3
+        f 2//1 4//1 5//1
+        f 2 5 3",
+        "test.obj",
+    )
+    .unwrap();
+    mesh.compute_smooth_normals();
+
+    assert_eq!(mesh.normals.len(), 1 + 5);
+    assert_eq!(mesh.triangles[1].vertices[0].normal, Some(0));
+    let normal = |t: usize, k: usize| mesh.normals[mesh.triangles[t].vertices[k].normal.unwrap()];
+    // Vertex 1 is only part of the front face.
+    assert_eq!(normal(0, 0).normalize(), Vec3::new(0.0, 0.0, 1.0));
+    // Vertex 2 has a 90 degree corner in the front face and two 45 degree corners in the side.
+    let n = normal(0, 1).normalize();
+    assert!((n.z() - n.x()).abs() < 1e-9, "{:?}", n);
+    assert!(n.y().abs() < 1e-9);
+}
+
+#[test]
+fn undecodable_texture_maps_are_errors() {
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("obj-bad-map");
+    fs::write(dir.join("bad.png"), "not an image").unwrap();
+    fs::write(
+        dir.join("materials.mtl"),
+        "newmtl wood\nKd 1 1 1\nmap_Kd bad.png\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("mesh.obj"),
+        "mtllib materials.mtl\nv 0 0 0\nv 1 0 0\nv 0 1 0\nusemtl wood\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let error = load_obj(
+        &dir.join("mesh.obj").to_string_lossy(),
+        &default_material(),
+        false,
+    )
+    .err()
+    .unwrap();
+
+    assert!(error.file.ends_with("materials.mtl"), "{}", error);
+    assert_eq!(error.line, 3, "{}", error);
+}
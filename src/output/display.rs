@@ -0,0 +1,235 @@
+use crate::material::color::Color;
+use image::Rgb;
+
+// Compresses the unlimited light of a render into the [0, 1] a screen can show.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub enum ToneMapping {
+    // Everything brighter than 1 is white.
+    Clamp,
+    // Divides by one plus the luminance, bright light approaches white without reaching it.
+    Reinhard,
+    // Reinhard scaled so luminance `white` and above becomes white.
+    ExtendedReinhard { white: f64 },
+    // Krzysztof Narkowicz's fit of the ACES filmic curve.
+    Aces,
+    // John Hable's filmic curve from Uncharted 2.
+    Hable,
+}
+
+impl ToneMapping {
+    // Extended Reinhard needs the `white` point, the others don't take one.
+    pub fn from_name(name: &str, white: Option<f64>) -> Result<ToneMapping, String> {
+        match (name, white) {
+            ("extended_reinhard", Some(white)) if white > 0.0 => {
+                return Ok(ToneMapping::ExtendedReinhard { white })
+            }
+            ("extended_reinhard", Some(white)) => {
+                return Err(format!("the white point must be positive, got {}", white))
+            }
+            ("extended_reinhard", None) => {
+                return Err(String::from("extended_reinhard needs a white point"))
+            }
+            (_, Some(_)) => return Err(format!("{} has no white point", name)),
+            _ => {}
+        }
+        match name {
+            "clamp" => Ok(ToneMapping::Clamp),
+            "reinhard" => Ok(ToneMapping::Reinhard),
+            "aces" => Ok(ToneMapping::Aces),
+            "hable" => Ok(ToneMapping::Hable),
+            _ => Err(format!(
+                "unknown tone mapping '{}', expected clamp, reinhard, extended_reinhard, aces or hable",
+                name
+            )),
+        }
+    }
+
+    pub fn white(&self) -> Option<f64> {
+        match self {
+            ToneMapping::ExtendedReinhard { white } => Some(*white),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMapping::Clamp => "clamp",
+            ToneMapping::Reinhard => "reinhard",
+            ToneMapping::ExtendedReinhard { .. } => "extended_reinhard",
+            ToneMapping::Aces => "aces",
+            ToneMapping::Hable => "hable",
+        }
+    }
+
+    // Linear light in, linear values for the display out. Values above 1, left by `Clamp` and by
+    // the others past their white point, are clamped when quantized.
+    pub fn apply(&self, color: Color) -> Color {
+        let per_channel = |curve: fn(f64) -> f64| {
+            Color::new(curve(color.r()), curve(color.g()), curve(color.b()))
+        };
+        match self {
+            ToneMapping::Clamp => color,
+            // On the luminance, which keeps the hue of bright colors.
+            ToneMapping::Reinhard => color / (1.0 + color.luminance().max(0.0)),
+            ToneMapping::ExtendedReinhard { white } => {
+                let luminance = color.luminance().max(0.0);
+                color * (1.0 + luminance / (white * white)) / (1.0 + luminance)
+            }
+            ToneMapping::Aces => per_channel(|x| {
+                let x = x.max(0.0);
+                (x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)).min(1.0)
+            }),
+            ToneMapping::Hable => per_channel(|x| hable(2.0 * x.max(0.0)) / hable(HABLE_WHITE)),
+        }
+    }
+}
+
+// Linear value the Hable curve maps to white.
+const HABLE_WHITE: f64 = 11.2;
+
+fn hable(x: f64) -> f64 {
+    let (a, b, c, d, e, f) = (0.15, 0.5, 0.1, 0.2, 0.02, 0.3);
+    (x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f) - e / f
+}
+
+// How the linear light of a render becomes 8 bit pixels: scaled by the exposure, tone mapped,
+// encoded with the sRGB transfer function and quantized. Dithering adds a little noise before
+// rounding so smooth gradients don't turn into bands.
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct DisplaySettings {
+    // In stops, every stop doubles the light.
+    pub exposure: f64,
+    pub tone_mapping: ToneMapping,
+    pub dither: bool,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> DisplaySettings {
+        DisplaySettings {
+            exposure: 0.0,
+            tone_mapping: ToneMapping::Clamp,
+            dither: true,
+        }
+    }
+}
+
+impl DisplaySettings {
+    // The dithering noise depends on the pixel's position, so the same render always gives the
+    // same image.
+    pub fn pixel(&self, color: Color, x: u32, y: u32) -> Rgb<u8> {
+        let color = self.tone_mapping.apply(color * 2f64.powf(self.exposure));
+        let mut pixel = [0; 3];
+        for (channel, value) in [color.r(), color.g(), color.b()].iter().enumerate() {
+            let value = value.clamp(0.0, 1.0);
+            let mut level = srgb_encode(value) * 255.0;
+            // Pure black and clipped white stay exact.
+            if self.dither && value > 0.0 && value < 1.0 {
+                let channel = channel as u32;
+                level += hash_noise(x, y, channel) + hash_noise(x, y, channel + 3) - 1.0;
+            }
+            pixel[channel] = level.round().clamp(0.0, 255.0) as u8;
+        }
+        Rgb(pixel)
+    }
+}
+
+// The sRGB transfer function, linear in the darkest tones and a 2.4 power above.
+pub fn srgb_encode(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        12.92 * value
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Uniform in [0, 1), the sum of two is triangular noise spanning one 8 bit level either way.
+fn hash_noise(x: u32, y: u32, channel: u32) -> f64 {
+    let mut h = x.wrapping_mul(0x8da6_b343)
+        ^ y.wrapping_mul(0xd816_3841)
+        ^ channel.wrapping_mul(0xcb1a_b31f);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    h as f64 / 4_294_967_296.0
+}
+
+#[test]
+fn tone_mapping_compresses_bright_light() {
+    let operators = [
+        ToneMapping::Reinhard,
+        ToneMapping::ExtendedReinhard { white: 4.0 },
+        ToneMapping::Aces,
+        ToneMapping::Hable,
+    ];
+    for operator in operators.iter() {
+        // Brighter stays brighter, everything up to the white points stays displayable.
+        let mut previous = -1.0;
+        for i in 0..100 {
+            let x = i as f64 * 0.2;
+            let mapped = operator.apply(Color::new(x, x, x));
+            assert!(mapped.r() > previous || mapped.r() == 1.0, "{:?}", operator);
+            assert!(x > 4.0 || mapped.r() <= 1.0 + 1e-9, "{:?} {}", operator, x);
+            previous = mapped.r();
+        }
+        assert_eq!(operator.apply(Color::black()), Color::black());
+        assert_eq!(
+            ToneMapping::from_name(operator.name(), operator.white()).unwrap(),
+            *operator
+        );
+    }
+    let extended = ToneMapping::ExtendedReinhard { white: 4.0 };
+    assert!((extended.apply(Color::new(4.0, 4.0, 4.0)).r() - 1.0).abs() < 1e-9);
+    assert!((ToneMapping::Hable.apply(Color::new(5.6, 5.6, 5.6)).r() - 1.0).abs() < 1e-9);
+    assert_eq!(
+        ToneMapping::Clamp.apply(Color::new(3.0, 0.5, 0.0)),
+        Color::new(3.0, 0.5, 0.0)
+    );
+    assert!(ToneMapping::from_name("filmic", None).is_err());
+    assert!(ToneMapping::from_name("extended_reinhard", Some(0.0)).is_err());
+    assert!(ToneMapping::from_name("extended_reinhard", None).is_err());
+    assert!(ToneMapping::from_name("aces", Some(2.0)).is_err());
+}
+
+#[test]
+fn pixels_are_srgb_encoded_and_dithered() {
+    let plain = DisplaySettings {
+        dither: false,
+        ..DisplaySettings::default()
+    };
+    assert_eq!(
+        plain.pixel(Color::new(0.0, 0.5, 1.0), 0, 0),
+        Rgb([0, 188, 255])
+    );
+    assert_eq!(
+        plain.pixel(Color::new(-1.0, 7.0, 0.001), 0, 0),
+        Rgb([0, 255, 3])
+    );
+    let brighter = DisplaySettings {
+        exposure: 1.0,
+        ..plain
+    };
+    assert_eq!(
+        brighter.pixel(Color::new(0.25, 0.25, 0.25), 0, 0),
+        plain.pixel(Color::new(0.5, 0.5, 0.5), 0, 0)
+    );
+    assert!((srgb_encode(0.0031308) - 0.04045).abs() < 1e-4);
+
+    // A flat grey between two levels is spread over both, averaging to the true value.
+    let dithered = DisplaySettings::default();
+    let grey = Color::new(0.2, 0.2, 0.2);
+    let level = srgb_encode(0.2) * 255.0;
+    let mut sum = 0.0;
+    for x in 0..100 {
+        for y in 0..100 {
+            let pixel = dithered.pixel(grey, x, y);
+            assert!((pixel.0[0] as f64 - level).abs() < 2.0);
+            sum += pixel.0[0] as f64;
+        }
+    }
+    assert!((sum / 10000.0 - level).abs() < 0.05);
+    assert_eq!(dithered.pixel(grey, 3, 4), dithered.pixel(grey, 3, 4));
+    assert_eq!(dithered.pixel(Color::white(), 3, 4), Rgb([255, 255, 255]));
+    assert_eq!(dithered.pixel(Color::black(), 3, 4), Rgb([0, 0, 0]));
+}
@@ -0,0 +1,118 @@
+use crate::material::color::Color;
+use crate::output::display::DisplaySettings;
+use crate::output::{openexr, pfm};
+use image::{ImageBuffer, Rgb};
+use std::fs;
+use std::io::BufWriter;
+use std::path::Path;
+
+// The linear light of every pixel as the integrator found it, before anything is clamped. Rows
+// go from the top of the image to the bottom.
+pub struct Framebuffer {
+    width: u32,
+    height: u32,
+    pixels: Vec<Color>,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32) -> Framebuffer {
+        Framebuffer {
+            width,
+            height,
+            pixels: vec![Color::black(); width as usize * height as usize],
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        self.pixels[self.index(x, y)]
+    }
+
+    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
+        let index = self.index(x, y);
+        self.pixels[index] = color;
+    }
+
+    // Row by row from the top.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    fn index(&self, x: u32, y: u32) -> usize {
+        y as usize * self.width as usize + x as usize
+    }
+
+    // The format follows the extension: `.hdr` (Radiance RGBE), `.pfm` and `.exr` keep the
+    // linear floats, anything else is an 8 bit image the `image` crate knows, converted with
+    // `display`.
+    pub fn save(&self, path: &str, display: &DisplaySettings) -> Result<(), String> {
+        let extension = Path::new(path)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        let result = match extension.as_deref() {
+            Some("hdr") => self.save_radiance_hdr(path),
+            Some("pfm") => fs::write(path, pfm::encode(self)).map_err(|e| e.to_string()),
+            Some("exr") => fs::write(path, openexr::encode(self)).map_err(|e| e.to_string()),
+            _ => self.to_image(display).save(path).map_err(|e| e.to_string()),
+        };
+        result.map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    // RGBE can't store negative values.
+    fn save_radiance_hdr(&self, path: &str) -> Result<(), String> {
+        let file = fs::File::create(path).map_err(|e| e.to_string())?;
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|c| Rgb([c.r(), c.g(), c.b()].map(|v| v.max(0.0) as f32)))
+            .collect::<Vec<Rgb<f32>>>();
+        image::codecs::hdr::HdrEncoder::new(BufWriter::new(file))
+            .encode(&pixels, self.width as usize, self.height as usize)
+            .map_err(|e| e.to_string())
+    }
+
+    // The 8 bit image shown on screen.
+    pub fn to_image(&self, display: &DisplaySettings) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        ImageBuffer::from_fn(self.width, self.height, |x, y| {
+            display.pixel(self.pixel(x, y), x, y)
+        })
+    }
+}
+
+#[test]
+fn radiance_hdr_files_keep_bright_pixels() {
+    use crate::testing::TempDir;
+    use image::codecs::hdr::HdrDecoder;
+    use std::io::BufReader;
+
+    let mut framebuffer = Framebuffer::new(3, 2);
+    framebuffer.set_pixel(0, 0, Color::new(15.0, 0.5, 0.0));
+    framebuffer.set_pixel(2, 1, Color::new(0.25, 1000.0, 2.0));
+
+    let dir = TempDir::new("framebuffer");
+    let path = dir.join("render.hdr");
+    let path = path.to_str().unwrap();
+    framebuffer.save(path, &DisplaySettings::default()).unwrap();
+    let decoder = HdrDecoder::new(BufReader::new(fs::File::open(path).unwrap())).unwrap();
+    let pixels = decoder.read_image_hdr().unwrap();
+
+    assert_eq!(pixels.len(), 6);
+    for (pixel, color) in pixels.iter().zip(framebuffer.pixels()) {
+        let expected = [color.r(), color.g(), color.b()];
+        for (value, expected) in pixel.0.iter().zip(expected.iter()) {
+            // RGBE keeps 8 bits of mantissa for the largest component.
+            assert!((*value as f64 - expected).abs() <= color.max_component() / 128.0);
+        }
+    }
+    assert!(Framebuffer::new(1, 1)
+        .save("/nonexistent/render.exr", &DisplaySettings::default())
+        .is_err());
+}
@@ -0,0 +1,4 @@
+pub mod display;
+pub mod framebuffer;
+pub mod openexr;
+pub mod pfm;
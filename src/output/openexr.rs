@@ -0,0 +1,110 @@
+use crate::output::framebuffer::Framebuffer;
+
+// Scanline OpenEXR without compression, the R, G and B channels as 32 bit floats.
+//
+// The file is the magic number and version, a header of `name type size value` attributes ended
+// by an empty name, a table with the file offset of every scanline, then the scanlines: the row,
+// the size of its data and each channel's values for the row, channels in alphabetical order.
+pub fn encode(framebuffer: &Framebuffer) -> Vec<u8> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    let mut bytes = vec![0x76, 0x2f, 0x31, 0x01];
+    // Version 2, single part scanline file.
+    bytes.extend_from_slice(&2u32.to_le_bytes());
+
+    let mut channels = vec![];
+    for name in ["B", "G", "R"] {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        // Float pixels, not perceptually linear, reserved bytes, no subsampling.
+        channels.extend_from_slice(&2u32.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&1u32.to_le_bytes());
+        channels.extend_from_slice(&1u32.to_le_bytes());
+    }
+    channels.push(0);
+    let window = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect::<Vec<u8>>();
+
+    attribute(&mut bytes, "channels", "chlist", &channels);
+    attribute(&mut bytes, "compression", "compression", &[0]);
+    attribute(&mut bytes, "dataWindow", "box2i", &window);
+    attribute(&mut bytes, "displayWindow", "box2i", &window);
+    // Increasing y.
+    attribute(&mut bytes, "lineOrder", "lineOrder", &[0]);
+    attribute(
+        &mut bytes,
+        "pixelAspectRatio",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    attribute(&mut bytes, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(
+        &mut bytes,
+        "screenWindowWidth",
+        "float",
+        &1.0f32.to_le_bytes(),
+    );
+    bytes.push(0);
+
+    let line_size = 3 * 4 * width as usize;
+    let first_line = bytes.len() + 8 * height as usize;
+    for y in 0..height as usize {
+        let offset = first_line + y * (8 + line_size);
+        bytes.extend_from_slice(&(offset as u64).to_le_bytes());
+    }
+    for y in 0..height {
+        bytes.extend_from_slice(&(y as i32).to_le_bytes());
+        bytes.extend_from_slice(&(line_size as u32).to_le_bytes());
+        for channel in [2, 1, 0] {
+            for x in 0..width {
+                let value = framebuffer.pixel(x, y).as_vector()[channel] as f32;
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    bytes
+}
+
+fn attribute(bytes: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    for text in [name, kind] {
+        bytes.extend_from_slice(text.as_bytes());
+        bytes.push(0);
+    }
+    bytes.extend_from_slice(&(value.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+#[test]
+fn scanlines_are_found_through_the_offset_table() {
+    use crate::material::color::Color;
+    use std::convert::TryInto;
+
+    let mut framebuffer = Framebuffer::new(3, 2);
+    framebuffer.set_pixel(1, 1, Color::new(42.5, 3.0, -0.5));
+    let bytes = encode(&framebuffer);
+    assert_eq!(&bytes[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+
+    let u32_at =
+        |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+    let f32_at = |i: usize| f32::from_bits(u32_at(i));
+    // Skip the attributes to the offset table.
+    let mut i = 8;
+    while bytes[i] != 0 {
+        let name_end = i + bytes[i..].iter().position(|b| *b == 0).unwrap();
+        let kind_end = name_end + 1 + bytes[name_end + 1..].iter().position(|b| *b == 0).unwrap();
+        i = kind_end + 5 + u32_at(kind_end + 1) as usize;
+    }
+    let table = i + 1;
+    let offset = u64::from_le_bytes(bytes[table + 8..table + 16].try_into().unwrap()) as usize;
+
+    // Row 1, 3 pixels of 3 floats, then B, G and R of the middle pixel.
+    assert_eq!(u32_at(offset), 1);
+    assert_eq!(u32_at(offset + 4), 36);
+    let data = offset + 8;
+    assert_eq!(f32_at(data + 4), -0.5);
+    assert_eq!(f32_at(data + 12 + 4), 3.0);
+    assert_eq!(f32_at(data + 24 + 4), 42.5);
+    assert_eq!(bytes.len(), data + 36);
+}
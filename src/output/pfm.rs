@@ -0,0 +1,38 @@
+use crate::output::framebuffer::Framebuffer;
+
+// Portable float map: a text header `PF`, the size and a scale whose sign gives the byte order,
+// then 32 bit floats for r, g and b of each pixel. Rows go from the bottom up.
+pub fn encode(framebuffer: &Framebuffer) -> Vec<u8> {
+    let (width, height) = (framebuffer.width(), framebuffer.height());
+    // A negative scale means little endian.
+    let mut bytes = format!("PF\n{} {}\n-1.0\n", width, height).into_bytes();
+    for y in (0..height).rev() {
+        for x in 0..width {
+            let color = framebuffer.pixel(x, y);
+            for value in [color.r(), color.g(), color.b()] {
+                bytes.extend_from_slice(&(value as f32).to_le_bytes());
+            }
+        }
+    }
+    bytes
+}
+
+#[test]
+fn float_maps_start_at_the_bottom_row() {
+    use crate::material::color::Color;
+
+    let mut framebuffer = Framebuffer::new(2, 2);
+    framebuffer.set_pixel(1, 1, Color::new(42.5, -1.0, 0.125));
+    let bytes = encode(&framebuffer);
+
+    let header = b"PF\n2 2\n-1.0\n";
+    assert_eq!(&bytes[..header.len()], header);
+    let floats = bytes[header.len()..]
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect::<Vec<f32>>();
+    assert_eq!(floats.len(), 12);
+    // Bottom right is the second pixel written.
+    assert_eq!(&floats[3..6], &[42.5, -1.0, 0.125]);
+    assert!(floats[6..].iter().all(|v| *v == 0.0));
+}
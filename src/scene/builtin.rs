@@ -0,0 +1,595 @@
+use std::sync::Arc;
+
+use rand::Rng;
+
+use crate::geometry::mat4::Mat4;
+use crate::geometry::quaternion::Quaternion;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::animated::{Animated, Keyframe};
+use crate::hittable::constant_medium::ConstantMedium;
+use crate::hittable::cube::Cube;
+use crate::hittable::heterogeneous_medium::{Emission, HeterogeneousMedium};
+use crate::hittable::hittable::Hittable;
+use crate::hittable::instance::Instance;
+use crate::hittable::moving_sphere::MovingSphere;
+use crate::hittable::quad::Quad;
+use crate::hittable::sphere::Sphere;
+use crate::material::color::Color;
+use crate::material::dielectric::Dielectric;
+use crate::material::image_texture::ImageTexture;
+use crate::material::isotropic::Isotropic;
+use crate::material::lambertian::Lambertian;
+use crate::material::material::Material;
+use crate::material::metal::Metal;
+use crate::mesh::obj::{load_obj, ObjError};
+use crate::scene::scene::Scene;
+use crate::volume::noise::NoiseField;
+use crate::world::camera::CameraSettings;
+use crate::world::render_settings::RenderSettings;
+
+// Name and description of every scene that can be passed to `builtin_scene`.
+pub const BUILTIN_SCENES: [(&str, &str); 8] = [
+    (
+        "cubes-and-spheres",
+        "Random field of metal, lambertian and glass spheres and cubes",
+    ),
+    ("cornell-box", "Cornell box with two white cuboids"),
+    ("cornell-smoke", "Cornell box with cuboids of smoke and fog"),
+    ("die-box", "Two uv-mapped dice on a metal backdrop"),
+    (
+        "object-loader",
+        "An OBJ mesh (--obj, default head.obj) on a sphere",
+    ),
+    (
+        "instances",
+        "A crowd of instances of one OBJ mesh (--obj) sharing a single BVH",
+    ),
+    (
+        "motion-blur",
+        "Bouncing spheres and a spinning cube blurred over the shutter interval",
+    ),
+    ("volumes", "A noise cloud above a glowing fireball"),
+];
+
+pub fn builtin_scene(name: &str, obj_file: &str) -> Result<Scene, String> {
+    match name {
+        "cubes-and-spheres" => cubes_and_spheres_scene(),
+        "cornell-box" => Ok(cornell_box_scene()),
+        "cornell-smoke" => Ok(cornell_smoke_scene()),
+        "die-box" => die_box_scene(),
+        "object-loader" => object_loader_scene(obj_file).map_err(|e| e.to_string()),
+        "instances" => instances_scene(obj_file).map_err(|e| e.to_string()),
+        "motion-blur" => Ok(motion_blur_scene()),
+        "volumes" => Ok(volumes_scene()),
+        _ => Err(format!(
+            "Unknown scene '{}', use --list-scenes to see the available scenes",
+            name
+        )),
+    }
+}
+
+pub fn cubes_and_spheres_scene() -> Result<Scene, String> {
+    // Camera & Viewport
+    let settings = RenderSettings::new(3.0 / 2.0, 300, Color::white(), "cubes_spheres_render.png");
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(13.0, 2.0, 3.0),
+        look_at: Vec3::new(0.0, 0.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.1,
+        focus_dist: 10.0,
+        vertical_fov: 50.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    let grey: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_color(Color::new(0.5, 0.5, 0.5)),
+    ));
+    let white: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Metal::new(Color::white(), 0.1)));
+    let earth_texture: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_texture(Box::new(ImageTexture::new("earthmap.jpg")?)),
+    ));
+    let mars_texture: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_texture(Box::new(ImageTexture::new("mars.jpg")?)),
+    ));
+
+    let ground = Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Arc::clone(&grey),
+    };
+
+    let earth = Sphere {
+        center: Vec3::new(0.0, 1.0, 0.0),
+        radius: 1.0,
+        material: Arc::clone(&earth_texture),
+    };
+
+    let mars = Sphere {
+        center: Vec3::new(4.0, 1.0, -3.5),
+        radius: 1.0,
+        material: Arc::clone(&mars_texture),
+    };
+
+    let cube1 = Cube::new(
+        Vec3::new(-8.0, 4.0, 0.0),
+        3.0,
+        Vec3::new(1.0, 1.0, 0.5),
+        Arc::clone(&white),
+    );
+
+    let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = vec![
+        Box::new(ground),
+        Box::new(cube1),
+        Box::new(earth),
+        Box::new(mars),
+    ];
+
+    let mut rng = rand::thread_rng();
+    for a in -12..12 {
+        for b in -12..12 {
+            let center = Vec3::new(
+                a as f64 + 0.9 * rng.gen::<f64>(),
+                0.5,
+                b as f64 + 0.9 * rng.gen::<f64>(),
+            );
+            if (center - Vec3::new(4.0, 0.2, 0.0)).len() < 3.5 {
+                continue;
+            }
+
+            let choose_mat = rng.gen::<f64>();
+            let material: Arc<Box<dyn Material + Send + Sync>> = if choose_mat < 0.8 {
+                Arc::new(Box::new(Metal::new(Color::random(), 0.1)))
+            } else if choose_mat < 0.95 {
+                Arc::new(Box::new(Lambertian::new_from_color(Color::random())))
+            } else {
+                Arc::new(Box::new(Dielectric::new(1.5)))
+            };
+
+            let choose_cube = rng.gen::<f64>();
+            let object: Box<dyn Hittable + Send + Sync> = if choose_cube < 0.1 {
+                Box::new(Cube::new(
+                    center,
+                    0.3,
+                    Vec3::new(1.0, 0.5, 0.0),
+                    Arc::clone(&material),
+                ))
+            } else {
+                Box::new(Sphere {
+                    center,
+                    radius: 0.2,
+                    material: Arc::clone(&material),
+                })
+            };
+
+            objects.push(object);
+        }
+    }
+
+    Ok(Scene {
+        objects,
+        camera,
+        settings,
+    })
+}
+
+pub fn cornell_box_scene() -> Scene {
+    let white: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Lambertian::new_from_color(Color::white())));
+    let (cube1, cube2) = cornell_box_cuboids(&white);
+    cornell_box(vec![Box::new(cube1), Box::new(cube2)], "cornell_scene.png")
+}
+
+// The two cuboids standing in the box, both made of `material`.
+fn cornell_box_cuboids(material: &Arc<Box<dyn Material + Send + Sync>>) -> (Cube, Cube) {
+    let cube1 = Cube::new_cuboid(
+        Vec3::new(138.0, 75.0, 130.0),
+        Vec3::new(200.0, 75.0, 300.0),
+        100.0,
+        150.0,
+        100.0,
+        Arc::clone(material),
+    );
+
+    let cube2 = Cube::new_cuboid(
+        Vec3::new(400.0, 150.0, 330.0),
+        Vec3::new(100.0, 150.0, 300.0),
+        100.0,
+        300.0,
+        100.0,
+        Arc::clone(material),
+    );
+    (cube1, cube2)
+}
+
+// The walls and light of the Cornell box around `contents`.
+fn cornell_box(contents: Vec<Box<dyn Hittable + Sync + Send>>, output: &str) -> Scene {
+    let right_wall = Quad::new_lambertian(
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 555.0, 555.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Color::new(0.65, 0.05, 0.05),
+    );
+
+    let left_wall = Quad::new_lambertian(
+        Vec3::new(555.0, 555.0, 0.0),
+        Vec3::new(555.0, 555.0, 555.0),
+        Vec3::new(555.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Color::new(0.12, 0.45, 0.15),
+    );
+
+    let back_wall = Quad::new_lambertian(
+        Vec3::new(0.0, 555.0, 555.0),
+        Vec3::new(555.0, 555.0, 555.0),
+        Vec3::new(555.0, 0.0, 555.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        Color::white(),
+    );
+
+    let light = Quad::new_diffuse_light(
+        Vec3::new(113.0, 554.0, 127.0),
+        Vec3::new(113.0, 554.0, 432.0),
+        Vec3::new(443.0, 554.0, 432.0),
+        Vec3::new(443.0, 554.0, 127.0),
+        Color::new(1.0, 1.0, 1.0),
+    );
+
+    let top_wall = Quad::new_lambertian(
+        Vec3::new(0.0, 555.0, 0.0),
+        Vec3::new(0.0, 555.0, 555.0),
+        Vec3::new(555.0, 555.0, 555.0),
+        Vec3::new(555.0, 555.0, 0.0),
+        Color::white(),
+    );
+
+    let bottom_wall = Quad::new_lambertian(
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 555.0),
+        Vec3::new(555.0, 0.0, 0.0),
+        Color::white(),
+    );
+
+    let mut objects: Vec<Box<dyn Hittable + Sync + Send>> = vec![
+        Box::new(right_wall),
+        Box::new(left_wall),
+        Box::new(back_wall),
+        Box::new(top_wall),
+        Box::new(bottom_wall),
+        Box::new(light),
+    ];
+    objects.extend(contents);
+
+    // Camera & Viewport
+    let mut settings = RenderSettings::new(1.0, 600, Color::black(), output);
+    settings.samples_per_pixel = 200;
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(278.0, 278.0, -800.0),
+        look_at: Vec3::new(278.0, 278.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.1,
+        focus_dist: 800.0,
+        vertical_fov: 40.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    Scene {
+        objects,
+        camera,
+        settings,
+    }
+}
+
+// The Cornell box with its cuboids filled with dark smoke and white fog.
+pub fn cornell_smoke_scene() -> Scene {
+    let smoke: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Isotropic::new_from_color(Color::black())));
+    let fog: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Isotropic::new_from_color(Color::white())));
+    let (cube1, cube2) = cornell_box_cuboids(&fog);
+    cornell_box(
+        vec![
+            Box::new(ConstantMedium::new(Box::new(cube1), 0.01, fog)),
+            Box::new(ConstantMedium::new(Box::new(cube2), 0.01, smoke)),
+        ],
+        "cornell_smoke.png",
+    )
+}
+
+pub fn die_box_scene() -> Result<Scene, String> {
+    let back_wall_material: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Metal::new(Color::new(0.7, 0.7, 0.7), 0.01)));
+
+    let back_wall = Quad::new(
+        Vec3::new(-5050.0, 10555.0, 6055.0),
+        Vec3::new(55055.0, 10555.0, 6055.0),
+        Vec3::new(55055.0, -1000.0, 0.0),
+        Vec3::new(-5050.0, -1000.0, 0.0),
+        &back_wall_material,
+    );
+
+    let bottom_wall = Quad::new_lambertian(
+        Vec3::new(-5000.0, 0.0, 0.0),
+        Vec3::new(-5000.0, 0.0, 555.0),
+        Vec3::new(5555.0, 0.0, 555.0),
+        Vec3::new(5555.0, 0.0, 0.0),
+        Color::new(0.65, 0.12, 0.12),
+    );
+
+    let cube1 = Cube::new_mapped_cube(
+        "uv-map.png",
+        Vec3::new(138.0, 225.0, 230.0),
+        Vec3::new(200.0, 105.0, 300.0),
+        200.0,
+        250.0,
+        250.0,
+    )?;
+
+    let cube2 = Cube::new_mapped_cube(
+        "uv-map-2.png",
+        Vec3::new(500.0, 350.0, 330.0),
+        Vec3::new(200.0, 500.0, 200.0),
+        150.0,
+        270.0,
+        200.0,
+    )?;
+
+    let objects: Vec<Box<dyn Hittable + Sync + Send>> = vec![
+        Box::new(bottom_wall),
+        Box::new(back_wall),
+        Box::new(cube1),
+        Box::new(cube2),
+    ];
+
+    // Camera & Viewport
+    let mut settings = RenderSettings::new(1.0, 600, Color::white(), "die_scene.png");
+    settings.samples_per_pixel = 200;
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(278.0, 278.0, -800.0),
+        look_at: Vec3::new(278.0, 278.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.1,
+        focus_dist: 800.0,
+        vertical_fov: 40.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    Ok(Scene {
+        objects,
+        camera,
+        settings,
+    })
+}
+
+pub fn object_loader_scene(obj_file: &str) -> Result<Scene, ObjError> {
+    // Camera & Viewport
+    let settings = RenderSettings::new(3.0 / 2.0, 300, Color::white(), "face.png");
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(0.0, 0.0, 5.0),
+        look_at: Vec3::new(0.0, 0.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.1,
+        focus_dist: 5.0,
+        vertical_fov: 50.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    let grey: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_color(Color::new(0.5, 0.5, 0.5)),
+    ));
+
+    let ground = Sphere {
+        center: Vec3::new(0.0, -7.0, 0.0),
+        radius: 6.0,
+        material: Arc::clone(&grey),
+    };
+
+    let objects: Vec<Box<dyn Hittable + Send + Sync>> =
+        vec![Box::new(load_obj(obj_file, &grey, true)?), Box::new(ground)];
+
+    Ok(Scene {
+        objects,
+        camera,
+        settings,
+    })
+}
+
+pub fn instances_scene(obj_file: &str) -> Result<Scene, ObjError> {
+    // Camera & Viewport
+    let settings = RenderSettings::new(3.0 / 2.0, 300, Color::white(), "instances.png");
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(0.0, 6.0, 14.0),
+        look_at: Vec3::new(0.0, 0.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.0,
+        focus_dist: 15.0,
+        vertical_fov: 50.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    let grey: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_color(Color::new(0.5, 0.5, 0.5)),
+    ));
+    let gold: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.1)));
+
+    // The mesh and its BVH are built once, every instance only adds a transform.
+    let mesh: Arc<Box<dyn Hittable + Send + Sync>> =
+        Arc::new(Box::new(load_obj(obj_file, &grey, true)?));
+    // Fit the mesh into a unit box whatever its original size.
+    let bounding_box = mesh.get_bounding_box();
+    let extent = bounding_box.max_point - bounding_box.min_point;
+    let size = extent.x().max(extent.y()).max(extent.z());
+    let center = 0.5 * (bounding_box.min_point + bounding_box.max_point);
+    let normalize =
+        Mat4::scaling(Vec3::new(1.0 / size, 1.0 / size, 1.0 / size)) * Mat4::translation(-center);
+
+    let mut rng = rand::thread_rng();
+    let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = vec![];
+    for i in -5..=5 {
+        for j in -5..=5 {
+            let transform = Mat4::translation(Vec3::new(i as f64 * 1.5, 0.0, j as f64 * 1.5))
+                * Mat4::rotation_y(rng.gen_range(0.0..std::f64::consts::TAU))
+                * Mat4::scaling(Vec3::new(1.2, 1.2, 1.2))
+                * normalize;
+            let material = if (i + j) % 3 == 0 {
+                Some(Arc::clone(&gold))
+            } else {
+                None
+            };
+            objects.push(Box::new(
+                Instance::new(Arc::clone(&mesh), transform, material).unwrap(),
+            ));
+        }
+    }
+    objects.push(Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.6, 0.0),
+        radius: 1000.0,
+        material: Arc::new(Box::new(Lambertian::new_from_color(Color::new(
+            0.4, 0.5, 0.4,
+        )))),
+    }));
+
+    Ok(Scene {
+        objects,
+        camera,
+        settings,
+    })
+}
+
+pub fn motion_blur_scene() -> Scene {
+    // Camera & Viewport
+    let settings = RenderSettings::new(3.0 / 2.0, 300, Color::white(), "motion-blur.png");
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(0.0, 2.0, 10.0),
+        look_at: Vec3::new(0.0, 0.5, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.0,
+        focus_dist: 10.0,
+        vertical_fov: 35.0,
+        shutter_open: 0.0,
+        shutter_close: 1.0,
+    };
+
+    let ground: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_color(Color::new(0.5, 0.5, 0.5)),
+    ));
+    let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = vec![Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: Arc::clone(&ground),
+    })];
+
+    // Spheres falling onto the ground during the exposure, faster to the right.
+    for i in 0..5 {
+        let x = i as f64 * 1.5 - 3.0;
+        let center = Vec3::new(x, 0.5, 0.0);
+        objects.push(Box::new(MovingSphere {
+            center0: center + Vec3::new(0.0, 0.25 * i as f64, 0.0),
+            center1: center,
+            time0: 0.0,
+            time1: 1.0,
+            radius: 0.5,
+            material: Arc::new(Box::new(Lambertian::new_from_color(Color::new(
+                0.2 * i as f64,
+                0.3,
+                1.0 - 0.2 * i as f64,
+            )))),
+        }));
+    }
+
+    // A cube spinning a quarter turn while sliding back.
+    let cube = Cube::new(
+        Vec3::origin(),
+        0.5,
+        Vec3::new(0.0, 0.0, -1.0),
+        Arc::new(Box::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.05))),
+    );
+    let keyframe = |time: f64, z: f64, angle: f64| Keyframe {
+        time,
+        translation: Vec3::new(0.0, 2.2, z),
+        rotation: Quaternion::from_axis_angle(Vec3::new(0.0, 1.0, 0.0), angle),
+        scale: Vec3::new(1.0, 1.0, 1.0),
+    };
+    objects.push(Box::new(
+        Animated::new(
+            Box::new(cube),
+            vec![keyframe(0.0, -2.0, 0.0), keyframe(1.0, -3.0, 1.5)],
+        )
+        .unwrap(),
+    ));
+
+    Scene {
+        objects,
+        camera,
+        settings,
+    }
+}
+
+// A noise cloud floating above a glowing fireball.
+pub fn volumes_scene() -> Scene {
+    let settings = RenderSettings::new(3.0 / 2.0, 300, Color::new(0.7, 0.8, 1.0), "volumes.png");
+
+    let camera = CameraSettings {
+        look_from: Vec3::new(0.0, 2.5, 12.0),
+        look_at: Vec3::new(0.0, 2.0, 0.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.0,
+        focus_dist: 12.0,
+        vertical_fov: 35.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    };
+
+    let ground: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(
+        Lambertian::new_from_color(Color::new(0.5, 0.5, 0.5)),
+    ));
+    let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = vec![Box::new(Sphere {
+        center: Vec3::new(0.0, -1000.0, 0.0),
+        radius: 1000.0,
+        material: ground,
+    })];
+
+    let cloud = HeterogeneousMedium::new(
+        Arc::new(NoiseField::new(1, 3.0, 5)),
+        4.0,
+        Color::new(0.9, 0.9, 0.9),
+        None,
+        Mat4::translation(Vec3::new(-1.5, 3.5, 0.0)) * Mat4::scaling(Vec3::new(6.0, 3.0, 4.0)),
+    )
+    .unwrap();
+    objects.push(Box::new(cloud));
+
+    // Glows brighter where it is denser, and absorbs the light around it.
+    let fireball = HeterogeneousMedium::new(
+        Arc::new(NoiseField::new(5, 2.0, 4)),
+        6.0,
+        Color::black(),
+        Some(Emission {
+            color: Color::new(1.0, 0.45, 0.1),
+            field: None,
+        }),
+        Mat4::translation(Vec3::new(2.0, 1.0, 1.0)) * Mat4::scaling(Vec3::new(2.0, 2.0, 2.0)),
+    )
+    .unwrap();
+    objects.push(Box::new(fireball));
+
+    Scene {
+        objects,
+        camera,
+        settings,
+    }
+}
@@ -0,0 +1,5 @@
+pub mod builtin;
+pub mod parser;
+#[allow(clippy::module_inception)]
+pub mod scene;
+pub mod writer;
@@ -0,0 +1,1448 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::geometry::mat4::Mat4;
+use crate::geometry::quaternion::Quaternion;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::animated::{Animated, Keyframe};
+use crate::hittable::constant_medium::ConstantMedium;
+use crate::hittable::cube::Cube;
+use crate::hittable::heterogeneous_medium::{Emission, HeterogeneousMedium};
+use crate::hittable::hittable::Hittable;
+use crate::hittable::instance::Instance;
+use crate::hittable::moving_sphere::MovingSphere;
+use crate::hittable::quad::Quad;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::transformed::Transformed;
+use crate::hittable::triangle::Triangle;
+use crate::hittable::triangle_mesh::TriangleMesh;
+use crate::material::checkered_texture::CheckeredTexture;
+use crate::material::color::Color;
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::image_texture::ImageTexture;
+use crate::material::isotropic::Isotropic;
+use crate::material::lambertian::Lambertian;
+use crate::material::material::Material;
+use crate::material::metal::Metal;
+use crate::material::solid_color_texture::SolidColorTexture;
+use crate::material::texture::Texture;
+use crate::mesh::obj::{default_material, load_obj};
+use crate::output::display::ToneMapping;
+use crate::scene::scene::Scene;
+use crate::volume::density_field::DensityField;
+use crate::volume::noise::NoiseField;
+use crate::volume::voxel_grid::VoxelGrid;
+use crate::world::camera::CameraSettings;
+use crate::world::render_settings::RenderSettings;
+
+// A scene file has one statement per line, `#` starts a comment:
+//
+//   camera look_from=278,278,-800 look_at=278,278,0 vfov=40 aperture=0.1 shutter=0,1
+//   render width=600 height=600 samples=200 depth=100 roulette=5 threads=16 leaf_size=4 output=cornell.png
+//          diffuse_depth=4 glossy_depth=8 transmission_depth=12
+//   background color=0,0,0
+//   display exposure=0.5 tone_map=clamp|reinhard|extended_reinhard|aces|hable white=4 dither=true
+//   texture <name> solid|checkered|image key=value...
+//   material <name> lambertian|metal|dielectric|diffuse_light|isotropic key=value...
+//   sphere|triangle|quad|cube|cuboid|mapped_cube|mesh key=value...
+//   object <name> mesh key=value...
+//   instance object=<name> translate=x,y,z rotate=x,y,z scale=x,y,z material=<name>
+//   volume grid=<file>|noise=<seed> density=<scale> color=r,g,b emission=r,g,b key=value...
+//
+// `object` builds a mesh once without placing it, every `instance` places it again with its own
+// transform and optionally replaces its materials.
+//
+// Shapes and instances take `translate=x,y,z rotate=x,y,z scale=x,y,z` (rotations in degrees,
+// applied around x, then y, then z, after scaling) or a full `matrix=` of 16 numbers, row by
+// row. The `size` of a `cube` is the length of its sides.
+//
+// Motion blur needs a `shutter` interval on the camera. A sphere with `center1` moves from
+// `center` at `time0` (default 0) to `center1` at `time1` (default 1), any shape with
+// `translate1`, `rotate1` or `scale1` moves between the two transforms over the same times.
+//
+// A shape with `density=` is the boundary of fog or smoke scattering with the `phase=` material,
+// usually an `isotropic` one, instead of a surface.
+//
+// A `volume` is a medium whose density varies, read from a voxel grid file or made of noise
+// (`frequency=`, `octaves=`) and multiplied by `density`. It fills the cube from -0.5 to 0.5 on
+// each axis, placed with the transform keys. With `emission` it glows with that color times the
+// density, or times the values of a separate `emission_grid`.
+//
+// `display` only changes how 8 bit outputs are made from the linear light of the render:
+// `exposure` in stops, a tone mapping (`white` is the luminance extended Reinhard maps to white)
+// and whether to dither. `.hdr`, `.pfm` and `.exr` outputs are written as rendered.
+//
+// Vectors and colors are written as `x,y,z`, values containing spaces can be quoted and relative
+// paths are resolved against the directory of the scene file.
+
+#[derive(Debug)]
+pub struct SceneError {
+    pub file: String,
+    // 1-based, 0 when the error is not tied to a line.
+    pub line: usize,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.file)?;
+        if self.line > 0 {
+            write!(f, ":{}", self.line)?;
+        }
+        match &self.key {
+            Some(key) => write!(f, ": '{}': {}", key, self.message),
+            None => write!(f, ": {}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+pub fn load_scene(path: &str) -> Result<Scene, SceneError> {
+    let contents = fs::read_to_string(path).map_err(|e| SceneError {
+        file: String::from(path),
+        line: 0,
+        key: None,
+        message: format!("could not read scene file: {}", e),
+    })?;
+    let base_dir = Path::new(path).parent().unwrap_or_else(|| Path::new(""));
+    parse_scene(&contents, path, base_dir)
+}
+
+// `file` is only used for error messages, relative paths in the scene are resolved against
+// `base_dir`.
+pub fn parse_scene(contents: &str, file: &str, base_dir: &Path) -> Result<Scene, SceneError> {
+    let mut parser = Parser {
+        base_dir,
+        textures: HashMap::new(),
+        materials: HashMap::new(),
+        prototypes: HashMap::new(),
+        objects: vec![],
+        camera: None,
+        settings: RenderSettings::new(3.0 / 2.0, 300, Color::white(), "render.png"),
+    };
+
+    for (i, line) in contents.lines().enumerate() {
+        let tokens = tokenize(line).map_err(|message| SceneError {
+            file: String::from(file),
+            line: i + 1,
+            key: None,
+            message,
+        })?;
+        if tokens.is_empty() {
+            continue;
+        }
+        let mut statement = Statement::new(file, i + 1, tokens)?;
+        parser.parse_statement(&mut statement)?;
+        statement.finish()?;
+    }
+
+    let camera = parser.camera.ok_or_else(|| SceneError {
+        file: String::from(file),
+        line: 0,
+        key: Some(String::from("camera")),
+        message: String::from("scene has no camera statement"),
+    })?;
+
+    Ok(Scene {
+        objects: parser.objects,
+        camera,
+        settings: parser.settings,
+    })
+}
+
+enum TextureDefinition {
+    Solid(Color),
+    Checkered(Color, Color, f64),
+    // Decoded once when the texture is defined, every material using it gets a copy.
+    Image(ImageTexture),
+}
+
+impl TextureDefinition {
+    fn build(&self) -> Box<dyn Texture + Send + Sync> {
+        match self {
+            TextureDefinition::Solid(color) => Box::new(SolidColorTexture::new(*color)),
+            TextureDefinition::Checkered(even, odd, size) => {
+                Box::new(CheckeredTexture::new(*even, *odd, *size))
+            }
+            TextureDefinition::Image(texture) => Box::new(texture.clone()),
+        }
+    }
+}
+
+struct Parser<'a> {
+    base_dir: &'a Path,
+    textures: HashMap<String, TextureDefinition>,
+    materials: HashMap<String, Arc<Box<dyn Material + Send + Sync>>>,
+    // Shared objects that are only placed through instances.
+    prototypes: HashMap<String, Arc<Box<dyn Hittable + Send + Sync>>>,
+    objects: Vec<Box<dyn Hittable + Send + Sync>>,
+    camera: Option<CameraSettings>,
+    settings: RenderSettings,
+}
+
+impl<'a> Parser<'a> {
+    fn parse_statement(&mut self, s: &mut Statement) -> Result<(), SceneError> {
+        let keyword = s.keyword.clone();
+        match keyword.as_str() {
+            "camera" => {
+                s.positional(0)?;
+                let look_from = s.required("look_from", parse_vec3)?;
+                let look_at = s.required("look_at", parse_vec3)?;
+                let (shutter_open, shutter_close) =
+                    s.optional("shutter", parse_interval)?.unwrap_or((0.0, 0.0));
+                let camera = CameraSettings {
+                    look_from,
+                    look_at,
+                    vup: s
+                        .optional("vup", parse_vec3)?
+                        .unwrap_or(Vec3::new(0.0, 1.0, 0.0)),
+                    aperture: s.optional("aperture", parse_non_negative)?.unwrap_or(0.0),
+                    focus_dist: s
+                        .optional("focus_dist", parse_positive_number)?
+                        .unwrap_or_else(|| (look_from - look_at).len()),
+                    vertical_fov: s.optional("vfov", parse_field_of_view)?.unwrap_or(50.0),
+                    shutter_open,
+                    shutter_close,
+                };
+                if self.camera.is_some() {
+                    return Err(s.error(None, "camera is defined more than once"));
+                }
+                self.camera = Some(camera);
+            }
+            "render" => {
+                s.positional(0)?;
+                let width = s.optional("width", parse_positive)?;
+                let height = s.optional("height", parse_positive)?;
+                let aspect_ratio = s.optional("aspect_ratio", parse_positive_number)?;
+                self.settings
+                    .resize(width, height, aspect_ratio)
+                    .map_err(|e| s.error(Some("aspect_ratio"), &e))?;
+                if let Some(samples_per_pixel) = s.optional("samples", parse_positive)? {
+                    self.settings.samples_per_pixel = samples_per_pixel;
+                }
+                if let Some(recursive_depth) = s.optional("depth", parse_positive)? {
+                    self.settings.recursive_depth = recursive_depth;
+                }
+                if let Some(depth) = s.optional("diffuse_depth", parse_number)? {
+                    self.settings.diffuse_depth = Some(depth);
+                }
+                if let Some(depth) = s.optional("glossy_depth", parse_number)? {
+                    self.settings.glossy_depth = Some(depth);
+                }
+                if let Some(depth) = s.optional("transmission_depth", parse_number)? {
+                    self.settings.transmission_depth = Some(depth);
+                }
+                if let Some(min_bounces) = s.optional("roulette", parse_number)? {
+                    self.settings.russian_roulette = Some(min_bounces);
+                }
+                if let Some(num_threads) = s.optional("threads", parse_positive)? {
+                    self.settings.num_threads = num_threads;
+                }
+                if let Some(bvh_leaf_size) = s.optional("leaf_size", parse_positive)? {
+                    self.settings.bvh_leaf_size = bvh_leaf_size;
+                }
+                if let Some(output) = s.optional("output", parse_string)? {
+                    self.settings.output = output;
+                }
+            }
+            "display" => {
+                s.positional(0)?;
+                let display = &mut self.settings.display;
+                if let Some(exposure) = s.optional("exposure", parse_finite)? {
+                    display.exposure = exposure;
+                }
+                let white = s.optional("white", parse_finite)?;
+                match s.optional("tone_map", parse_string)? {
+                    Some(name) => {
+                        display.tone_mapping = ToneMapping::from_name(&name, white)
+                            .map_err(|e| s.error(Some("tone_map"), &e))?
+                    }
+                    None if white.is_some() => {
+                        return Err(s.error(Some("white"), "white needs tone_map=extended_reinhard"))
+                    }
+                    None => {}
+                }
+                if let Some(dither) = s.optional("dither", parse_bool)? {
+                    display.dither = dither;
+                }
+            }
+            "background" => {
+                s.positional(0)?;
+                self.settings.background = s.required("color", parse_radiance)?;
+            }
+            "texture" => {
+                let (name, kind) = s.name_and_kind()?;
+                let texture = match kind.as_str() {
+                    "solid" => TextureDefinition::Solid(s.required("color", parse_color)?),
+                    "checkered" => TextureDefinition::Checkered(
+                        s.required("even", parse_color)?,
+                        s.required("odd", parse_color)?,
+                        s.required("size", parse_positive_number)?,
+                    ),
+                    "image" => {
+                        let path = self.existing_path(s, "path")?;
+                        TextureDefinition::Image(
+                            ImageTexture::new(&path).map_err(|e| s.error(Some("path"), &e))?,
+                        )
+                    }
+                    _ => return Err(s.error(None, &format!("unknown texture type '{}'", kind))),
+                };
+                if self.textures.insert(name.clone(), texture).is_some() {
+                    return Err(s.error(None, &format!("texture '{}' is defined twice", name)));
+                }
+            }
+            "material" => {
+                let (name, kind) = s.name_and_kind()?;
+                let material: Box<dyn Material + Send + Sync> = match kind.as_str() {
+                    "lambertian" => Box::new(Lambertian::new_from_texture(self.texture(s)?)),
+                    "metal" => Box::new(Metal::new(
+                        s.required("color", parse_color)?,
+                        s.optional("fuzz", parse_finite)?.unwrap_or(0.0),
+                    )),
+                    "dielectric" => {
+                        Box::new(Dielectric::new(s.required("ior", parse_positive_number)?))
+                    }
+                    "diffuse_light" => {
+                        Box::new(DiffuseLight::new(s.required("color", parse_radiance)?))
+                    }
+                    "isotropic" => Box::new(Isotropic::new_from_texture(self.texture(s)?)),
+                    _ => return Err(s.error(None, &format!("unknown material type '{}'", kind))),
+                };
+                if self
+                    .materials
+                    .insert(name.clone(), Arc::new(material))
+                    .is_some()
+                {
+                    return Err(s.error(None, &format!("material '{}' is defined twice", name)));
+                }
+            }
+            "sphere" => {
+                s.positional(0)?;
+                let center = s.required("center", parse_vec3)?;
+                let radius = s.required("radius", parse_positive_number)?;
+                let material = self.material(s)?;
+                let object: Box<dyn Hittable + Send + Sync> =
+                    match s.optional("center1", parse_vec3)? {
+                        Some(center1) => Box::new(MovingSphere {
+                            center0: center,
+                            center1,
+                            time0: s.optional("time0", parse_finite)?.unwrap_or(0.0),
+                            time1: s.optional("time1", parse_finite)?.unwrap_or(1.0),
+                            radius,
+                            material,
+                        }),
+                        None => Box::new(Sphere {
+                            center,
+                            radius,
+                            material,
+                        }),
+                    };
+                self.push_object(s, object)?;
+            }
+            "triangle" => {
+                s.positional(0)?;
+                let (p1, p2, p3) = (
+                    s.required("p1", parse_vec3)?,
+                    s.required("p2", parse_vec3)?,
+                    s.required("p3", parse_vec3)?,
+                );
+                let normals = s.optional_triple(["n1", "n2", "n3"], parse_vec3)?;
+                let tex_coords = s.optional_triple(["uv1", "uv2", "uv3"], parse_uv)?;
+                let material = self.material(s)?;
+                let object = Box::new(match (normals, tex_coords) {
+                    (Some(normals), _) => {
+                        Triangle::new_smooth(p1, p2, p3, normals, tex_coords, material)
+                    }
+                    (None, Some(tex_coords)) => {
+                        Triangle::new_with_tex_coords(p1, p2, p3, tex_coords, material)
+                    }
+                    (None, None) => Triangle::new(p1, p2, p3, material),
+                });
+                self.push_object(s, object)?;
+            }
+            "quad" => {
+                s.positional(0)?;
+                let object = Box::new(Quad::new(
+                    s.required("p1", parse_vec3)?,
+                    s.required("p2", parse_vec3)?,
+                    s.required("p3", parse_vec3)?,
+                    s.required("p4", parse_vec3)?,
+                    &self.material(s)?,
+                ));
+                self.push_object(s, object)?;
+            }
+            "cube" => {
+                s.positional(0)?;
+                let object = Box::new(Cube::new(
+                    s.required("center", parse_vec3)?,
+                    s.required("size", parse_positive_number)? / 2.0,
+                    s.required("to", parse_vec3)?,
+                    self.material(s)?,
+                ));
+                self.push_object(s, object)?;
+            }
+            "cuboid" => {
+                s.positional(0)?;
+                let object = Box::new(Cube::new_cuboid(
+                    s.required("center", parse_vec3)?,
+                    s.required("to", parse_vec3)?,
+                    s.required("width", parse_positive_number)?,
+                    s.required("height", parse_positive_number)?,
+                    s.required("depth", parse_positive_number)?,
+                    self.material(s)?,
+                ));
+                self.push_object(s, object)?;
+            }
+            "mapped_cube" => {
+                s.positional(0)?;
+                let image = self.existing_path(s, "image")?;
+                let object = Box::new(
+                    Cube::new_mapped_cube(
+                        &image,
+                        s.required("center", parse_vec3)?,
+                        s.required("to", parse_vec3)?,
+                        s.required("width", parse_positive_number)?,
+                        s.required("height", parse_positive_number)?,
+                        s.required("depth", parse_positive_number)?,
+                    )
+                    .map_err(|e| s.error(Some("image"), &e))?,
+                );
+                self.push_object(s, object)?;
+            }
+            "mesh" => {
+                s.positional(0)?;
+                let mesh = self.mesh(s)?;
+                self.push_object(s, Box::new(mesh))?;
+            }
+            "object" => {
+                let (name, kind) = s.name_and_kind()?;
+                let object: Box<dyn Hittable + Send + Sync> = match kind.as_str() {
+                    "mesh" => Box::new(self.mesh(s)?),
+                    _ => return Err(s.error(None, &format!("unknown object type '{}'", kind))),
+                };
+                if self
+                    .prototypes
+                    .insert(name.clone(), Arc::new(object))
+                    .is_some()
+                {
+                    return Err(s.error(None, &format!("object '{}' is defined twice", name)));
+                }
+            }
+            "instance" => {
+                s.positional(0)?;
+                let name: String = s.required("object", parse_string)?;
+                let object = match self.prototypes.get(&name) {
+                    Some(object) => Arc::clone(object),
+                    None => {
+                        return Err(
+                            s.error(Some("object"), &format!("object '{}' is not defined", name))
+                        )
+                    }
+                };
+                let transform = self.transform(s)?.unwrap_or_else(Mat4::identity);
+                let material = self.optional_material(s, "material")?;
+                // The transform was checked to be invertible.
+                let instance = Instance::new(object, transform, material).unwrap();
+                self.objects.push(Box::new(instance));
+            }
+            "volume" => {
+                s.positional(0)?;
+                let grid = self.optional_existing_path(s, "grid")?;
+                let seed = s.optional("noise", parse_number)?;
+                let density: Arc<dyn DensityField + Send + Sync> = match (grid, seed) {
+                    (Some(path), None) => Arc::new(self.voxel_grid(s, "grid", &path)?),
+                    (None, Some(seed)) => Arc::new(NoiseField::new(
+                        seed,
+                        s.optional("frequency", parse_positive_number)?
+                            .unwrap_or(4.0),
+                        s.optional("octaves", parse_positive)?.unwrap_or(4),
+                    )),
+                    (Some(_), Some(_)) => {
+                        return Err(
+                            s.error(Some("noise"), "only one of 'grid' and 'noise' can be given")
+                        )
+                    }
+                    (None, None) => return Err(s.error(Some("grid"), "missing required key")),
+                };
+                let density_scale = s.required("density", parse_positive_number)?;
+                let albedo = s
+                    .optional("color", parse_color)?
+                    .unwrap_or_else(Color::white);
+                let emission_field = match self.optional_existing_path(s, "emission_grid")? {
+                    Some(path) => {
+                        let grid: Arc<dyn DensityField + Send + Sync> =
+                            Arc::new(self.voxel_grid(s, "emission_grid", &path)?);
+                        Some(grid)
+                    }
+                    None => None,
+                };
+                let emission = match s.optional("emission", parse_radiance)? {
+                    Some(color) => Some(Emission {
+                        color,
+                        field: emission_field,
+                    }),
+                    None if emission_field.is_some() => {
+                        return Err(s.error(Some("emission"), "missing, needed by 'emission_grid'"))
+                    }
+                    None => None,
+                };
+                let transform = self.transform(s)?.unwrap_or_else(Mat4::identity);
+                // The transform was checked to be invertible.
+                let medium =
+                    HeterogeneousMedium::new(density, density_scale, albedo, emission, transform)
+                        .unwrap();
+                self.objects.push(Box::new(medium));
+            }
+            _ => return Err(s.error(Some(&keyword), &format!("unknown statement '{}'", keyword))),
+        }
+        Ok(())
+    }
+
+    fn voxel_grid(&self, s: &Statement, key: &str, path: &str) -> Result<VoxelGrid, SceneError> {
+        VoxelGrid::load(path).map_err(|e| s.error(Some(key), &e))
+    }
+
+    fn material(
+        &self,
+        s: &mut Statement,
+    ) -> Result<Arc<Box<dyn Material + Send + Sync>>, SceneError> {
+        self.named_material(s, "material")
+    }
+
+    fn named_material(
+        &self,
+        s: &mut Statement,
+        key: &str,
+    ) -> Result<Arc<Box<dyn Material + Send + Sync>>, SceneError> {
+        self.optional_material(s, key)?
+            .ok_or_else(|| s.error(Some(key), "missing required key"))
+    }
+
+    fn optional_material(
+        &self,
+        s: &mut Statement,
+        key: &str,
+    ) -> Result<Option<Arc<Box<dyn Material + Send + Sync>>>, SceneError> {
+        let name: Option<String> = s.optional(key, parse_string)?;
+        match name {
+            Some(name) => match self.materials.get(&name) {
+                Some(material) => Ok(Some(Arc::clone(material))),
+                None => Err(s.error(Some(key), &format!("material '{}' is not defined", name))),
+            },
+            None => Ok(None),
+        }
+    }
+
+    // Fills the object with a constant medium when the statement has a `density`, the medium
+    // scatters with the material named by `phase`.
+    fn push_object(
+        &mut self,
+        s: &mut Statement,
+        object: Box<dyn Hittable + Send + Sync>,
+    ) -> Result<(), SceneError> {
+        let object = self.place_object(s, object)?;
+        match s.optional("density", parse_positive_number)? {
+            Some(density) => {
+                let phase = self.named_material(s, "phase")?;
+                self.objects
+                    .push(Box::new(ConstantMedium::new(object, density, phase)));
+            }
+            None => self.objects.push(object),
+        }
+        Ok(())
+    }
+
+    // Wraps the object in a `Transformed` when the statement has transform keys, or in an
+    // `Animated` moving between two poses when it also has `translate1`, `rotate1` or `scale1`.
+    fn place_object(
+        &mut self,
+        s: &mut Statement,
+        object: Box<dyn Hittable + Send + Sync>,
+    ) -> Result<Box<dyn Hittable + Send + Sync>, SceneError> {
+        let translate1 = s.optional("translate1", parse_vec3)?;
+        let rotate1 = s.optional("rotate1", parse_vec3)?;
+        let scale1 = s.optional("scale1", parse_vec3)?;
+        if translate1.is_some() || rotate1.is_some() || scale1.is_some() {
+            if s.optional("matrix", parse_matrix)?.is_some() {
+                return Err(s.error(
+                    Some("matrix"),
+                    "can't be animated, use translate, rotate and scale",
+                ));
+            }
+            let translate = s
+                .optional("translate", parse_vec3)?
+                .unwrap_or_else(Vec3::origin);
+            let rotate = s
+                .optional("rotate", parse_vec3)?
+                .unwrap_or_else(Vec3::origin);
+            let scale = s
+                .optional("scale", parse_vec3)?
+                .unwrap_or(Vec3::new(1.0, 1.0, 1.0));
+            let keyframe = |time: f64, translation: Vec3, rotation: Vec3, scale: Vec3| Keyframe {
+                time,
+                translation,
+                rotation: Quaternion::from_euler(rotation.map(f64::to_radians)),
+                scale,
+            };
+            let keyframes = vec![
+                keyframe(
+                    s.optional("time0", parse_finite)?.unwrap_or(0.0),
+                    translate,
+                    rotate,
+                    scale,
+                ),
+                keyframe(
+                    s.optional("time1", parse_finite)?.unwrap_or(1.0),
+                    translate1.unwrap_or(translate),
+                    rotate1.unwrap_or(rotate),
+                    scale1.unwrap_or(scale),
+                ),
+            ];
+            let animated =
+                Animated::new(object, keyframes).map_err(|e| s.error(Some("scale"), &e))?;
+            return Ok(Box::new(animated));
+        }
+
+        Ok(match self.transform(s)? {
+            // The transform was checked to be invertible.
+            Some(transform) => Box::new(Transformed::new(object, transform).unwrap()),
+            None => object,
+        })
+    }
+
+    fn transform(&self, s: &mut Statement) -> Result<Option<Mat4>, SceneError> {
+        let translate = s.optional("translate", parse_vec3)?;
+        let rotate = s.optional("rotate", parse_vec3)?;
+        let scale = s.optional("scale", parse_vec3)?;
+        let matrix = s.optional("matrix", parse_matrix)?;
+
+        let (transform, key) = match (translate, rotate, scale, matrix) {
+            (None, None, None, None) => return Ok(None),
+            (None, None, None, Some(matrix)) => (matrix, "matrix"),
+            (_, _, _, Some(_)) => {
+                return Err(s.error(
+                    Some("matrix"),
+                    "can't be combined with translate, rotate or scale",
+                ))
+            }
+            (translate, rotate, scale, None) => {
+                let rotate = rotate.unwrap_or_else(Vec3::origin).map(f64::to_radians);
+                let transform = Mat4::translation(translate.unwrap_or_else(Vec3::origin))
+                    * Mat4::from_euler(rotate)
+                    * Mat4::scaling(scale.unwrap_or(Vec3::new(1.0, 1.0, 1.0)));
+                (transform, "scale")
+            }
+        };
+        if transform.inverse().is_none() {
+            return Err(s.error(Some(key), "transform is not invertible"));
+        }
+        Ok(Some(transform))
+    }
+
+    fn mesh(&self, s: &mut Statement) -> Result<TriangleMesh, SceneError> {
+        let file = self.existing_path(s, "file")?;
+        // Without `material`, faces the MTL libraries don't cover are grey.
+        let material = self
+            .optional_material(s, "material")?
+            .unwrap_or_else(default_material);
+        let smooth = s.optional("smooth", parse_bool)?.unwrap_or(false);
+        load_obj(&file, &material, smooth).map_err(|e| s.error(Some("file"), &e.to_string()))
+    }
+
+    // A lambertian takes either an inline `color` or a named `texture`.
+    fn texture(&self, s: &mut Statement) -> Result<Box<dyn Texture + Send + Sync>, SceneError> {
+        let color = s.optional("color", parse_color)?;
+        let texture: Option<String> = s.optional("texture", parse_string)?;
+        match (color, texture) {
+            (Some(color), None) => Ok(Box::new(SolidColorTexture::new(color))),
+            (None, Some(name)) => match self.textures.get(&name) {
+                Some(texture) => Ok(texture.build()),
+                None => Err(s.error(
+                    Some("texture"),
+                    &format!("texture '{}' is not defined", name),
+                )),
+            },
+            (Some(_), Some(_)) => Err(s.error(
+                Some("texture"),
+                "only one of 'color' and 'texture' can be given",
+            )),
+            (None, None) => Err(s.error(Some("color"), "missing required key")),
+        }
+    }
+
+    fn existing_path(&self, s: &mut Statement, key: &str) -> Result<String, SceneError> {
+        self.optional_existing_path(s, key)?
+            .ok_or_else(|| s.error(Some(key), "missing required key"))
+    }
+
+    fn optional_existing_path(
+        &self,
+        s: &mut Statement,
+        key: &str,
+    ) -> Result<Option<String>, SceneError> {
+        let path: String = match s.optional(key, parse_string)? {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+        let resolved: PathBuf = self.base_dir.join(&path);
+        if !resolved.is_file() {
+            return Err(s.error(
+                Some(key),
+                &format!("file '{}' does not exist", resolved.display()),
+            ));
+        }
+        resolved
+            .to_str()
+            .map(|path| Some(String::from(path)))
+            .ok_or_else(|| s.error(Some(key), "path is not valid UTF-8"))
+    }
+}
+
+struct Statement<'a> {
+    file: &'a str,
+    line: usize,
+    keyword: String,
+    positional: Vec<String>,
+    properties: Vec<(String, String)>,
+}
+
+impl<'a> Statement<'a> {
+    fn new(file: &'a str, line: usize, tokens: Vec<String>) -> Result<Statement<'a>, SceneError> {
+        let mut statement = Statement {
+            file,
+            line,
+            keyword: tokens[0].clone(),
+            positional: vec![],
+            properties: vec![],
+        };
+        for token in tokens.into_iter().skip(1) {
+            match token.find('=') {
+                Some(i) => {
+                    let key = String::from(&token[..i]);
+                    if statement.properties.iter().any(|(k, _)| *k == key) {
+                        return Err(statement.error(Some(&key), "key is given more than once"));
+                    }
+                    statement
+                        .properties
+                        .push((key, String::from(&token[i + 1..])));
+                }
+                None => {
+                    if !statement.properties.is_empty() {
+                        return Err(
+                            statement.error(Some(&token), "expected key=value after the first key")
+                        );
+                    }
+                    statement.positional.push(token)
+                }
+            }
+        }
+        Ok(statement)
+    }
+
+    fn error(&self, key: Option<&str>, message: &str) -> SceneError {
+        SceneError {
+            file: String::from(self.file),
+            line: self.line,
+            key: key.map(String::from),
+            message: String::from(message),
+        }
+    }
+
+    fn positional(&self, count: usize) -> Result<(), SceneError> {
+        if self.positional.len() != count {
+            return Err(self.error(
+                Some(&self.keyword),
+                &format!(
+                    "expected {} value(s) before the key=value pairs, got {}",
+                    count,
+                    self.positional.len()
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn name_and_kind(&self) -> Result<(String, String), SceneError> {
+        self.positional(2)?;
+        Ok((self.positional[0].clone(), self.positional[1].clone()))
+    }
+
+    fn optional<T>(
+        &mut self,
+        key: &str,
+        parse: fn(&str) -> Result<T, String>,
+    ) -> Result<Option<T>, SceneError> {
+        match self.properties.iter().position(|(k, _)| k == key) {
+            Some(i) => {
+                let (_, value) = self.properties.remove(i);
+                parse(&value)
+                    .map(Some)
+                    .map_err(|message| self.error(Some(key), &message))
+            }
+            None => Ok(None),
+        }
+    }
+
+    // Per-vertex values such as `n1= n2= n3=` are given for all three vertices or none.
+    fn optional_triple<T>(
+        &mut self,
+        keys: [&str; 3],
+        parse: fn(&str) -> Result<T, String>,
+    ) -> Result<Option<[T; 3]>, SceneError> {
+        match (
+            self.optional(keys[0], parse)?,
+            self.optional(keys[1], parse)?,
+            self.optional(keys[2], parse)?,
+        ) {
+            (Some(a), Some(b), Some(c)) => Ok(Some([a, b, c])),
+            (None, None, None) => Ok(None),
+            (a, b, _) => {
+                let missing = if a.is_none() {
+                    keys[0]
+                } else if b.is_none() {
+                    keys[1]
+                } else {
+                    keys[2]
+                };
+                Err(self.error(Some(missing), "missing key, needed for all three vertices"))
+            }
+        }
+    }
+
+    fn required<T>(
+        &mut self,
+        key: &str,
+        parse: fn(&str) -> Result<T, String>,
+    ) -> Result<T, SceneError> {
+        self.optional(key, parse)?
+            .ok_or_else(|| self.error(Some(key), "missing required key"))
+    }
+
+    // Every key must have been consumed by the statement's parser.
+    fn finish(&self) -> Result<(), SceneError> {
+        match self.properties.first() {
+            Some((key, _)) => {
+                Err(self.error(Some(key), &format!("unknown key for '{}'", self.keyword)))
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+fn tokenize(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = vec![];
+    let mut token = String::new();
+    let mut in_quotes = false;
+    let mut has_token = false;
+
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_token = true;
+            }
+            '#' if !in_quotes => break,
+            c if c.is_whitespace() && !in_quotes => {
+                if has_token {
+                    tokens.push(token.clone());
+                    token.clear();
+                    has_token = false;
+                }
+            }
+            c => {
+                token.push(c);
+                has_token = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err(String::from("unterminated quote"));
+    }
+    if has_token {
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_string(value: &str) -> Result<String, String> {
+    Ok(String::from(value))
+}
+
+fn parse_number<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid number '{}'", value))
+}
+
+fn parse_finite(value: &str) -> Result<f64, String> {
+    match parse_number::<f64>(value)? {
+        x if x.is_finite() => Ok(x),
+        _ => Err(format!("must be finite, got '{}'", value)),
+    }
+}
+
+fn parse_positive(value: &str) -> Result<u32, String> {
+    match parse_number::<u32>(value)? {
+        0 => Err(String::from("must be positive")),
+        n => Ok(n),
+    }
+}
+
+fn parse_positive_number(value: &str) -> Result<f64, String> {
+    let n = parse_finite(value)?;
+    if n <= 0.0 {
+        return Err(format!("must be positive, got '{}'", value));
+    }
+    Ok(n)
+}
+
+fn parse_non_negative(value: &str) -> Result<f64, String> {
+    let n = parse_finite(value)?;
+    if n < 0.0 {
+        return Err(format!("must not be negative, got '{}'", value));
+    }
+    Ok(n)
+}
+
+fn parse_field_of_view(value: &str) -> Result<f64, String> {
+    let degrees = parse_finite(value)?;
+    if degrees <= 0.0 || degrees >= 180.0 {
+        return Err(format!(
+            "must be between 0 and 180 degrees, got '{}'",
+            value
+        ));
+    }
+    Ok(degrees)
+}
+
+fn parse_vec3(value: &str) -> Result<Vec3, String> {
+    let parts = value.split(',').collect::<Vec<&str>>();
+    if parts.len() != 3 {
+        return Err(format!("expected x,y,z, got '{}'", value));
+    }
+    Ok(Vec3::new(
+        parse_finite(parts[0])?,
+        parse_finite(parts[1])?,
+        parse_finite(parts[2])?,
+    ))
+}
+
+fn parse_interval(value: &str) -> Result<(f64, f64), String> {
+    match value.split(',').collect::<Vec<&str>>()[..] {
+        [open, close] => {
+            let (open, close) = (parse_finite(open.trim())?, parse_finite(close.trim())?);
+            if close < open {
+                return Err(format!("closes before it opens, got '{}'", value));
+            }
+            Ok((open, close))
+        }
+        _ => Err(format!("expected open,close, got '{}'", value)),
+    }
+}
+
+fn parse_matrix(value: &str) -> Result<Mat4, String> {
+    let values = value
+        .split(',')
+        .map(|v| parse_finite(v.trim()))
+        .collect::<Result<Vec<f64>, String>>()?;
+    if values.len() != 16 {
+        return Err(format!("expected 16 numbers, got {}", values.len()));
+    }
+    let mut m = [[0.0; 4]; 4];
+    for (i, value) in values.into_iter().enumerate() {
+        m[i / 4][i % 4] = value;
+    }
+    Ok(Mat4::new(m))
+}
+
+fn parse_uv(value: &str) -> Result<(f64, f64), String> {
+    match value.split(',').collect::<Vec<&str>>()[..] {
+        [u, v] => Ok((parse_finite(u.trim())?, parse_finite(v.trim())?)),
+        _ => Err(format!("expected u,v, got '{}'", value)),
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(format!("expected true or false, got '{}'", value)),
+    }
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    let rgb = parse_vec3(value)?;
+    for c in [rgb.x(), rgb.y(), rgb.z()] {
+        if !(0.0..=1.0).contains(&c) {
+            return Err(format!(
+                "color components must be in [0, 1], got '{}'",
+                value
+            ));
+        }
+    }
+    Ok(Color::new(rgb.x(), rgb.y(), rgb.z()))
+}
+
+// Light, unlike the colors of surfaces, can be brighter than 1.
+fn parse_radiance(value: &str) -> Result<Color, String> {
+    let rgb = parse_vec3(value)?;
+    if rgb.as_slice().iter().any(|c| !c.is_finite() || *c < 0.0) {
+        return Err(format!(
+            "light must be finite and not negative, got '{}'",
+            value
+        ));
+    }
+    Ok(Color::new_from_vector(rgb))
+}
+
+#[cfg(test)]
+fn parse_test_scene(contents: &str) -> Result<Scene, SceneError> {
+    parse_scene(contents, "test.scene", Path::new("."))
+}
+
+#[test]
+fn parse_full_scene() {
+    let scene = parse_test_scene(
+        "# A small scene
+        camera look_from=0,0,5 look_at=0,0,0 vfov=40 aperture=0.1
+        render width=120 aspect_ratio=1.5 samples=4 depth=8 glossy_depth=0 roulette=3 threads=2 leaf_size=3 output=\"out put.png\"
+        background color=0,0,0
+        display exposure=-0.5 tone_map=extended_reinhard white=8 dither=false
+
+        texture check checkered even=1,1,1 odd=0,0,0 size=0.5
+        material grey lambertian color=0.5,0.5,0.5
+        material floor lambertian texture=check
+        material shiny metal color=0.9,0.9,0.9 fuzz=0.1
+        material glass dielectric ior=1.5
+        material lamp diffuse_light color=15,15,15
+
+        sphere center=0,-100,0 radius=100 material=floor
+        sphere center=0,1,0 radius=1 material=glass # inline comment
+        triangle p1=0,0,0 p2=1,0,0 p3=0,1,0 material=grey
+        triangle p1=0,0,0 p2=1,0,0 p3=0,1,0 n1=0,0,1 n2=0,1,1 n3=1,0,1 uv1=0,0 uv2=1,0 uv3=0,1 material=grey
+        quad p1=0,3,0 p2=1,3,0 p3=1,3,1 p4=0,3,1 material=lamp
+        cuboid center=2,1,0 to=2,1,1 width=1 height=2 depth=1 material=shiny
+        cube center=-2,1,0 size=1 to=-2,1,1 material=shiny",
+    )
+    .unwrap();
+
+    assert_eq!(scene.objects.len(), 7);
+    assert_eq!(scene.settings.img_width, 120);
+    assert_eq!(scene.settings.img_height, 80);
+    assert_eq!(scene.settings.samples_per_pixel, 4);
+    assert_eq!(scene.settings.recursive_depth, 8);
+    assert_eq!(scene.settings.russian_roulette, Some(3));
+    assert_eq!(scene.settings.glossy_depth, Some(0));
+    assert_eq!(scene.settings.diffuse_depth, None);
+    assert_eq!(scene.settings.num_threads, 2);
+    assert_eq!(scene.settings.bvh_leaf_size, 3);
+    assert_eq!(scene.settings.output, "out put.png");
+    assert_eq!(scene.settings.background, Color::black());
+    assert_eq!(scene.settings.display.exposure, -0.5);
+    assert_eq!(
+        scene.settings.display.tone_mapping,
+        ToneMapping::ExtendedReinhard { white: 8.0 }
+    );
+    assert!(!scene.settings.display.dither);
+    assert_eq!(scene.camera.look_from, Vec3::new(0.0, 0.0, 5.0));
+    assert_eq!(scene.camera.vup, Vec3::new(0.0, 1.0, 0.0));
+    assert_eq!(scene.camera.focus_dist, 5.0);
+}
+
+#[test]
+fn parse_errors_report_line_and_key() {
+    let camera = "camera look_from=0,0,5 look_at=0,0,0\n";
+    let cases = [
+        (
+            "sphere center=0,0,0 radius=1 material=grey",
+            2,
+            Some("material"),
+        ),
+        (
+            "sphere center=0,0 radius=1 material=grey",
+            2,
+            Some("center"),
+        ),
+        ("material m metal color=1,1,1 fuz=0.1", 2, Some("fuz")),
+        ("material grey lambertian color=2,0,0", 2, Some("color")),
+        ("material lamp diffuse_light color=-1,0,0", 2, Some("color")),
+        ("teapot size=1", 2, Some("teapot")),
+        ("\n\nrender samples=0", 4, Some("samples")),
+        ("render width=10 width=20", 2, Some("width")),
+        ("render width=8 aspect_ratio=100", 2, Some("aspect_ratio")),
+        ("render aspect_ratio=NaN", 2, Some("aspect_ratio")),
+        (
+            "sphere center=0,0,0 radius=NaN material=grey",
+            2,
+            Some("radius"),
+        ),
+        (
+            "sphere center=0,inf,0 radius=1 material=grey",
+            2,
+            Some("center"),
+        ),
+        ("material m metal color=1,1,1 fuzz=inf", 2, Some("fuzz")),
+        ("material glass dielectric ior=NaN", 2, Some("ior")),
+        (
+            "camera look_from=0,0,5 look_at=0,0,0 vfov=180",
+            2,
+            Some("vfov"),
+        ),
+        (
+            "camera look_from=0,0,5 look_at=0,0,0 aperture=-1",
+            2,
+            Some("aperture"),
+        ),
+        ("mesh file=missing.obj material=grey", 2, Some("file")),
+        (
+            "triangle p1=0,0,0 p2=1,0,0 p3=0,1,0 n1=0,0,1 material=grey",
+            2,
+            Some("n2"),
+        ),
+        (
+            "triangle p1=0,0,0 p2=1,0,0 p3=0,1,0 uv1=0 uv2=0,1 uv3=1,1",
+            2,
+            Some("uv1"),
+        ),
+        ("background color=\"0,0,0", 2, None),
+        ("display tone_map=filmic", 2, Some("tone_map")),
+        ("display tone_map=aces white=4", 2, Some("tone_map")),
+        ("display white=4", 2, Some("white")),
+        ("display exposure=inf", 2, Some("exposure")),
+    ];
+
+    for (line, expected_line, expected_key) in cases.iter() {
+        let error = parse_test_scene(&format!("{}{}", camera, line))
+            .err()
+            .unwrap_or_else(|| panic!("Expected an error for '{}'", line));
+        assert_eq!(error.file, "test.scene");
+        assert_eq!(error.line, *expected_line, "{}", error);
+        assert_eq!(error.key.as_deref(), *expected_key, "{}", error);
+    }
+}
+
+#[test]
+fn undecodable_images_are_errors() {
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("bad-image-scene");
+    fs::write(dir.join("bad.png"), "not an image").unwrap();
+
+    let camera = "camera look_from=0,0,5 look_at=0,0,0\n";
+    let cases = [
+        ("texture t image path=bad.png", "path"),
+        (
+            "mapped_cube image=bad.png center=0,0,0 to=0,0,1 width=1 height=1 depth=1",
+            "image",
+        ),
+    ];
+    for (line, expected_key) in cases.iter() {
+        let error = parse_scene(&format!("{}{}", camera, line), "test.scene", dir.path())
+            .err()
+            .unwrap_or_else(|| panic!("Expected an error for '{}'", line));
+        assert_eq!(error.line, 2, "{}", error);
+        assert_eq!(error.key.as_deref(), Some(*expected_key), "{}", error);
+    }
+}
+
+#[test]
+fn meshes_without_material_use_their_libraries() {
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("mtl-mesh-scene");
+    fs::write(dir.join("red.mtl"), "newmtl red\nKd 1 0 0\n").unwrap();
+    fs::write(
+        dir.join("red.obj"),
+        "mtllib red.mtl\nv -1 -1 0\nv 1 -1 0\nv 0 1 0\nusemtl red\nf 1 2 3\n",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("plain.obj"),
+        "v -1 -1 0\nv 1 -1 0\nv 0 1 0\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let scene = parse_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        mesh file=red.obj
+        mesh file=plain.obj",
+        "test.scene",
+        dir.path(),
+    )
+    .unwrap();
+
+    let ray = crate::geometry::ray::Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let colors = scene
+        .objects
+        .iter()
+        .map(|object| {
+            let hit = object.hit(&ray, 0.001, f64::MAX).unwrap();
+            hit.material.scatter(&ray, &hit).attenuation
+        })
+        .collect::<Vec<Color>>();
+    assert_eq!(
+        colors,
+        vec![Color::new(1.0, 0.0, 0.0), Color::new(0.5, 0.5, 0.5)]
+    );
+}
+
+#[test]
+fn parse_objects_and_instances() {
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("instance-scene");
+    fs::write(
+        dir.join("tri.obj"),
+        "v -1 -1 0\nv 1 -1 0\nv 0 1 0\nf 1 2 3\n",
+    )
+    .unwrap();
+
+    let scene = parse_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material grey lambertian color=0.5,0.5,0.5
+        material shiny metal color=0.9,0.9,0.9
+        object tri mesh file=tri.obj material=grey
+        instance object=tri
+        instance object=tri translate=10,0,0 rotate=0,90,0 scale=2,2,2 material=shiny",
+        "test.scene",
+        dir.path(),
+    );
+    let error = parse_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material grey lambertian color=0.5,0.5,0.5
+        object tri mesh file=tri.obj material=grey
+        instance object=tri scale=1,0,1",
+        "test.scene",
+        dir.path(),
+    )
+    .err()
+    .unwrap();
+
+    // Prototypes are only placed through their instances.
+    let scene = scene.unwrap();
+    assert_eq!(scene.objects.len(), 2);
+    let bounding_box = scene.objects[1].get_bounding_box();
+    assert!((bounding_box.min_point.x() - 10.0).abs() < 1e-3);
+    assert!((bounding_box.min_point.z() + 2.0).abs() < 1e-3);
+    assert!((bounding_box.max_point.y() - 2.0).abs() < 1e-3);
+
+    assert_eq!(error.line, 4);
+    assert_eq!(error.key.as_deref(), Some("scale"));
+
+    let error = parse_test_scene("camera look_from=0,0,5 look_at=0,0,0\ninstance object=teapot")
+        .err()
+        .unwrap();
+    assert_eq!(error.key.as_deref(), Some("object"));
+}
+
+#[test]
+fn parse_transformed_shapes() {
+    let scene = parse_test_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material grey lambertian color=0.5,0.5,0.5
+        sphere center=0,0,0 radius=1 scale=2,1,1 rotate=0,0,90 translate=0,0,-3 material=grey
+        cube center=0,0,0 size=2 to=0,0,1 material=grey matrix=1,0,0,5,0,1,0,0,0,0,1,0,0,0,0,1
+        cube center=0,0,0 size=2 to=0,0,1 material=grey scale=2,1,1
+        sphere center=0,0,0 radius=1 material=grey",
+    )
+    .unwrap();
+
+    // Stretched along x, then turned onto the y axis.
+    let ellipsoid = scene.objects[0].get_bounding_box();
+    assert!((ellipsoid.min_point - Vec3::new(-1.0, -2.0, -4.0)).len() < 1e-9);
+    assert!((ellipsoid.max_point - Vec3::new(1.0, 2.0, -2.0)).len() < 1e-9);
+    let cube = scene.objects[1].get_bounding_box();
+    assert!((cube.min_point.x() - 4.0).abs() < 1e-9);
+    // `scale` stretches cubes like every other shape.
+    let stretched = scene.objects[2].get_bounding_box();
+    assert!((stretched.max_point - Vec3::new(2.0, 1.0, 1.0)).len() < 1e-9);
+    assert_eq!(
+        scene.objects[3].get_bounding_box().max_point,
+        Vec3::new(1.0, 1.0, 1.0)
+    );
+
+    let camera = "camera look_from=0,0,5 look_at=0,0,0\nmaterial grey lambertian color=1,1,1\n";
+    for (line, key) in [
+        (
+            "sphere center=0,0,0 radius=1 scale=1,0,1 material=grey",
+            "scale",
+        ),
+        (
+            "sphere center=0,0,0 radius=1 translate=1,0,0 matrix=1,0,0,0,0,1,0,0,0,0,1,0,0,0,0,1 material=grey",
+            "matrix",
+        ),
+        (
+            "sphere center=0,0,0 radius=1 matrix=1,0,0 material=grey",
+            "matrix",
+        ),
+        (
+            "sphere center=0,0,0 radius=1 scale=NaN,1,1 material=grey",
+            "scale",
+        ),
+        (
+            "sphere center=0,0,0 radius=1 matrix=1,0,0,0,0,1,0,0,0,0,1,0,0,0,0,inf material=grey",
+            "matrix",
+        ),
+    ] {
+        let error = parse_test_scene(&format!("{}{}", camera, line))
+            .err()
+            .unwrap();
+        assert_eq!(error.key.as_deref(), Some(key), "{}", error);
+    }
+}
+
+#[test]
+fn parse_moving_objects() {
+    use crate::geometry::ray::Ray;
+
+    let scene = parse_test_scene(
+        "camera look_from=0,0,5 look_at=0,0,0 shutter=0,0.5
+        material grey lambertian color=0.5,0.5,0.5
+        sphere center=0,0,0 center1=2,0,0 radius=1 material=grey
+        cuboid center=0,0,0 to=0,0,-1 width=1 height=1 depth=1 translate1=0,4,0 rotate1=0,90,0 time1=2 material=grey",
+    )
+    .unwrap();
+    assert_eq!(
+        (scene.camera.shutter_open, scene.camera.shutter_close),
+        (0.0, 0.5)
+    );
+
+    let sphere = scene.objects[0].get_bounding_box();
+    assert_eq!(sphere.max_point, Vec3::new(3.0, 1.0, 1.0));
+    let down = Vec3::new(0.0, -1.0, 0.0);
+    let ray = Ray::new_at_time(Vec3::new(2.5, 5.0, 0.0), down, 0.25);
+    assert!(scene.objects[0].hit(&ray, 0.0, f64::MAX).is_none());
+    let ray = Ray::new_at_time(Vec3::new(2.5, 5.0, 0.0), down, 1.0);
+    assert!(scene.objects[0].hit(&ray, 0.0, f64::MAX).is_some());
+
+    // Halfway up at t = 1.
+    let ray = Ray::new_at_time(Vec3::new(0.0, 5.0, 0.0), down, 1.0);
+    let hit = scene.objects[1].hit(&ray, 0.0, f64::MAX).unwrap();
+    assert!((hit.hit_point.y() - 2.5).abs() < 1e-9);
+    assert!(scene.objects[1].get_bounding_box().max_point.y() >= 4.5);
+
+    let error = parse_test_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material grey lambertian color=0.5,0.5,0.5
+        sphere center=0,0,0 radius=1 translate1=1,0,0 matrix=1,0,0,0,0,1,0,0,0,0,1,0,0,0,0,1 material=grey",
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error.key.as_deref(), Some("matrix"));
+    let error = parse_test_scene("camera look_from=0,0,5 look_at=0,0,0 shutter=1,0")
+        .err()
+        .unwrap();
+    assert_eq!(error.key.as_deref(), Some("shutter"));
+}
+
+#[test]
+fn parse_media() {
+    use crate::geometry::ray::Ray;
+    use crate::scene::writer::SceneWriter;
+
+    let scene = parse_test_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material smoke isotropic color=0.2,0.2,0.2
+        material grey lambertian color=0.5,0.5,0.5
+        sphere center=0,0,0 radius=1 material=grey density=1000 phase=smoke",
+    )
+    .unwrap();
+    // So dense that rays scatter right where they enter, with the phase function.
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), Vec3::new(0.0, 0.0, -1.0));
+    let hit = scene.objects[0].hit(&ray, 0.0, f64::MAX).unwrap();
+    assert!((hit.t - 4.0).abs() < 0.05);
+    let mut writer = SceneWriter::new();
+    assert_eq!(
+        writer.material_definition(hit.material).unwrap(),
+        "isotropic color=0.2,0.2,0.2"
+    );
+
+    let error = parse_test_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material grey lambertian color=0.5,0.5,0.5
+        sphere center=0,0,0 radius=1 material=grey density=1",
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error.key.as_deref(), Some("phase"));
+    let error = parse_test_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        material grey lambertian color=0.5,0.5,0.5
+        sphere center=0,0,0 radius=1 material=grey density=0 phase=grey",
+    )
+    .err()
+    .unwrap();
+    assert_eq!(error.key.as_deref(), Some("density"));
+}
+
+#[test]
+fn parse_volumes() {
+    use crate::scene::writer::scene_to_string;
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("volume-scene");
+    let grid = VoxelGrid::new([2, 2, 2], vec![0.0, 1.0, 0.5, 0.25, 1.0, 0.0, 0.0, 2.0]).unwrap();
+    grid.save(dir.join("cloud.vox").to_str().unwrap()).unwrap();
+
+    let scene = parse_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        volume grid=cloud.vox density=0.5 color=0.8,0.8,0.8 translate=0,1,0 scale=4,2,4
+        volume noise=3 frequency=2 octaves=3 density=1 emission=1,0.5,0 emission_grid=cloud.vox",
+        "test.scene",
+        dir.path(),
+    );
+    let error = parse_scene(
+        "camera look_from=0,0,5 look_at=0,0,0
+        volume grid=cloud.vox noise=3 density=1",
+        "test.scene",
+        dir.path(),
+    )
+    .err()
+    .unwrap();
+    let scene = scene.unwrap();
+    let contents = scene_to_string(&scene).unwrap();
+    let written = parse_scene(&contents, "written.scene", dir.path()).unwrap();
+
+    let bounding_box = scene.objects[0].get_bounding_box();
+    assert_eq!(bounding_box.min_point, Vec3::new(-2.0, 0.0, -2.0));
+    assert_eq!(bounding_box.max_point, Vec3::new(2.0, 2.0, 2.0));
+    assert_eq!(error.key.as_deref(), Some("noise"));
+    assert!(contents.contains("noise=3 frequency=2 octaves=3 density=1"));
+    assert_eq!(scene_to_string(&written).unwrap(), contents);
+
+    let error = parse_test_scene("camera look_from=0,0,5 look_at=0,0,0\nvolume density=1")
+        .err()
+        .unwrap();
+    assert_eq!(error.key.as_deref(), Some("grid"));
+}
+
+#[test]
+fn missing_camera_is_an_error() {
+    let error = parse_test_scene("background color=1,1,1").err().unwrap();
+    assert_eq!(error.key.as_deref(), Some("camera"));
+    assert_eq!(
+        error.to_string(),
+        "test.scene: 'camera': scene has no camera statement"
+    );
+}
+
+#[test]
+fn display_scene_error() {
+    let error = SceneError {
+        file: String::from("a.scene"),
+        line: 3,
+        key: Some(String::from("radius")),
+        message: String::from("must be positive, got '-1'"),
+    };
+    assert_eq!(
+        error.to_string(),
+        "a.scene:3: 'radius': must be positive, got '-1'"
+    );
+}
@@ -0,0 +1,23 @@
+use crate::hittable::hittable::Hittable;
+use crate::world::camera::{Camera, CameraSettings};
+use crate::world::render_settings::RenderSettings;
+use crate::world::world::World;
+
+pub struct Scene {
+    pub objects: Vec<Box<dyn Hittable + Send + Sync>>,
+    pub camera: CameraSettings,
+    pub settings: RenderSettings,
+}
+
+impl Scene {
+    pub fn build_camera(&self) -> Camera {
+        self.camera
+            .build(self.settings.img_width, self.settings.img_height)
+    }
+
+    pub fn into_world(self) -> (World, Camera, RenderSettings) {
+        let camera = self.build_camera();
+        let world = World::new_with_leaf_size(self.objects, self.settings.bvh_leaf_size as usize);
+        (world, camera, self.settings)
+    }
+}
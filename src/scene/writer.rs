@@ -0,0 +1,528 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::geometry::mat4::Mat4;
+use crate::geometry::vec3::Vec3;
+use crate::hittable::constant_medium::ConstantMedium;
+use crate::hittable::cube::Cube;
+use crate::hittable::heterogeneous_medium::HeterogeneousMedium;
+use crate::hittable::hittable::Hittable;
+use crate::hittable::instance::Instance;
+use crate::hittable::moving_sphere::MovingSphere;
+use crate::hittable::quad::Quad;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::transformed::Transformed;
+use crate::hittable::triangle::Triangle;
+use crate::hittable::triangle_mesh::TriangleMesh;
+use crate::material::checkered_texture::CheckeredTexture;
+use crate::material::color::Color;
+use crate::material::dielectric::Dielectric;
+use crate::material::diffuse_light::DiffuseLight;
+use crate::material::image_texture::ImageTexture;
+use crate::material::isotropic::Isotropic;
+use crate::material::lambertian::Lambertian;
+use crate::material::material::Material;
+use crate::material::metal::Metal;
+use crate::material::solid_color_texture::SolidColorTexture;
+use crate::material::texture::Texture;
+use crate::scene::scene::Scene;
+use crate::volume::density_field::DensityField;
+use crate::volume::noise::NoiseField;
+use crate::volume::voxel_grid::VoxelGrid;
+
+// Objects that can be written to a scene file add the statements that recreate them.
+pub trait WriteScene {
+    fn write_scene(&self, writer: &mut SceneWriter) -> Result<(), String>;
+}
+
+// Materials, textures and density fields that can be written to a scene file. Materials and
+// textures give the `<type> key=value...` part of their statement, fields the `key=value...` part
+// of the volume statement.
+pub trait SceneDefinition {
+    fn scene_definition(&self, writer: &mut SceneWriter) -> Result<String, String>;
+}
+
+// Calls the scene file implementation of the concrete type behind a trait object, for the types
+// the parser can read back.
+macro_rules! write_as {
+    ($value:expr, $method:ident($writer:expr), $kind:literal, [$($t:ty),+ $(,)?]) => {{
+        let value: &dyn Any = $value;
+        $(
+            if let Some(value) = value.downcast_ref::<$t>() {
+                return value.$method($writer);
+            }
+        )+
+        Err(String::from(concat!(
+            "scene files have no statement for this ",
+            $kind
+        )))
+    }};
+}
+
+// Collects the statements of a scene file. Objects describe themselves through `WriteScene`,
+// materials that are shared between objects are written once and referenced by name.
+pub struct SceneWriter {
+    textures: Vec<String>,
+    materials: Vec<String>,
+    objects: Vec<String>,
+    material_names: HashMap<usize, String>,
+    // Transforms of the enclosing `Transformed` objects, each composed with the previous ones.
+    transforms: Vec<Mat4>,
+    // Keys added by enclosing objects such as a `ConstantMedium`.
+    modifiers: Vec<String>,
+    // Names of the materials that replace those of the objects inside an `Instance`.
+    material_overrides: Vec<String>,
+}
+
+impl SceneWriter {
+    pub fn new() -> SceneWriter {
+        SceneWriter {
+            textures: vec![],
+            materials: vec![],
+            objects: vec![],
+            material_names: HashMap::new(),
+            transforms: vec![],
+            modifiers: vec![],
+            material_overrides: vec![],
+        }
+    }
+
+    // Returns the name of the material, defining it on first use. Inside `push_material` it is
+    // the name of the replacing material instead.
+    pub fn material(
+        &mut self,
+        material: &Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Result<String, String> {
+        if let Some(name) = self.material_overrides.last() {
+            return Ok(name.clone());
+        }
+        let key = Arc::as_ptr(material) as *const () as usize;
+        if let Some(name) = self.material_names.get(&key) {
+            return Ok(name.clone());
+        }
+
+        let definition = self.material_definition(material.as_ref().as_ref())?;
+        let name = format!("material_{}", self.materials.len());
+        self.materials
+            .push(format!("material {} {}", name, definition));
+        self.material_names.insert(key, name.clone());
+        Ok(name)
+    }
+
+    // Textures are owned by their material, so every call defines a new texture.
+    pub fn texture(&mut self, texture: &(dyn Texture + Send + Sync)) -> Result<String, String> {
+        let definition = self.texture_definition(texture)?;
+        let name = format!("texture_{}", self.textures.len());
+        self.textures
+            .push(format!("texture {} {}", name, definition));
+        Ok(name)
+    }
+
+    pub fn write_object(&mut self, object: &(dyn Hittable + Send + Sync)) -> Result<(), String> {
+        write_as!(
+            object,
+            write_scene(self),
+            "object",
+            [
+                Sphere,
+                MovingSphere,
+                Triangle,
+                Quad,
+                Cube,
+                TriangleMesh,
+                Transformed,
+                Instance,
+                ConstantMedium,
+                HeterogeneousMedium,
+            ]
+        )
+    }
+
+    pub fn material_definition(
+        &mut self,
+        material: &(dyn Material + Send + Sync),
+    ) -> Result<String, String> {
+        write_as!(
+            material,
+            scene_definition(self),
+            "material",
+            [Lambertian, Metal, Dielectric, DiffuseLight, Isotropic]
+        )
+    }
+
+    pub fn texture_definition(
+        &mut self,
+        texture: &(dyn Texture + Send + Sync),
+    ) -> Result<String, String> {
+        write_as!(
+            texture,
+            scene_definition(self),
+            "texture",
+            [SolidColorTexture, CheckeredTexture, ImageTexture]
+        )
+    }
+
+    pub fn field_definition(
+        &mut self,
+        field: &(dyn DensityField + Send + Sync),
+    ) -> Result<String, String> {
+        write_as!(
+            field,
+            scene_definition(self),
+            "density field",
+            [VoxelGrid, NoiseField]
+        )
+    }
+
+    // Objects written between `push_transform` and `pop_transform` get a `matrix=` key, and
+    // the keys of every `push_modifier` after that.
+    pub fn object(&mut self, statement: String) {
+        let mut statement = statement;
+        if let Some(transform) = self.transforms.last() {
+            statement.push_str(&format!(" matrix={}", format_matrix(transform)));
+        }
+        for modifier in self.modifiers.iter() {
+            statement.push(' ');
+            statement.push_str(modifier);
+        }
+        self.objects.push(statement);
+    }
+
+    pub fn push_transform(&mut self, transform: &Mat4) {
+        let composed = match self.transforms.last() {
+            Some(outer) => outer * transform,
+            None => *transform,
+        };
+        self.transforms.push(composed);
+    }
+
+    pub fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+
+    // Objects written until `pop_material` use `material` in place of their own.
+    pub fn push_material(
+        &mut self,
+        material: &Arc<Box<dyn Material + Send + Sync>>,
+    ) -> Result<(), String> {
+        let name = self.material(material)?;
+        self.material_overrides.push(name);
+        Ok(())
+    }
+
+    pub fn pop_material(&mut self) {
+        self.material_overrides.pop();
+    }
+
+    pub fn push_modifier(&mut self, keys: String) {
+        self.modifiers.push(keys);
+    }
+
+    pub fn pop_modifier(&mut self) {
+        self.modifiers.pop();
+    }
+}
+
+impl Default for SceneWriter {
+    fn default() -> Self {
+        SceneWriter::new()
+    }
+}
+
+pub fn scene_to_string(scene: &Scene) -> Result<String, String> {
+    let mut writer = SceneWriter::new();
+    for object in scene.objects.iter() {
+        writer.write_object(object.as_ref())?;
+    }
+
+    let camera = &scene.camera;
+    let settings = &scene.settings;
+    let mut render = format!(
+        "render width={} height={} samples={} depth={}",
+        settings.img_width,
+        settings.img_height,
+        settings.samples_per_pixel,
+        settings.recursive_depth
+    );
+    for (key, depth) in [
+        ("diffuse_depth", settings.diffuse_depth),
+        ("glossy_depth", settings.glossy_depth),
+        ("transmission_depth", settings.transmission_depth),
+    ] {
+        if let Some(depth) = depth {
+            render.push_str(&format!(" {}={}", key, depth));
+        }
+    }
+    if let Some(min_bounces) = settings.russian_roulette {
+        render.push_str(&format!(" roulette={}", min_bounces));
+    }
+    render.push_str(&format!(
+        " threads={} leaf_size={} output={}",
+        settings.num_threads,
+        settings.bvh_leaf_size,
+        format_string(&settings.output)?
+    ));
+    let display = &settings.display;
+    let mut tone_map = format!("tone_map={}", display.tone_mapping.name());
+    if let Some(white) = display.tone_mapping.white() {
+        tone_map.push_str(&format!(" white={}", white));
+    }
+    let mut lines = vec![
+        format!(
+            "camera look_from={} look_at={} vup={} vfov={} aperture={} focus_dist={} shutter={},{}",
+            format_vec3(&camera.look_from),
+            format_vec3(&camera.look_at),
+            format_vec3(&camera.vup),
+            camera.vertical_fov,
+            camera.aperture,
+            camera.focus_dist,
+            camera.shutter_open,
+            camera.shutter_close
+        ),
+        render,
+        format!("background color={}", format_color(&settings.background)),
+        format!(
+            "display exposure={} {} dither={}",
+            display.exposure, tone_map, display.dither
+        ),
+        String::new(),
+    ];
+    for section in [writer.textures, writer.materials, writer.objects] {
+        if !section.is_empty() {
+            lines.extend(section);
+            lines.push(String::new());
+        }
+    }
+    Ok(lines.join("\n"))
+}
+
+pub fn save_scene(scene: &Scene, path: &str) -> Result<(), String> {
+    let contents = scene_to_string(scene)?;
+    fs::write(path, contents).map_err(|e| format!("Could not write {}: {}", path, e))
+}
+
+pub fn format_vec3(v: &Vec3) -> String {
+    format!("{},{},{}", v.x(), v.y(), v.z())
+}
+
+pub fn format_color(c: &Color) -> String {
+    format!("{},{},{}", c.r(), c.g(), c.b())
+}
+
+// All 16 values, row by row.
+pub fn format_matrix(m: &Mat4) -> String {
+    (0..16)
+        .map(|i| m.get(i / 4, i % 4).to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// Values with whitespace or `#` are quoted. Scene files have no way to escape a quote, so values
+// containing one can't be written.
+pub fn format_string(s: &str) -> Result<String, String> {
+    if s.contains('"') {
+        return Err(format!(
+            "'{}' contains a quote, which scene files cannot hold",
+            s
+        ));
+    }
+    if s.is_empty() || s.contains(|c: char| c.is_whitespace() || c == '#') {
+        Ok(format!("\"{}\"", s))
+    } else {
+        Ok(String::from(s))
+    }
+}
+
+// The parser resolves relative paths against the scene file, so paths are written as absolute
+// paths when the file exists.
+pub fn format_path(path: &str) -> Result<String, String> {
+    match fs::canonicalize(Path::new(path)) {
+        Ok(absolute) => format_string(&absolute.to_string_lossy()),
+        Err(_) => format_string(path),
+    }
+}
+
+#[cfg(test)]
+fn random_field_scene() -> Scene {
+    use crate::hittable::cube::Cube;
+    use crate::hittable::hittable::Hittable;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::dielectric::Dielectric;
+    use crate::material::lambertian::Lambertian;
+    use crate::material::metal::Metal;
+    use crate::world::camera::CameraSettings;
+    use crate::world::render_settings::RenderSettings;
+
+    let glass: Arc<Box<dyn Material + Send + Sync>> = Arc::new(Box::new(Dielectric::new(1.5)));
+    let mut objects: Vec<Box<dyn Hittable + Send + Sync>> = vec![];
+    for i in 0..10 {
+        let center = Vec3::random(-10.0, 10.0);
+        let material: Arc<Box<dyn Material + Send + Sync>> = match i % 3 {
+            0 => Arc::new(Box::new(Metal::new(Color::random(), 0.1))),
+            1 => Arc::new(Box::new(Lambertian::new_from_color(Color::random()))),
+            _ => Arc::clone(&glass),
+        };
+        if i % 2 == 0 {
+            objects.push(Box::new(Cube::new(
+                center,
+                0.3,
+                Vec3::new(1.0, 0.5, 0.0),
+                material,
+            )));
+        } else {
+            objects.push(Box::new(Sphere {
+                center,
+                radius: 0.2,
+                material,
+            }));
+        }
+    }
+
+    Scene {
+        objects,
+        camera: CameraSettings {
+            look_from: Vec3::new(13.0, 2.0, 3.0),
+            look_at: Vec3::new(0.0, 0.0, 0.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            aperture: 0.1,
+            focus_dist: 10.0,
+            vertical_fov: 50.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        },
+        settings: RenderSettings::new(1.5, 300, Color::white(), "random field.png"),
+    }
+}
+
+#[test]
+fn written_scene_parses_back_to_the_same_scene() {
+    use crate::output::display::ToneMapping;
+    use crate::scene::parser::parse_scene;
+
+    let mut scene = random_field_scene();
+    scene.settings.russian_roulette = Some(5);
+    scene.settings.transmission_depth = Some(3);
+    scene.settings.display.exposure = 1.5;
+    scene.settings.display.tone_mapping = ToneMapping::ExtendedReinhard { white: 6.0 };
+    let contents = scene_to_string(&scene).unwrap();
+    let parsed = parse_scene(&contents, "written.scene", Path::new(".")).unwrap();
+
+    assert_eq!(parsed.objects.len(), scene.objects.len());
+    assert!(contents.lines().any(|line| line.starts_with("cube ")));
+    assert_eq!(parsed.camera.look_from, scene.camera.look_from);
+    assert_eq!(parsed.camera.focus_dist, scene.camera.focus_dist);
+    assert_eq!(parsed.settings.img_height, scene.settings.img_height);
+    assert_eq!(parsed.settings.output, "random field.png");
+    assert_eq!(parsed.settings.russian_roulette, Some(5));
+    assert_eq!(parsed.settings.transmission_depth, Some(3));
+    assert_eq!(parsed.settings.display, scene.settings.display);
+    assert_eq!(scene_to_string(&parsed).unwrap(), contents);
+}
+
+#[test]
+fn transformed_objects_are_written_with_their_matrix() {
+    use crate::hittable::transformed::Transformed;
+    use crate::scene::parser::parse_scene;
+
+    let mut scene = random_field_scene();
+    let transform = Mat4::translation(Vec3::new(1.0, 2.0, 3.0)) * Mat4::rotation_y(0.3);
+    let cube = scene.objects.remove(0);
+    let inner = Transformed::new(cube, Mat4::scaling(Vec3::new(1.0, 2.0, 1.0))).unwrap();
+    scene.objects.push(Box::new(
+        Transformed::new(Box::new(inner), transform).unwrap(),
+    ));
+
+    let contents = scene_to_string(&scene).unwrap();
+    let composed = transform * Mat4::scaling(Vec3::new(1.0, 2.0, 1.0));
+    let statement = format!("matrix={}", format_matrix(&composed));
+    assert_eq!(
+        contents
+            .lines()
+            .filter(|line| line.ends_with(&statement))
+            .count(),
+        1
+    );
+    let parsed = parse_scene(&contents, "written.scene", Path::new(".")).unwrap();
+    assert_eq!(scene_to_string(&parsed).unwrap(), contents);
+}
+
+#[test]
+fn media_are_written_with_their_density() {
+    use crate::hittable::constant_medium::ConstantMedium;
+    use crate::material::isotropic::Isotropic;
+    use crate::scene::parser::parse_scene;
+
+    let mut scene = random_field_scene();
+    let boundary = scene.objects.remove(0);
+    let phase: Arc<Box<dyn Material + Send + Sync>> =
+        Arc::new(Box::new(Isotropic::new_from_color(Color::white())));
+    scene
+        .objects
+        .push(Box::new(ConstantMedium::new(boundary, 0.5, phase)));
+
+    let contents = scene_to_string(&scene).unwrap();
+    assert!(contents
+        .lines()
+        .any(|line| line.starts_with("material ") && line.ends_with("isotropic color=1,1,1")));
+    assert_eq!(
+        contents
+            .lines()
+            .filter(|line| line.contains(" density=0.5 phase="))
+            .count(),
+        1
+    );
+    let parsed = parse_scene(&contents, "written.scene", Path::new(".")).unwrap();
+    assert_eq!(scene_to_string(&parsed).unwrap(), contents);
+}
+
+#[test]
+fn shared_materials_are_written_once() {
+    let contents = scene_to_string(&random_field_scene()).unwrap();
+    let lines = contents.lines().collect::<Vec<&str>>();
+
+    // Three of the ten objects share the glass material.
+    let glass = lines
+        .iter()
+        .filter(|line| line.starts_with("material ") && line.contains("dielectric"))
+        .collect::<Vec<&&str>>();
+    assert_eq!(glass.len(), 1);
+    let name = glass[0].split(' ').nth(1).unwrap();
+    let references = lines
+        .iter()
+        .filter(|line| line.ends_with(&format!("material={}", name)))
+        .count();
+    assert_eq!(references, 3);
+}
+
+#[test]
+fn quote_strings_with_spaces() {
+    assert_eq!(format_string("a.png").unwrap(), "a.png");
+    assert_eq!(format_string("my render.png").unwrap(), "\"my render.png\"");
+    assert_eq!(format_string("").unwrap(), "\"\"");
+    assert!(format_string("say \"cheese\".png").is_err());
+    assert!(format_string("a\"b.png").is_err());
+}
+
+#[test]
+fn unknown_types_are_errors() {
+    use crate::geometry::ray::Ray;
+    use crate::hittable::hittable::HitRecord;
+    use crate::material::material::ScatterResult;
+
+    struct Glow;
+    impl Material for Glow {
+        fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord) -> ScatterResult {
+            ScatterResult {
+                scattered_ray: None,
+                attenuation: Color::black(),
+                emitted: Color::white(),
+            }
+        }
+    }
+
+    let error = SceneWriter::new().material_definition(&Glow).unwrap_err();
+    assert_eq!(error, "scene files have no statement for this material");
+}
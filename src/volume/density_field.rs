@@ -0,0 +1,13 @@
+use crate::geometry::vec3::Vec3;
+use std::any::Any;
+
+// A scalar field over the unit cube [0, 1]^3, the density or emission of a heterogeneous medium.
+// `Any` lets the scene writer find the concrete type behind a trait object.
+pub trait DensityField: Any {
+    // Zero outside of the unit cube.
+    fn density(&self, p: &Vec3) -> f64;
+
+    // An upper bound of `density`, the majorant used to track rays through the field. The tighter
+    // it is the fewer lookups tracking needs.
+    fn max_density(&self) -> f64;
+}
@@ -0,0 +1,3 @@
+pub mod density_field;
+pub mod noise;
+pub mod voxel_grid;
@@ -0,0 +1,152 @@
+use crate::geometry::vec3::{dot, Vec3};
+use crate::scene::writer::{SceneDefinition, SceneWriter};
+use crate::volume::density_field::DensityField;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+const POINT_COUNT: usize = 256;
+
+// Perlin gradient noise, smooth values in about [-1, 1] that vary over distances of about 1. The
+// same seed always gives the same noise.
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    permutations: [Vec<usize>; 3],
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| loop {
+                let v = Vec3::new(
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                    rng.gen_range(-1.0..1.0),
+                );
+                let len_squared = v.len_squared();
+                if len_squared > 1e-6 && len_squared <= 1.0 {
+                    break v.normalize();
+                }
+            })
+            .collect();
+        let mut permutation = || {
+            let mut p = (0..POINT_COUNT).collect::<Vec<usize>>();
+            p.shuffle(&mut rng);
+            p
+        };
+        let permutations = [permutation(), permutation(), permutation()];
+        Perlin {
+            gradients,
+            permutations,
+        }
+    }
+
+    pub fn noise(&self, p: &Vec3) -> f64 {
+        let floor = p.map(f64::floor);
+        let fraction = p - floor;
+        // Hermite smoothing hides the grid.
+        let smooth = fraction.map(|f| f * f * (3.0 - 2.0 * f));
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut index = 0;
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                let i = (floor[axis] as i64 + offset[axis] as i64) & (POINT_COUNT as i64 - 1);
+                index ^= self.permutations[axis][i as usize];
+                weight *= if offset[axis] == 1 {
+                    smooth[axis]
+                } else {
+                    1.0 - smooth[axis]
+                };
+            }
+            let to_point =
+                fraction - Vec3::new(offset[0] as f64, offset[1] as f64, offset[2] as f64);
+            value += weight * dot(&self.gradients[index], &to_point);
+        }
+        value
+    }
+
+    // Fractal sum of `octaves` layers of noise, each twice the frequency and half the weight of
+    // the one before.
+    pub fn fractal(&self, p: &Vec3, octaves: u32) -> f64 {
+        let mut value = 0.0;
+        let mut point = *p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            value += weight * self.noise(&point);
+            point *= 2.0;
+            weight *= 0.5;
+        }
+        value
+    }
+}
+
+// A cloud filling the unit cube: a ball of density fading towards the faces of the cube, its
+// edges torn up by fractal noise. Densities are in [0, 1].
+pub struct NoiseField {
+    perlin: Perlin,
+    seed: u64,
+    frequency: f64,
+    octaves: u32,
+}
+
+impl NoiseField {
+    pub fn new(seed: u64, frequency: f64, octaves: u32) -> NoiseField {
+        NoiseField {
+            perlin: Perlin::new(seed),
+            seed,
+            frequency,
+            octaves,
+        }
+    }
+}
+
+impl DensityField for NoiseField {
+    fn density(&self, p: &Vec3) -> f64 {
+        if p.as_slice().iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return 0.0;
+        }
+        let center = Vec3::new(0.5, 0.5, 0.5);
+        let falloff = 1.0 - 2.0 * (p - center).len();
+        (falloff + self.perlin.fractal(&(p * self.frequency), self.octaves)).clamp(0.0, 1.0)
+    }
+
+    fn max_density(&self) -> f64 {
+        1.0
+    }
+}
+
+impl SceneDefinition for NoiseField {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        Ok(format!(
+            "noise={} frequency={} octaves={}",
+            self.seed, self.frequency, self.octaves
+        ))
+    }
+}
+
+#[test]
+fn noise_is_smooth_and_repeatable() {
+    let a = Perlin::new(7);
+    let b = Perlin::new(7);
+    let p = Vec3::new(1.3, -2.7, 0.4);
+    assert_eq!(a.noise(&p), b.noise(&p));
+    assert_ne!(a.noise(&p), Perlin::new(8).noise(&p));
+
+    // Zero at the lattice points, small steps give small changes.
+    assert!(a.noise(&Vec3::new(3.0, -1.0, 2.0)).abs() < 1e-12);
+    for i in 0..100 {
+        let p = Vec3::new(i as f64 * 0.37, i as f64 * -0.11, i as f64 * 0.05);
+        let step = Vec3::new(1e-4, 1e-4, 1e-4);
+        assert!(a.noise(&p).abs() <= 1.5);
+        assert!((a.noise(&p) - a.noise(&(p + step))).abs() < 1e-2);
+    }
+
+    let field = NoiseField::new(7, 4.0, 3);
+    assert_eq!(field.density(&Vec3::new(0.0, 0.0, 0.0)), 0.0);
+    assert!(field.density(&Vec3::new(0.5, 0.5, 0.5)) > 0.0);
+    assert_eq!(field.density(&Vec3::new(-0.5, 0.5, 0.5)), 0.0);
+}
@@ -0,0 +1,198 @@
+use crate::geometry::vec3::Vec3;
+use crate::scene::writer::{format_path, SceneDefinition, SceneWriter};
+use crate::volume::density_field::DensityField;
+use std::fs;
+
+// Dense grid of voxel values filling the unit cube, sampled with trilinear interpolation between
+// the voxel centers.
+//
+// Files start with an ASCII header line holding the dimensions, `nx ny nz`, followed by
+// nx * ny * nz little endian 32 bit floats with x varying fastest, then y, then z.
+pub struct VoxelGrid {
+    dimensions: [usize; 3],
+    values: Vec<f32>,
+    max_value: f64,
+    // Set when loaded from a file, so the grid can be written to scene files.
+    path: Option<String>,
+}
+
+impl VoxelGrid {
+    pub fn new(dimensions: [usize; 3], values: Vec<f32>) -> Result<VoxelGrid, String> {
+        if dimensions.contains(&0) {
+            return Err(String::from("voxel grid dimensions must be positive"));
+        }
+        let expected = dimensions
+            .iter()
+            .try_fold(1usize, |count, d| count.checked_mul(*d))
+            .ok_or_else(|| String::from("voxel grid dimensions are too large"))?;
+        if values.len() != expected {
+            return Err(format!(
+                "expected {} voxel values, got {}",
+                expected,
+                values.len()
+            ));
+        }
+        if values.iter().any(|v| !v.is_finite() || *v < 0.0) {
+            return Err(String::from("voxel values must be finite and not negative"));
+        }
+        let max_value = values.iter().fold(0.0f32, |max, v| max.max(*v)) as f64;
+        Ok(VoxelGrid {
+            dimensions,
+            values,
+            max_value,
+            path: None,
+        })
+    }
+
+    pub fn load(path: &str) -> Result<VoxelGrid, String> {
+        let bytes = fs::read(path).map_err(|e| format!("Could not read {}: {}", path, e))?;
+        let mut grid = VoxelGrid::parse(&bytes).map_err(|e| format!("{}: {}", path, e))?;
+        grid.path = Some(String::from(path));
+        Ok(grid)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<VoxelGrid, String> {
+        let header_end = bytes
+            .iter()
+            .position(|b| *b == b'\n')
+            .ok_or_else(|| String::from("missing the dimensions header"))?;
+        let header = std::str::from_utf8(&bytes[..header_end])
+            .map_err(|_| String::from("the dimensions header is not text"))?;
+        let dimensions = header
+            .split_whitespace()
+            .map(|d| d.parse::<usize>())
+            .collect::<Result<Vec<usize>, _>>()
+            .ok()
+            .filter(|d| d.len() == 3)
+            .ok_or_else(|| format!("expected 'nx ny nz' as the header, got '{}'", header))?;
+
+        let data = &bytes[header_end + 1..];
+        if !data.len().is_multiple_of(4) {
+            return Err(String::from(
+                "the voxel data is not a whole number of floats",
+            ));
+        }
+        let values = data
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        VoxelGrid::new([dimensions[0], dimensions[1], dimensions[2]], values)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let [nx, ny, nz] = self.dimensions;
+        let mut bytes = format!("{} {} {}\n", nx, ny, nz).into_bytes();
+        for value in self.values.iter() {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        fs::write(path, bytes).map_err(|e| format!("Could not write {}: {}", path, e))
+    }
+
+    pub fn dimensions(&self) -> [usize; 3] {
+        self.dimensions
+    }
+
+    pub fn path(&self) -> Option<&str> {
+        self.path.as_deref()
+    }
+
+    // The value of voxel (x, y, z), coordinates past the edges are clamped to it.
+    pub fn voxel(&self, x: isize, y: isize, z: isize) -> f64 {
+        let clamp = |i: isize, n: usize| i.clamp(0, n as isize - 1) as usize;
+        let [nx, ny, nz] = self.dimensions;
+        let (x, y, z) = (clamp(x, nx), clamp(y, ny), clamp(z, nz));
+        self.values[x + nx * (y + ny * z)] as f64
+    }
+}
+
+impl DensityField for VoxelGrid {
+    fn density(&self, p: &Vec3) -> f64 {
+        if p.as_slice().iter().any(|c| !(0.0..=1.0).contains(c)) {
+            return 0.0;
+        }
+        // Position in voxels, voxel i has its center at i + 0.5.
+        let mut cell = [0isize; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let position = p[axis] * self.dimensions[axis] as f64 - 0.5;
+            let floor = position.floor();
+            cell[axis] = floor as isize;
+            fraction[axis] = position - floor;
+        }
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let offset = [corner & 1, (corner >> 1) & 1, (corner >> 2) & 1];
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                weight *= if offset[axis] == 1 {
+                    fraction[axis]
+                } else {
+                    1.0 - fraction[axis]
+                };
+            }
+            value += weight
+                * self.voxel(
+                    cell[0] + offset[0] as isize,
+                    cell[1] + offset[1] as isize,
+                    cell[2] + offset[2] as isize,
+                );
+        }
+        value
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_value
+    }
+}
+
+impl SceneDefinition for VoxelGrid {
+    fn scene_definition(&self, _writer: &mut SceneWriter) -> Result<String, String> {
+        match &self.path {
+            Some(path) => Ok(format!("grid={}", format_path(path)?)),
+            None => Err(String::from(
+                "voxel grids that were not loaded from a file cannot be written to a scene file",
+            )),
+        }
+    }
+}
+
+#[test]
+fn grids_interpolate_between_voxel_centers() {
+    let grid = VoxelGrid::new([2, 1, 1], vec![1.0, 3.0]).unwrap();
+    assert_eq!(grid.density(&Vec3::new(0.25, 0.5, 0.5)), 1.0);
+    assert_eq!(grid.density(&Vec3::new(0.5, 0.5, 0.5)), 2.0);
+    assert!((grid.density(&Vec3::new(0.9, 0.1, 0.9)) - 3.0).abs() < 1e-12);
+    assert_eq!(grid.density(&Vec3::new(1.5, 0.5, 0.5)), 0.0);
+    assert_eq!(grid.max_density(), 3.0);
+
+    assert!(VoxelGrid::new([2, 2, 1], vec![1.0, 3.0]).is_err());
+    assert!(VoxelGrid::new([2, 1, 1], vec![1.0, -3.0]).is_err());
+}
+
+#[test]
+fn grid_files_round_trip() {
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("voxel-grid");
+    let path = dir.join("grid.vox");
+    let path = path.to_str().unwrap();
+    let values = (0..24).map(|i| i as f32 * 0.5).collect::<Vec<f32>>();
+    VoxelGrid::new([2, 3, 4], values.clone())
+        .unwrap()
+        .save(path)
+        .unwrap();
+
+    let grid = VoxelGrid::load(path).unwrap();
+    assert_eq!(grid.dimensions(), [2, 3, 4]);
+    assert_eq!(grid.voxel(1, 2, 3), 11.5);
+    assert_eq!(grid.voxel(1, 0, 1), 3.5);
+    assert_eq!(grid.path(), Some(path));
+
+    let mut truncated = fs::read(path).unwrap();
+    truncated.truncate(truncated.len() - 4);
+    assert!(VoxelGrid::parse(&truncated).is_err());
+    assert!(VoxelGrid::parse(b"2 3\n").is_err());
+    let huge = format!("{} {} 2\n", usize::MAX, usize::MAX);
+    assert!(VoxelGrid::parse(huge.as_bytes()).is_err());
+}
@@ -1,5 +1,7 @@
+use crate::geometry::mat3::Mat3;
 use crate::geometry::ray::Ray;
-use crate::geometry::vec3::{cross, Vec3};
+use crate::geometry::sampling::concentric_disk;
+use crate::geometry::vec3::Vec3;
 use rand::Rng;
 
 pub struct Camera {
@@ -9,9 +11,6 @@ pub struct Camera {
     up: Vec3,
 
     // Viewport Stuff
-    vertical_fov: f64,
-    aspect_ratio: f64,
-    focal_length: f64,
     viewport_width: f64,
     viewport_height: f64,
 
@@ -19,18 +18,55 @@ pub struct Camera {
     aperture: f64,
     focus_dist: f64,
 
+    // Rays are sent at random times between the shutter opening and closing.
+    shutter_open: f64,
+    shutter_close: f64,
+
     // Viewport to Screen stuff
     pub raster_width: u32,
     pub raster_height: u32,
 }
 
+pub struct CameraSettings {
+    pub look_from: Vec3,
+    pub look_at: Vec3,
+    pub vup: Vec3,
+    pub aperture: f64,
+    pub focus_dist: f64,
+    pub vertical_fov: f64,
+    pub shutter_open: f64,
+    pub shutter_close: f64,
+}
+
 pub struct PixelRays {
     pub x: u32,
     pub y: u32,
     pub rays: Vec<Ray>,
 }
 
+impl CameraSettings {
+    pub fn build(&self, raster_width: u32, raster_height: u32) -> Camera {
+        let camera = Camera::camera(
+            self.look_from,
+            self.look_at,
+            self.vup,
+            self.aperture,
+            self.focus_dist,
+            self.vertical_fov,
+            raster_width as f64 / raster_height as f64,
+            raster_width,
+            raster_height,
+        );
+        Camera {
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
+            ..camera
+        }
+    }
+}
+
 impl Camera {
+    #[allow(clippy::too_many_arguments, clippy::self_named_constructors)]
     pub fn camera(
         from: Vec3,
         to: Vec3,
@@ -43,9 +79,8 @@ impl Camera {
         raster_height: u32,
     ) -> Camera {
         // Reference: https://www.scratchapixel.com/lessons/mathematics-physics-for-computer-graphics/lookat-function
-        let forward = (from - to).normalize();
-        let right: Vec3 = cross(&vup, &forward).normalize();
-        let up: Vec3 = cross(&forward, &right).normalize();
+        let basis = Mat3::basis(from - to, vup);
+        let (right, up, forward) = (basis.column(0), basis.column(1), basis.column(2));
 
         let theta = vertical_fov.to_radians();
         let h = (theta * 0.5).tan();
@@ -57,11 +92,10 @@ impl Camera {
             forward,
             right,
             up,
-            vertical_fov,
-            aspect_ratio,
             aperture,
             focus_dist,
-            focal_length: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
             viewport_width,
             viewport_height,
             raster_width,
@@ -94,9 +128,15 @@ impl Camera {
                             / self.raster_height as f64;
                     let destination = viewport_lower_left + (sx * self.right) + (sy * self.up);
 
-                    let rd = (self.aperture * 0.5) * random_in_unit_disk();
-                    let offset = self.right * rd.x() + self.up * rd.y();
-                    rays.push(Ray::from_to(self.position + offset, destination));
+                    let (dx, dy) = concentric_disk(&mut rng);
+                    let offset = (self.aperture * 0.5) * (self.right * dx + self.up * dy);
+                    let origin = self.position + offset;
+                    let time = if self.shutter_close > self.shutter_open {
+                        rng.gen_range(self.shutter_open..self.shutter_close)
+                    } else {
+                        self.shutter_open
+                    };
+                    rays.push(Ray::new_at_time(origin, destination - origin, time));
                 }
                 pixel_rays.push(PixelRays { x, y, rays });
             }
@@ -104,13 +144,3 @@ impl Camera {
         pixel_rays
     }
 }
-
-fn random_in_unit_disk() -> Vec3 {
-    loop {
-        let mut rng = rand::thread_rng();
-        let p = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0);
-        if p.len_squared() < 1.0 {
-            return p;
-        }
-    }
-}
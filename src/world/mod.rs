@@ -1,2 +1,4 @@
 pub mod camera;
+pub mod render_settings;
+#[allow(clippy::module_inception)]
 pub mod world;
@@ -0,0 +1,83 @@
+use crate::material::color::Color;
+use crate::output::display::DisplaySettings;
+
+pub struct RenderSettings {
+    pub img_width: u32,
+    pub img_height: u32,
+    pub samples_per_pixel: u32,
+    pub recursive_depth: u32,
+    // Most bounces of each kind a path takes, None leaves only `recursive_depth` in the way.
+    pub diffuse_depth: Option<u32>,
+    pub glossy_depth: Option<u32>,
+    pub transmission_depth: Option<u32>,
+    // Paths that bounced at least this many times are stopped at random, with a chance that grows
+    // as they carry less light. None follows every path to `recursive_depth`.
+    pub russian_roulette: Option<u32>,
+    pub num_threads: u32,
+    // Maximum number of objects in a leaf of the world's bounding box tree.
+    pub bvh_leaf_size: u32,
+    pub background: Color,
+    pub output: String,
+    // How 8 bit outputs show the light.
+    pub display: DisplaySettings,
+}
+
+impl RenderSettings {
+    pub fn new(
+        aspect_ratio: f64,
+        img_width: u32,
+        background: Color,
+        output: &str,
+    ) -> RenderSettings {
+        RenderSettings {
+            img_width,
+            img_height: (img_width as f64 / aspect_ratio) as u32,
+            samples_per_pixel: 100,
+            recursive_depth: 100,
+            diffuse_depth: None,
+            glossy_depth: None,
+            transmission_depth: None,
+            russian_roulette: None,
+            num_threads: 16,
+            bvh_leaf_size: 4,
+            background,
+            output: String::from(output),
+            display: DisplaySettings::default(),
+        }
+    }
+
+    pub fn aspect_ratio(&self) -> f64 {
+        self.img_width as f64 / self.img_height as f64
+    }
+
+    // Sets the resolution, deriving a missing width or height from the aspect ratio. The aspect
+    // ratio defaults to the current one. Fails without changing anything when a derived side
+    // would be less than a pixel.
+    pub fn resize(
+        &mut self,
+        width: Option<u32>,
+        height: Option<u32>,
+        aspect_ratio: Option<f64>,
+    ) -> Result<(), String> {
+        let aspect_ratio = aspect_ratio.unwrap_or_else(|| self.aspect_ratio());
+
+        let (img_width, img_height) = match (width, height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, (width as f64 / aspect_ratio) as u32),
+            (None, Some(height)) => ((height as f64 * aspect_ratio) as u32, height),
+            (None, None) => (
+                self.img_width,
+                (self.img_width as f64 / aspect_ratio) as u32,
+            ),
+        };
+        if img_width == 0 || img_height == 0 {
+            return Err(format!(
+                "an aspect ratio of {} makes the image {}x{}, it needs at least one pixel each way",
+                aspect_ratio, img_width, img_height
+            ));
+        }
+        self.img_width = img_width;
+        self.img_height = img_height;
+        Ok(())
+    }
+}
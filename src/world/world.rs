@@ -1,51 +1,111 @@
 use crate::geometry::ray::Ray;
-use crate::hittable::bounding_box_tree::BoundingBoxTree;
+use crate::hittable::bounding_box_tree::{BoundingBoxTree, TreeStats};
 use crate::hittable::hittable::{HitRecord, Hittable};
+use crate::integrator::integrator::Integrator;
+use crate::integrator::path_tracer::PathTracer;
 use crate::material::color::Color;
+use crate::output::framebuffer::Framebuffer;
 use crate::world::camera::{Camera, PixelRays};
-use image::ImageBuffer;
-use std::rc::Rc;
+use crate::world::render_settings::RenderSettings;
 use std::sync::{Arc, Mutex};
 use std::thread;
 
 pub struct World {
     bounding_box_tree: BoundingBoxTree,
+    // The objects that are lights, also in the tree.
+    lights: Vec<Arc<Box<dyn Hittable + Send + Sync>>>,
 }
 
 impl World {
     pub fn new(objects: Vec<Box<dyn Hittable + Send + Sync>>) -> World {
+        World::new_with_leaf_size(objects, 4)
+    }
+
+    pub fn new_with_leaf_size(
+        objects: Vec<Box<dyn Hittable + Send + Sync>>,
+        leaf_size: usize,
+    ) -> World {
         let mut nobjects = vec![];
         for object in objects {
             nobjects.push(Arc::new(object));
         }
 
+        let lights = nobjects
+            .iter()
+            .filter(|object| object.is_light())
+            .map(Arc::clone)
+            .collect();
+
         World {
-            bounding_box_tree: BoundingBoxTree::new(&*nobjects, 5),
+            bounding_box_tree: BoundingBoxTree::new(&nobjects, leaf_size),
+            lights,
         }
     }
+
+    pub fn lights(&self) -> &[Arc<Box<dyn Hittable + Send + Sync>>] {
+        &self.lights
+    }
+
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord<'_>> {
+        self.bounding_box_tree.hit(ray, t_min, t_max)
+    }
+
+    // Like `hit`, with the index of the object that was hit for `light_pdf`.
+    pub fn hit_object(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<(usize, HitRecord<'_>)> {
+        self.bounding_box_tree.hit_object(ray, t_min, t_max)
+    }
+
+    // The fraction of light getting through everything along the ray between `t_min` and `t_max`.
+    pub fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64) -> f64 {
+        self.bounding_box_tree.transmittance(ray, t_min, t_max)
+    }
+
+    // The density of light sampling picking the point where the ray hits `object`, an index from
+    // `hit_object`. None when the object isn't a registered light.
+    pub fn light_pdf(&self, ray: &Ray, object: usize) -> Option<f64> {
+        let light = self.bounding_box_tree.object(object);
+        if !light.is_light() {
+            return None;
+        }
+        Some(light.light_pdf(ray.origin(), ray.direction()) / self.lights.len() as f64)
+    }
+
+    pub fn stats(&self) -> TreeStats {
+        self.bounding_box_tree.stats()
+    }
 }
 
+// Renders the image, writes it to the settings' output and returns the linear pixels.
 pub fn render(
     world: World,
-    filename: &str,
     camera: &Camera,
-    samples_per_pixel: u32,
-    recursive_depth: u32,
-    num_threads: u32,
-    background_color: Color,
-) {
-    let img_buf = ImageBuffer::new(camera.raster_width, camera.raster_height);
+    settings: &RenderSettings,
+) -> Result<Framebuffer, String> {
+    render_with_integrator(world, camera, settings, Arc::new(PathTracer::new(settings)))
+}
+
+pub fn render_with_integrator(
+    world: World,
+    camera: &Camera,
+    settings: &RenderSettings,
+    integrator: Arc<dyn Integrator + Send + Sync>,
+) -> Result<Framebuffer, String> {
+    let samples_per_pixel = settings.samples_per_pixel;
+    let num_threads = settings.num_threads;
+
+    let framebuffer = Framebuffer::new(camera.raster_width, camera.raster_height);
     let pixel_rays: Vec<PixelRays> = camera.get_rays(samples_per_pixel);
 
-    let img_buf_mutex = Arc::new(Mutex::new(img_buf));
+    let framebuffer_mutex = Arc::new(Mutex::new(framebuffer));
     let pixel_rays_mutex = Arc::new(Mutex::new(pixel_rays));
-    let objects_arc = Arc::new(world.bounding_box_tree);
+    let objects_arc = Arc::new(world);
 
     let mut handlers = vec![];
     for _ in 0..num_threads {
-        let img_buf_thread_copy = Arc::clone(&img_buf_mutex);
+        let framebuffer_thread_copy = Arc::clone(&framebuffer_mutex);
         let pixel_rays_thread_copy = Arc::clone(&pixel_rays_mutex);
         let objects_copy = Arc::clone(&objects_arc);
+        let integrator_copy = Arc::clone(&integrator);
 
         let handle = thread::spawn(move || loop {
             let pixel_rays;
@@ -56,70 +116,145 @@ pub fn render(
                 }
                 pixel_rays = pixel_rays_queue.pop().unwrap();
             }
-            let color = get_pixel_color(
-                &*objects_copy,
-                &pixel_rays,
-                recursive_depth,
-                background_color,
-            );
+            let color = get_pixel_color(&objects_copy, &pixel_rays, integrator_copy.as_ref());
             {
-                let mut img_buf = img_buf_thread_copy.lock().unwrap();
-                img_buf.put_pixel(pixel_rays.x, pixel_rays.y, color.image_pixel());
+                let mut framebuffer = framebuffer_thread_copy.lock().unwrap();
+                framebuffer.set_pixel(pixel_rays.x, pixel_rays.y, color);
             }
         });
-
-        img_buf_mutex
-            .lock()
-            .unwrap()
-            .put_pixel(0, 0, Color::white().image_pixel());
         handlers.push(handle);
     }
 
     for handler in handlers {
-        handler.join().unwrap();
+        handler
+            .join()
+            .map_err(|_| String::from("A render thread panicked"))?;
     }
 
-    img_buf_mutex.lock().unwrap().save(filename).unwrap();
+    // Every thread has finished, so nothing else holds the framebuffer.
+    let framebuffer = Arc::try_unwrap(framebuffer_mutex)
+        .ok()
+        .and_then(|mutex| mutex.into_inner().ok())
+        .ok_or_else(|| String::from("The framebuffer is still in use after rendering"))?;
+    framebuffer.save(&settings.output, &settings.display)?;
+    Ok(framebuffer)
 }
 
 fn get_pixel_color(
-    objects: &BoundingBoxTree,
+    world: &World,
     pixel_ray: &PixelRays,
-    recursive_depth: u32,
-    background: Color,
+    integrator: &(dyn Integrator + Send + Sync),
 ) -> Color {
     let mut sampled_colors: Vec<Color> = vec![];
     for ray in pixel_ray.rays.iter() {
-        sampled_colors.push(ray_color(objects, &ray, recursive_depth, background));
+        let sample = integrator.radiance(world, ray);
+        // A single broken sample would spoil the whole pixel.
+        if sample.is_finite() {
+            sampled_colors.push(sample);
+        } else {
+            sampled_colors.push(Color::black());
+        }
     }
-    Color::average_color(sampled_colors.iter()).gamma_corrected()
+    Color::average_color(sampled_colors.iter())
+}
+
+#[test]
+fn emissive_objects_are_registered_as_lights() {
+    use crate::geometry::vec3::Vec3;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffuse_light::DiffuseLight;
+    use crate::material::lambertian::Lambertian;
+
+    let sphere = |x: f64, material: Box<dyn crate::material::material::Material + Send + Sync>| {
+        Box::new(Sphere {
+            center: Vec3::new(x, 0.0, 0.0),
+            radius: 0.5,
+            material: Arc::new(material),
+        })
+    };
+    let world = World::new(vec![
+        sphere(0.0, Box::new(Lambertian::new_from_color(Color::white()))),
+        sphere(2.0, Box::new(DiffuseLight::new(Color::white()))),
+    ]);
+    assert_eq!(world.lights().len(), 1);
+    assert_eq!(
+        world.lights()[0].get_bounding_box().min_point,
+        Vec3::new(1.5, -0.5, -0.5)
+    );
+
+    // Only hits on lights have a light sampling density.
+    let direction = Vec3::new(0.0, 0.0, -1.0);
+    let ray = Ray::new(Vec3::new(2.0, 0.0, 5.0), direction);
+    let (object, hit) = world.hit_object(&ray, 0.0001, f64::MAX).unwrap();
+    assert!((hit.t - 4.5).abs() < 1e-9);
+    assert_eq!(
+        world.light_pdf(&ray, object),
+        Some(world.lights()[0].light_pdf(ray.origin(), &direction))
+    );
+    let ray = Ray::new(Vec3::new(0.0, 0.0, 5.0), direction);
+    let (object, _) = world.hit_object(&ray, 0.0001, f64::MAX).unwrap();
+    assert_eq!(world.light_pdf(&ray, object), None);
 }
 
-fn ray_color(objects: &BoundingBoxTree, ray: &Ray, depth: u32, background: Color) -> Color {
-    if depth <= 0 {
-        return background;
+// A pinhole camera at the origin with a 90 degree view down -z.
+#[cfg(test)]
+fn looking_down_z(settings: &RenderSettings) -> Camera {
+    use crate::geometry::vec3::Vec3;
+    use crate::world::camera::CameraSettings;
+
+    CameraSettings {
+        look_from: Vec3::origin(),
+        look_at: Vec3::new(0.0, 0.0, -1.0),
+        vup: Vec3::new(0.0, 1.0, 0.0),
+        aperture: 0.0,
+        focus_dist: 1.0,
+        vertical_fov: 90.0,
+        shutter_open: 0.0,
+        shutter_close: 0.0,
     }
-    let mut nearest_hit_record: Option<HitRecord> = objects.hit(ray, 0.0, f64::MAX);
-
-    if nearest_hit_record.is_some() {
-        let nearest_hit_record = nearest_hit_record.unwrap();
-        let scatter_result = nearest_hit_record
-            .material
-            .scatter(ray, &nearest_hit_record);
-
-        return if scatter_result.scattered_ray.is_some() {
-            scatter_result.emitted
-                + ray_color(
-                    objects,
-                    &scatter_result.scattered_ray.unwrap(),
-                    depth - 1,
-                    background,
-                )
-                .attenuate(scatter_result.attenuation)
-        } else {
-            scatter_result.emitted
-        };
-    } else {
-        background
+    .build(settings.img_width, settings.img_height)
+}
+
+#[test]
+fn render_runs_the_given_integrator() {
+    use crate::testing::TempDir;
+
+    // Lights the pixels by the height their rays point at.
+    struct Height;
+    impl Integrator for Height {
+        fn radiance(&self, _world: &World, ray: &Ray) -> Color {
+            let up = if ray.direction().y() > 0.0 { 1.0 } else { 0.0 };
+            Color::new(up, up, up)
+        }
+    }
+
+    let dir = TempDir::new("integrator");
+    let path = dir.join("render.png");
+    let mut settings = RenderSettings::new(1.0, 4, Color::black(), path.to_str().unwrap());
+    settings.samples_per_pixel = 2;
+    settings.num_threads = 2;
+    let camera = looking_down_z(&settings);
+    render_with_integrator(World::new(vec![]), &camera, &settings, Arc::new(Height)).unwrap();
+
+    let image = image::open(&path).unwrap().to_rgb8();
+    for (_, y, pixel) in image.enumerate_pixels() {
+        let expected = if y < 2 { 255 } else { 0 };
+        assert_eq!(pixel.0, [expected; 3], "row {}", y);
     }
 }
+
+#[test]
+fn unsupported_outputs_are_errors() {
+    use crate::testing::TempDir;
+
+    let dir = TempDir::new("render");
+    let path = dir.join("render.xyz");
+    let mut settings = RenderSettings::new(1.0, 2, Color::black(), path.to_str().unwrap());
+    settings.samples_per_pixel = 1;
+    let camera = looking_down_z(&settings);
+
+    let error = render(World::new(vec![]), &camera, &settings)
+        .err()
+        .unwrap();
+    assert!(error.contains("render.xyz"), "{}", error);
+}